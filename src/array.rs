@@ -0,0 +1,402 @@
+use std::{cell::RefCell, cmp::Ordering, fmt::Display, rc::Rc};
+
+use crate::{
+    error_codes,
+    executer::{self, RuntimeError, Value},
+    parser::{Number, NumberType, Type},
+};
+
+/// The value of a `Vec<T>`.
+///
+/// Like objects, arrays are shared between the variables holding them, so a method changing the
+/// elements is seen through all of them.
+#[derive(Debug, Clone)]
+pub struct Array {
+    element_type: Type,
+    elements: Rc<RefCell<Vec<Value>>>,
+}
+
+impl Array {
+    /// Makes an array of `elements`, which must all be values of `element_type`.
+    pub fn new(element_type: Type, elements: Vec<Value>) -> Self {
+        Array {
+            element_type,
+            elements: Rc::new(RefCell::new(elements)),
+        }
+    }
+
+    /// Makes an array of `element_type` from `values`, converting each of them.
+    pub(crate) fn collect(element_type: Type, values: Vec<Value>) -> Result<Self, RuntimeError> {
+        let array = Array::new(element_type, Vec::with_capacity(values.len()));
+        for value in values {
+            let value = array.convert_element(value)?;
+            array.elements.borrow_mut().push(value);
+        }
+        Ok(array)
+    }
+
+    pub fn element_type(&self) -> &Type {
+        &self.element_type
+    }
+
+    pub fn value_type(&self) -> Type {
+        Type::Vec(Box::new(self.element_type.clone()))
+    }
+
+    pub fn len(&self) -> usize {
+        self.elements.borrow().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.elements.borrow().is_empty()
+    }
+
+    /// Returns a copy of the elements, which aren't affected by later changes to the array.
+    pub fn to_vec(&self) -> Vec<Value> {
+        self.elements.borrow().clone()
+    }
+
+    /// Returns how many bytes the array takes on the heap, including its elements.
+    pub fn heap_size(&self) -> usize {
+        let elements = self.elements.borrow();
+        elements.capacity() * std::mem::size_of::<Value>()
+            + elements.iter().map(Value::heap_size).sum::<usize>()
+    }
+
+    /// Converts the array to an array of `element_type`, sharing it if it already is one.
+    /// Otherwise the elements are converted one by one, and the conversion fails if one of them
+    /// doesn't fit.
+    pub(crate) fn convert_to(self, element_type: Type) -> Option<Array> {
+        if self.element_type == element_type {
+            return Some(self);
+        }
+        let elements = self
+            .to_vec()
+            .into_iter()
+            .map(|element| element.convert_to(element_type.clone()))
+            .collect::<Option<Vec<_>>>()?;
+        Some(Array::new(element_type, elements))
+    }
+
+    /// Returns the element at `index`, which must be an integer.
+    pub(crate) fn get(&self, index: &Value) -> Result<Value, RuntimeError> {
+        let position = self.position(index, self.len())?;
+        Ok(self.elements.borrow()[position].clone())
+    }
+
+    /// Replaces the element at `index` by `value`, converted to the type of the elements.
+    pub(crate) fn set(&self, index: &Value, value: Value) -> Result<(), RuntimeError> {
+        let position = self.position(index, self.len())?;
+        let value = self.convert_element(value)?;
+        self.elements.borrow_mut()[position] = value;
+        Ok(())
+    }
+
+    /// Calls the method `name` with `arguments`. `map` and `filter` take a lambda, and are run
+    /// by the engines instead when they are given one.
+    pub(crate) fn call_method(
+        &self,
+        name: &str,
+        arguments: Vec<Value>,
+    ) -> Result<Value, RuntimeError> {
+        if LAMBDA_METHODS.contains(&name) {
+            return Err(expected_lambda(name, arguments.len()));
+        }
+        let method = METHODS.iter().find(|(method, _)| *method == name);
+        let Some((_, parameters)) = method.copied() else {
+            return Err(executer::unknown_member(&self.value_type(), "method", name));
+        };
+        if arguments.len() != parameters {
+            return Err(wrong_argument_count(name, parameters, arguments.len()));
+        }
+        let mut arguments = arguments.into_iter();
+        let mut argument = || arguments.next().expect("The arguments were counted");
+        match name {
+            "push" => {
+                let value = self.convert_element(argument())?;
+                self.elements.borrow_mut().push(value);
+                Ok(Value::Vec(self.clone()))
+            }
+            "pop" => self
+                .elements
+                .borrow_mut()
+                .pop()
+                .ok_or_else(|| RuntimeError::IndexError {
+                    code: &error_codes::INDEX_OUT_OF_BOUNDS,
+                    message: format!("Cannot pop from an empty `{}`.", self.value_type()),
+                }),
+            "len" => Ok(Value::Number(Number::Int64(self.len() as i64))),
+            "insert" => {
+                // An element can be inserted after the last one.
+                let position = self.position(&argument(), self.len() + 1)?;
+                let value = self.convert_element(argument())?;
+                self.elements.borrow_mut().insert(position, value);
+                Ok(Value::Vec(self.clone()))
+            }
+            "remove" => {
+                let position = self.position(&argument(), self.len())?;
+                Ok(self.elements.borrow_mut().remove(position))
+            }
+            "contains" => {
+                // A value which can't be converted to the type of the elements isn't one of them.
+                let found = argument()
+                    .convert_to(self.element_type.clone())
+                    .is_some_and(|value| self.elements.borrow().iter().any(|e| equal(e, &value)));
+                Ok(Value::Bool(found))
+            }
+            "sort" => {
+                if !is_ordered(&self.element_type) {
+                    return Err(unordered(&self.element_type));
+                }
+                self.elements.borrow_mut().sort_by(compare);
+                Ok(Value::Vec(self.clone()))
+            }
+            "reverse" => {
+                self.elements.borrow_mut().reverse();
+                Ok(Value::Vec(self.clone()))
+            }
+            "join" => {
+                let separator = match argument() {
+                    Value::String(separator) => separator,
+                    value => {
+                        return Err(RuntimeError::TypeError {
+                            code: &error_codes::MISMATCHED_TYPES,
+                            message: format!(
+                                "Argument 1 of `join` should be a `String`, found {value} of type `{}`.",
+                                value.value_type()
+                            ),
+                        })
+                    }
+                };
+                if !is_joinable(&self.element_type) {
+                    return Err(unjoinable(&self.element_type));
+                }
+                let parts = self.elements.borrow().iter().map(text).collect::<Vec<_>>();
+                Ok(Value::String(parts.join(&separator)))
+            }
+            _ => unreachable!("Every method in `METHODS` is handled."),
+        }
+    }
+
+    fn convert_element(&self, value: Value) -> Result<Value, RuntimeError> {
+        value
+            .clone()
+            .convert_to(self.element_type.clone())
+            .ok_or_else(|| RuntimeError::TypeError {
+                code: &error_codes::MISMATCHED_TYPES,
+                message: format!(
+                    "Cannot store {value} of type `{}` in a `{}`.",
+                    value.value_type(),
+                    self.value_type()
+                ),
+            })
+    }
+
+    /// Returns the position `index` refers to, if it is an integer lower than `end`.
+    fn position(&self, index: &Value, end: usize) -> Result<usize, RuntimeError> {
+        let Value::Number(number) = index else {
+            return Err(not_an_index(index));
+        };
+        if number.number_type().is_floating_point() || number.number_type() == NumberType::Decimal {
+            return Err(not_an_index(index));
+        }
+        number
+            .convert_lossless(NumberType::UInt64)
+            .and_then(|position| usize::try_from(position.as_u128()).ok())
+            .filter(|position| *position < end)
+            .ok_or_else(|| RuntimeError::IndexError {
+                code: &error_codes::INDEX_OUT_OF_BOUNDS,
+                message: format!(
+                    "Index {number} is out of bounds of a `{}` of length {}.",
+                    self.value_type(),
+                    self.len()
+                ),
+            })
+    }
+}
+
+impl Display for Array {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("[")?;
+        for (index, element) in self.elements.borrow().iter().enumerate() {
+            if index > 0 {
+                f.write_str(", ")?;
+            }
+            write!(f, "{element}")?;
+        }
+        f.write_str("]")
+    }
+}
+
+/// The methods of `Vec` taking values rather than a lambda, with how many arguments they take.
+const METHODS: &[(&str, usize)] = &[
+    ("push", 1),
+    ("pop", 0),
+    ("len", 0),
+    ("insert", 2),
+    ("remove", 1),
+    ("contains", 1),
+    ("sort", 0),
+    ("reverse", 0),
+    ("join", 1),
+];
+
+/// The methods of `Vec` taking a lambda, which is called with each element.
+pub const LAMBDA_METHODS: &[&str] = &["map", "filter"];
+
+/// Returns how many arguments the method `name` of `Vec` takes, if it has one.
+pub(crate) fn method_parameters(name: &str) -> Option<usize> {
+    if LAMBDA_METHODS.contains(&name) {
+        return Some(1);
+    }
+    METHODS
+        .iter()
+        .find(|(method, _)| *method == name)
+        .map(|(_, parameters)| *parameters)
+}
+
+/// Every method of `Vec`.
+pub fn method_names() -> impl Iterator<Item = &'static str> {
+    METHODS
+        .iter()
+        .map(|(name, _)| *name)
+        .chain(LAMBDA_METHODS.iter().copied())
+}
+
+pub(crate) fn wrong_argument_count(method: &str, parameters: usize, given: usize) -> RuntimeError {
+    let plural = if parameters == 1 { "" } else { "s" };
+    let verb = if given == 1 { "was" } else { "were" };
+    RuntimeError::TypeError {
+        code: &error_codes::WRONG_ARGUMENT_COUNT,
+        message: format!(
+            "`{method}` takes {parameters} argument{plural} but {given} {verb} given."
+        ),
+    }
+}
+
+/// Returns the error for calling `map` or `filter` with `given` arguments which aren't a lambda.
+pub(crate) fn expected_lambda(method: &str, given: usize) -> RuntimeError {
+    if given != 1 {
+        return wrong_argument_count(method, 1, given);
+    }
+    RuntimeError::TypeError {
+        code: &error_codes::MISMATCHED_TYPES,
+        message: format!("The argument of `{method}` should be a lambda like `x => x`."),
+    }
+}
+
+/// Returns whether the lambda of `filter` kept an element, from the `bool` it returned.
+pub(crate) fn filter_result(value: Value) -> Result<bool, RuntimeError> {
+    match value {
+        Value::Bool(kept) => Ok(kept),
+        value => Err(RuntimeError::TypeError {
+            code: &error_codes::MISMATCHED_TYPES,
+            message: format!(
+                "The lambda of `filter` should return a `bool`, found {value} of type `{}`.",
+                value.value_type()
+            ),
+        }),
+    }
+}
+
+fn not_an_index(index: &Value) -> RuntimeError {
+    RuntimeError::TypeError {
+        code: &error_codes::MISMATCHED_TYPES,
+        message: format!(
+            "An index must be an integer, found {index} of type `{}`.",
+            index.value_type()
+        ),
+    }
+}
+
+/// Whether values of `element_type` can be compared, to sort them.
+pub(crate) fn is_ordered(element_type: &Type) -> bool {
+    match element_type {
+        Type::Number(_) | Type::Bool | Type::Char | Type::String => true,
+        Type::Vec(element) => is_ordered(element),
+        Type::Class(_) => false,
+    }
+}
+
+pub(crate) fn unordered(element_type: &Type) -> RuntimeError {
+    RuntimeError::NameError {
+        code: &error_codes::UNKNOWN_MEMBER,
+        message: format!(
+            "`Vec<{element_type}>` has no method named `sort`, because values of type `{element_type}` can't be compared."
+        ),
+    }
+}
+
+/// Whether values of `element_type` have a text, to join them.
+pub(crate) fn is_joinable(element_type: &Type) -> bool {
+    matches!(
+        element_type,
+        Type::Number(_) | Type::Bool | Type::Char | Type::String
+    )
+}
+
+pub(crate) fn unjoinable(element_type: &Type) -> RuntimeError {
+    RuntimeError::NameError {
+        code: &error_codes::UNKNOWN_MEMBER,
+        message: format!(
+            "`Vec<{element_type}>` has no method named `join`, because only numbers, `bool`, `char` and `String` values can be joined."
+        ),
+    }
+}
+
+/// Returns the text of `value` in a joined string: strings and chars without their quotes.
+fn text(value: &Value) -> String {
+    match value {
+        Value::String(string) => string.clone(),
+        Value::Char(char) => char.to_string(),
+        value => value.to_string(),
+    }
+}
+
+fn compare_numbers(left: &Number, right: &Number) -> Ordering {
+    let floating_point = left.is_floating_point(right);
+    let unbounded = |number: &Number| matches!(number, Number::BigInt(_) | Number::Decimal(_));
+    if floating_point {
+        left.as_f64().total_cmp(&right.as_f64())
+    } else if unbounded(left) || unbounded(right) {
+        left.to_decimal().cmp(&right.to_decimal())
+    } else if left.number_type().is_signed() {
+        left.as_i128().cmp(&right.as_i128())
+    } else {
+        left.as_u128().cmp(&right.as_u128())
+    }
+}
+
+/// Orders two elements of an array whose type [`is_ordered`]. Floating point numbers are
+/// totally ordered, with NaN before or after every other number depending on its sign.
+fn compare(left: &Value, right: &Value) -> Ordering {
+    match (left, right) {
+        (Value::Number(left), Value::Number(right)) => compare_numbers(left, right),
+        (Value::Bool(left), Value::Bool(right)) => left.cmp(right),
+        (Value::Char(left), Value::Char(right)) => left.cmp(right),
+        (Value::String(left), Value::String(right)) => left.cmp(right),
+        (Value::Vec(left), Value::Vec(right)) => {
+            let (left, right) = (left.elements.borrow(), right.elements.borrow());
+            let mut order = left.iter().zip(right.iter()).map(|(l, r)| compare(l, r));
+            order
+                .find(|order| order.is_ne())
+                .unwrap_or_else(|| left.len().cmp(&right.len()))
+        }
+        _ => Ordering::Equal,
+    }
+}
+
+/// Whether two elements of an array are equal. Objects are equal only to themselves.
+fn equal(left: &Value, right: &Value) -> bool {
+    match (left, right) {
+        (Value::Number(left), Value::Number(right)) if left.is_floating_point(right) => {
+            left.as_f64() == right.as_f64()
+        }
+        (Value::Object(left), Value::Object(right)) => left.same_as(right),
+        (Value::Vec(left), Value::Vec(right)) => {
+            let (left, right) = (left.elements.borrow(), right.elements.borrow());
+            left.len() == right.len() && left.iter().zip(right.iter()).all(|(l, r)| equal(l, r))
+        }
+        (left, right) => compare(left, right).is_eq(),
+    }
+}
@@ -0,0 +1,457 @@
+use std::{
+    cmp::Ordering,
+    fmt::Display,
+    ops::{Add, Mul, Neg, Sub},
+    str::FromStr,
+};
+
+/// An arbitrary-precision signed integer.
+///
+/// The magnitude is stored as little-endian base 2^32 limbs without trailing zero limbs, so zero
+/// is an empty magnitude and is never negative. This keeps the derived equality correct.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BigInt {
+    negative: bool,
+    magnitude: Vec<u32>,
+}
+
+fn trim(magnitude: &mut Vec<u32>) {
+    while let Some(0) = magnitude.last() {
+        magnitude.pop();
+    }
+}
+
+fn cmp_magnitude(left: &[u32], right: &[u32]) -> Ordering {
+    left.len()
+        .cmp(&right.len())
+        .then_with(|| left.iter().rev().cmp(right.iter().rev()))
+}
+
+fn add_magnitude(left: &[u32], right: &[u32]) -> Vec<u32> {
+    let mut result = Vec::with_capacity(left.len().max(right.len()) + 1);
+    let mut carry = 0u64;
+    for index in 0..left.len().max(right.len()) {
+        let sum =
+            *left.get(index).unwrap_or(&0) as u64 + *right.get(index).unwrap_or(&0) as u64 + carry;
+        result.push(sum as u32);
+        carry = sum >> 32;
+    }
+    if carry != 0 {
+        result.push(carry as u32);
+    }
+    result
+}
+
+/// Subtracts `right` from `left`, which must have the larger or equal magnitude.
+fn sub_magnitude(left: &[u32], right: &[u32]) -> Vec<u32> {
+    let mut result = Vec::with_capacity(left.len());
+    let mut borrow = 0i64;
+    for (index, limb) in left.iter().enumerate() {
+        let mut difference = *limb as i64 - *right.get(index).unwrap_or(&0) as i64 - borrow;
+        borrow = 0;
+        if difference < 0 {
+            difference += 1 << 32;
+            borrow = 1;
+        }
+        result.push(difference as u32);
+    }
+    trim(&mut result);
+    result
+}
+
+fn mul_magnitude(left: &[u32], right: &[u32]) -> Vec<u32> {
+    if left.is_empty() || right.is_empty() {
+        return vec![];
+    }
+    let mut result = vec![0u32; left.len() + right.len()];
+    for (i, l) in left.iter().enumerate() {
+        let mut carry = 0u64;
+        for (j, r) in right.iter().enumerate() {
+            let product = *l as u64 * *r as u64 + result[i + j] as u64 + carry;
+            result[i + j] = product as u32;
+            carry = product >> 32;
+        }
+        result[i + right.len()] = carry as u32;
+    }
+    trim(&mut result);
+    result
+}
+
+fn divrem_small(left: &[u32], divisor: u32) -> (Vec<u32>, u32) {
+    let mut quotient = vec![0u32; left.len()];
+    let mut remainder = 0u64;
+    for (index, limb) in left.iter().enumerate().rev() {
+        let current = (remainder << 32) | *limb as u64;
+        quotient[index] = (current / divisor as u64) as u32;
+        remainder = current % divisor as u64;
+    }
+    trim(&mut quotient);
+    (quotient, remainder as u32)
+}
+
+/// Binary long division of two magnitudes. `right` must not be zero.
+fn divrem_magnitude(left: &[u32], right: &[u32]) -> (Vec<u32>, Vec<u32>) {
+    if right.len() == 1 {
+        let (quotient, remainder) = divrem_small(left, right[0]);
+        let mut remainder = vec![remainder];
+        trim(&mut remainder);
+        return (quotient, remainder);
+    }
+    if cmp_magnitude(left, right) == Ordering::Less {
+        return (vec![], left.to_vec());
+    }
+
+    let mut quotient = vec![0u32; left.len()];
+    let mut remainder: Vec<u32> = vec![];
+    for bit in (0..left.len() * 32).rev() {
+        // remainder = remainder << 1 | next bit of left
+        let mut carry = (left[bit / 32] >> (bit % 32)) & 1;
+        for limb in remainder.iter_mut() {
+            let next_carry = *limb >> 31;
+            *limb = (*limb << 1) | carry;
+            carry = next_carry;
+        }
+        if carry != 0 {
+            remainder.push(carry);
+        }
+        if cmp_magnitude(&remainder, right) != Ordering::Less {
+            remainder = sub_magnitude(&remainder, right);
+            quotient[bit / 32] |= 1 << (bit % 32);
+        }
+    }
+    trim(&mut quotient);
+    (quotient, remainder)
+}
+
+impl BigInt {
+    fn from_parts(negative: bool, mut magnitude: Vec<u32>) -> Self {
+        trim(&mut magnitude);
+        let negative = negative && !magnitude.is_empty();
+        BigInt {
+            negative,
+            magnitude,
+        }
+    }
+
+    pub fn zero() -> Self {
+        BigInt::from_parts(false, vec![])
+    }
+
+    pub fn from_u128(value: u128) -> Self {
+        BigInt::from_parts(
+            false,
+            (0..4).map(|index| (value >> (32 * index)) as u32).collect(),
+        )
+    }
+
+    pub fn from_i128(value: i128) -> Self {
+        let mut result = BigInt::from_u128(value.unsigned_abs());
+        result.negative = value < 0;
+        result
+    }
+
+    /// Converts a float by truncating it toward zero. Non-finite values become zero.
+    pub fn from_f64(value: f64) -> Self {
+        if !value.is_finite() {
+            return BigInt::zero();
+        }
+        format!("{:.0}", value.trunc())
+            .parse()
+            .unwrap_or_else(|_| BigInt::zero())
+    }
+
+    /// Returns `10^exponent`.
+    pub fn pow10(exponent: u32) -> Self {
+        let mut result = BigInt::from_u128(1);
+        let ten_to_the_19 = BigInt::from_u128(10u128.pow(19));
+        let mut remaining = exponent;
+        while remaining >= 19 {
+            result = &result * &ten_to_the_19;
+            remaining -= 19;
+        }
+        &result * &BigInt::from_u128(10u128.pow(remaining))
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.magnitude.is_empty()
+    }
+
+    /// Returns how many bytes the value takes on the heap.
+    pub fn heap_size(&self) -> usize {
+        self.magnitude.capacity() * std::mem::size_of::<u32>()
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.negative
+    }
+
+    pub fn is_even(&self) -> bool {
+        self.magnitude.first().is_none_or(|limb| limb % 2 == 0)
+    }
+
+    pub fn abs(&self) -> Self {
+        BigInt::from_parts(false, self.magnitude.clone())
+    }
+
+    /// Returns the value if it fits in an `i128`.
+    pub fn to_i128(&self) -> Option<i128> {
+        if self.magnitude.len() > 4 {
+            return None;
+        }
+        let magnitude = self.low_u128();
+        if self.negative {
+            if magnitude == i128::MIN.unsigned_abs() {
+                Some(i128::MIN)
+            } else {
+                i128::try_from(magnitude).ok().map(|value| -value)
+            }
+        } else {
+            i128::try_from(magnitude).ok()
+        }
+    }
+
+    /// Returns the lowest 128 bits of the two's complement representation, the way `as` truncates.
+    pub fn to_i128_wrapping(&self) -> i128 {
+        let low = self.low_u128() as i128;
+        if self.negative {
+            low.wrapping_neg()
+        } else {
+            low
+        }
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        self.magnitude
+            .iter()
+            .rev()
+            .fold(0f64, |value, limb| value * 4294967296f64 + *limb as f64)
+            * if self.negative { -1. } else { 1. }
+    }
+
+    fn low_u128(&self) -> u128 {
+        self.magnitude
+            .iter()
+            .take(4)
+            .enumerate()
+            .fold(0u128, |value, (index, limb)| {
+                value | (*limb as u128) << (32 * index)
+            })
+    }
+
+    /// Truncating division and remainder, like Rust's `/` and `%` on integers.
+    /// Returns `None` when dividing by zero.
+    pub fn div_rem(&self, other: &BigInt) -> Option<(BigInt, BigInt)> {
+        if other.is_zero() {
+            return None;
+        }
+        let (quotient, remainder) = divrem_magnitude(&self.magnitude, &other.magnitude);
+        Some((
+            BigInt::from_parts(self.negative != other.negative, quotient),
+            BigInt::from_parts(self.negative, remainder),
+        ))
+    }
+}
+
+impl Add for &BigInt {
+    type Output = BigInt;
+
+    fn add(self, other: &BigInt) -> BigInt {
+        if self.negative == other.negative {
+            return BigInt::from_parts(
+                self.negative,
+                add_magnitude(&self.magnitude, &other.magnitude),
+            );
+        }
+        match cmp_magnitude(&self.magnitude, &other.magnitude) {
+            Ordering::Less => BigInt::from_parts(
+                other.negative,
+                sub_magnitude(&other.magnitude, &self.magnitude),
+            ),
+            _ => BigInt::from_parts(
+                self.negative,
+                sub_magnitude(&self.magnitude, &other.magnitude),
+            ),
+        }
+    }
+}
+
+impl Sub for &BigInt {
+    type Output = BigInt;
+
+    fn sub(self, other: &BigInt) -> BigInt {
+        self + &-other
+    }
+}
+
+impl Mul for &BigInt {
+    type Output = BigInt;
+
+    fn mul(self, other: &BigInt) -> BigInt {
+        BigInt::from_parts(
+            self.negative != other.negative,
+            mul_magnitude(&self.magnitude, &other.magnitude),
+        )
+    }
+}
+
+impl Neg for &BigInt {
+    type Output = BigInt;
+
+    fn neg(self) -> BigInt {
+        BigInt::from_parts(!self.negative, self.magnitude.clone())
+    }
+}
+
+impl Ord for BigInt {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self.negative, other.negative) {
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+            (false, false) => cmp_magnitude(&self.magnitude, &other.magnitude),
+            (true, true) => cmp_magnitude(&other.magnitude, &self.magnitude),
+        }
+    }
+}
+
+impl PartialOrd for BigInt {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl FromStr for BigInt {
+    type Err = ();
+
+    /// Parses an optionally `-` prefixed string of decimal digits.
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        let (negative, digits) = match string.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, string),
+        };
+        if digits.is_empty() || !digits.bytes().all(|byte| byte.is_ascii_digit()) {
+            return Err(());
+        }
+
+        let mut result = BigInt::zero();
+        let chunk_size = 19;
+        let mut index = 0;
+        while index < digits.len() {
+            let end = (index + chunk_size).min(digits.len());
+            let chunk = u128::from_str(&digits[index..end]).map_err(|_| ())?;
+            result = &(&result * &BigInt::pow10((end - index) as u32)) + &BigInt::from_u128(chunk);
+            index = end;
+        }
+        result.negative = negative && !result.is_zero();
+        Ok(result)
+    }
+}
+
+impl Display for BigInt {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_zero() {
+            return f.write_str("0");
+        }
+        let mut chunks = vec![];
+        let mut magnitude = self.magnitude.clone();
+        while !magnitude.is_empty() {
+            let (quotient, remainder) = divrem_small(&magnitude, 1_000_000_000);
+            chunks.push(remainder);
+            magnitude = quotient;
+        }
+        if self.negative {
+            f.write_str("-")?;
+        }
+        let mut chunks = chunks.iter().rev();
+        if let Some(first) = chunks.next() {
+            write!(f, "{first}")?;
+        }
+        for chunk in chunks {
+            write!(f, "{chunk:09}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn big(value: &str) -> BigInt {
+        value.parse().unwrap()
+    }
+
+    #[test]
+    fn addition_carries_across_limbs() {
+        let max_limb = BigInt::from_u128(u32::MAX as u128);
+        assert_eq!(
+            &max_limb + &BigInt::from_u128(1),
+            BigInt::from_u128(1 << 32)
+        );
+        let max = BigInt::from_u128(u128::MAX);
+        assert_eq!(
+            (&max + &max).to_string(),
+            "680564733841876926926749214863536422910"
+        );
+    }
+
+    #[test]
+    fn subtraction_borrows_across_limbs() {
+        let difference = &BigInt::from_u128(1 << 64) - &BigInt::from_u128(1);
+        assert_eq!(difference, BigInt::from_u128(u64::MAX as u128));
+        assert_eq!(
+            &BigInt::from_i128(1) - &BigInt::from_u128(1 << 64),
+            big("-18446744073709551615")
+        );
+        // Zero is never negative, whatever the operands were.
+        let zero = &big("-12345678901234567890") - &big("-12345678901234567890");
+        assert!(zero.is_zero() && !zero.is_negative());
+        assert_eq!(zero, BigInt::zero());
+    }
+
+    #[test]
+    fn multiplication_carries_into_the_top_limb() {
+        let max = BigInt::from_u128(u64::MAX as u128);
+        assert_eq!(
+            &max * &max,
+            BigInt::from_u128(u64::MAX as u128 * u64::MAX as u128)
+        );
+        assert_eq!(&big("-3") * &BigInt::zero(), BigInt::zero());
+    }
+
+    #[test]
+    fn division_truncates_toward_zero() {
+        for (left, right) in [(7, 2), (-7, 2), (7, -2), (-7, -2), (1, 3), (0, 5)] {
+            let (quotient, remainder) = BigInt::from_i128(left)
+                .div_rem(&BigInt::from_i128(right))
+                .unwrap();
+            assert_eq!(quotient.to_i128(), Some(left / right), "{left} / {right}");
+            assert_eq!(remainder.to_i128(), Some(left % right), "{left} % {right}");
+        }
+        assert!(BigInt::from_i128(1).div_rem(&BigInt::zero()).is_none());
+    }
+
+    #[test]
+    fn long_division_matches_multiplication() {
+        let divisor = big("98765432109876543210987");
+        let quotient = big("12345678901234567890123456789");
+        let remainder = big("98765432109876543210986");
+        let dividend = &(&divisor * &quotient) + &remainder;
+        assert_eq!(dividend.div_rem(&divisor), Some((quotient, remainder)));
+        let small = big("5");
+        assert_eq!(small.div_rem(&divisor), Some((BigInt::zero(), small)));
+    }
+
+    #[test]
+    fn parses_and_prints_in_base_ten() {
+        for text in [
+            "0",
+            "-1",
+            "340282366920938463463374607431768211456",
+            "-98765432109876543210",
+        ] {
+            assert_eq!(big(text).to_string(), text);
+        }
+        assert!("".parse::<BigInt>().is_err());
+        assert!("12a".parse::<BigInt>().is_err());
+    }
+}
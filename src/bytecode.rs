@@ -0,0 +1,576 @@
+use std::collections::HashMap;
+
+use crate::{
+    executer::{self, Value},
+    formatter,
+    parser::{BinaryOperator, Expression, NumberType, Statement, Type, UnaryOperator},
+};
+
+/// An instruction of the virtual machine, which works on a stack of values. Operands are indexes
+/// in the tables of the [`Chunk`] holding the instruction.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Instruction {
+    /// Pushes a constant.
+    Constant(u32),
+    /// Pushes the value of the variable in a slot.
+    Load(u32),
+    /// Pops a value and declares the variable in `slot` with it, as a `types[declared_type]` or
+    /// with the type of the value when it is `None`.
+    Declare {
+        slot: u32,
+        declared_type: Option<u32>,
+        mutable: bool,
+    },
+    /// Pops a value and stores it in the variable in a slot, converted to its declared type.
+    Store(u32),
+    /// Checks that the value on top of the stack is a number.
+    Plus,
+    Negate,
+    /// Pops the right then the left operand, and pushes the result.
+    Binary(BinaryOperator),
+    Cast(NumberType),
+    /// Pops `arguments` values and pushes what the function `names[function]` returns for them.
+    Call {
+        function: u32,
+        arguments: u32,
+        site: u32,
+    },
+    /// Pops `arguments` values then the object, and pushes what its method `names[method]`
+    /// returns for them.
+    CallMethod {
+        method: u32,
+        arguments: u32,
+        site: u32,
+    },
+    /// Pops an object and pushes its field `names[field]`.
+    GetField {
+        field: u32,
+        site: u32,
+    },
+    /// Pushes the constant `names[member]` of the class `names[class]`.
+    GetConstant {
+        class: u32,
+        member: u32,
+    },
+    /// Pops `arguments` values and pushes what the static function `names[function]` of the
+    /// class `names[class]` returns for them.
+    CallStatic {
+        class: u32,
+        function: u32,
+        arguments: u32,
+        site: u32,
+    },
+    /// Pops the value of an expression statement, which is its result.
+    Yield,
+    /// Pops `count` values and pushes a `Vec` of them, whose elements are `types[element_type]`
+    /// or have the type of the first value when it is `None`.
+    MakeVec {
+        count: u32,
+        element_type: Option<u32>,
+    },
+    /// Pops an index then a `Vec`, and pushes its element at the index.
+    Index,
+    /// Pops a value, an index then a `Vec`, and replaces its element at the index by the value.
+    StoreIndex,
+    /// Pops a `Vec` and starts a loop over its elements. The variables in the slots of
+    /// `scopes[scope]` are put back as they were once the loop ends.
+    Iterate {
+        scope: u32,
+    },
+    /// Declares the next element of the innermost loop in `slot`, or jumps to `end` if there is
+    /// none left.
+    Next {
+        slot: u32,
+        end: u32,
+    },
+    /// Continues at the instruction at index `target`.
+    Jump(u32),
+    /// Pops a value and appends it to the results of the innermost loop.
+    Append,
+    /// Pops a `bool`, and appends the current element of the innermost loop to its results if
+    /// it is `true`.
+    AppendIf,
+    /// Ends the innermost loop.
+    EndLoop,
+    /// Ends the innermost loop, pushing a `Vec` of its results whose elements are
+    /// `types[element_type]`, or have the type of the elements it iterated over when it is
+    /// `None`.
+    Collect {
+        element_type: Option<u32>,
+    },
+}
+
+impl Instruction {
+    /// Returns how many values the instruction pops, then pushes.
+    pub fn stack_effect(&self) -> (u32, u32) {
+        match self {
+            Instruction::Constant(_) | Instruction::Load(_) | Instruction::GetConstant { .. } => {
+                (0, 1)
+            }
+            Instruction::Declare { .. }
+            | Instruction::Store(_)
+            | Instruction::Yield
+            | Instruction::Iterate { .. }
+            | Instruction::Append
+            | Instruction::AppendIf => (1, 0),
+            Instruction::Next { .. } | Instruction::Jump(_) | Instruction::EndLoop => (0, 0),
+            Instruction::Plus
+            | Instruction::Negate
+            | Instruction::Cast(_)
+            | Instruction::GetField { .. } => (1, 1),
+            Instruction::Binary(_) | Instruction::Index => (2, 1),
+            Instruction::StoreIndex => (3, 0),
+            Instruction::MakeVec { count, .. } => (*count, 1),
+            Instruction::Collect { .. } => (0, 1),
+            Instruction::Call { arguments, .. } | Instruction::CallStatic { arguments, .. } => {
+                (*arguments, 1)
+            }
+            Instruction::CallMethod { arguments, .. } => (arguments + 1, 1),
+        }
+    }
+}
+
+/// A program compiled to bytecode.
+#[derive(Debug, Clone, Default)]
+pub struct Chunk {
+    pub code: Vec<Instruction>,
+    /// The statement each instruction was compiled from, as an index in `statements`.
+    pub statement_of: Vec<u32>,
+    /// How deeply the tree-walker nests expressions before it gets to each instruction, counting
+    /// only the expressions it enters since the previous one, or 0 if there are none. The
+    /// virtual machine checks it against [`Limits::max_call_depth`](crate::Limits), so that both
+    /// engines accept the same programs.
+    pub depths: Vec<u32>,
+    /// The source of each statement, for stack traces.
+    pub statements: Vec<String>,
+    /// The 1-based line each statement starts on in the file it was compiled from, or nothing if
+    /// it wasn't compiled from a file.
+    pub lines: Vec<u32>,
+    pub constants: Vec<Value>,
+    /// The name of the variable in each slot.
+    pub slots: Vec<String>,
+    /// The names of the functions, classes and members used by the program.
+    pub names: Vec<String>,
+    /// The types of the declarations.
+    pub types: Vec<Type>,
+    /// The source of each call, for stack traces.
+    pub sites: Vec<String>,
+    /// The slots of the variables of each loop.
+    pub scopes: Vec<Vec<u32>>,
+}
+
+/// Compiles `statements`, which must have been type checked, to bytecode. The statements of the
+/// body of a loop follow the loop in `statements`.
+///
+/// # Panics
+///
+/// Panics if a lambda isn't the argument of `map` or `filter`, which the checker rejects.
+pub fn compile(statements: &[Statement]) -> Chunk {
+    let mut compiler = Compiler::default();
+    for statement in statements {
+        compiler.statement(statement);
+    }
+    compiler.chunk
+}
+
+#[derive(Default)]
+struct Compiler {
+    chunk: Chunk,
+    slots: HashMap<String, u32>,
+    names: HashMap<String, u32>,
+    /// The statement the instructions are compiled from, as an index in `chunk.statements`.
+    statement: u32,
+    /// How deeply the expression being compiled is nested in its statement.
+    depth: u32,
+    /// The deepest expression entered since the last instruction, for `chunk.depths`.
+    entered: u32,
+}
+
+/// Returns the index of `value` in `table`, adding it if it isn't there yet.
+fn intern(table: &mut Vec<String>, indexes: &mut HashMap<String, u32>, value: &str) -> u32 {
+    *indexes.entry(value.to_string()).or_insert_with(|| {
+        table.push(value.to_string());
+        (table.len() - 1) as u32
+    })
+}
+
+impl Compiler {
+    fn emit(&mut self, instruction: Instruction) {
+        self.chunk.code.push(instruction);
+        self.chunk.statement_of.push(self.statement);
+        self.chunk.depths.push(self.entered);
+        self.entered = 0;
+    }
+
+    fn slot(&mut self, name: &str) -> u32 {
+        intern(&mut self.chunk.slots, &mut self.slots, name)
+    }
+
+    fn name(&mut self, name: &str) -> u32 {
+        intern(&mut self.chunk.names, &mut self.names, name)
+    }
+
+    fn constant(&mut self, value: Value) {
+        self.chunk.constants.push(value);
+        self.emit(Instruction::Constant(
+            (self.chunk.constants.len() - 1) as u32,
+        ));
+    }
+
+    fn declared_type(&mut self, declared_type: &Type) -> u32 {
+        self.chunk.types.push(declared_type.clone());
+        (self.chunk.types.len() - 1) as u32
+    }
+
+    fn scope<'a>(&mut self, names: impl IntoIterator<Item = &'a str>) -> u32 {
+        let mut slots = vec![];
+        for name in names {
+            slots.push(self.slot(name));
+        }
+        self.chunk.scopes.push(slots);
+        (self.chunk.scopes.len() - 1) as u32
+    }
+
+    /// Starts the body of the loop started by an [`Instruction::Iterate`], which declares its
+    /// elements in `slot`. Returns the index of its first instruction, for [`Compiler::end_body`].
+    fn start_body(&mut self, slot: u32) -> usize {
+        self.emit(Instruction::Next { slot, end: 0 });
+        self.chunk.code.len() - 1
+    }
+
+    /// Ends the body of a loop starting at `start`, going back to it for the next element.
+    fn end_body(&mut self, start: usize) {
+        self.emit(Instruction::Jump(start as u32));
+        let after = self.chunk.code.len() as u32;
+        if let Instruction::Next { end, .. } = &mut self.chunk.code[start] {
+            *end = after;
+        }
+    }
+
+    fn site(&mut self, call: &Expression) -> u32 {
+        self.chunk.sites.push(formatter::format_expression(call));
+        (self.chunk.sites.len() - 1) as u32
+    }
+
+    fn statement(&mut self, statement: &Statement) {
+        let source = match statement {
+            Statement::For {
+                variable, iterable, ..
+            } => formatter::format_loop_header(variable, iterable),
+            statement => formatter::format_statement(statement),
+        };
+        self.chunk.statements.push(source);
+        self.statement = (self.chunk.statements.len() - 1) as u32;
+        match statement {
+            Statement::Declaration {
+                declared_type,
+                mutable,
+                name,
+                value,
+            } => {
+                self.expression(value);
+                let declared_type = declared_type
+                    .as_ref()
+                    .map(|declared_type| self.declared_type(declared_type));
+                let slot = self.slot(name);
+                self.emit(Instruction::Declare {
+                    slot,
+                    declared_type,
+                    mutable: *mutable,
+                });
+            }
+            Statement::Assignment { name, value } => {
+                self.expression(value);
+                let slot = self.slot(name);
+                self.emit(Instruction::Store(slot));
+            }
+            Statement::IndexAssignment {
+                object,
+                index,
+                value,
+            } => {
+                self.expression(object);
+                self.expression(index);
+                self.expression(value);
+                self.emit(Instruction::StoreIndex);
+            }
+            Statement::For {
+                variable,
+                iterable,
+                body,
+            } => {
+                let header = self.statement;
+                self.expression(iterable);
+                let scope = self.scope(executer::loop_scope(variable, body));
+                self.emit(Instruction::Iterate { scope });
+                let slot = self.slot(variable);
+                let start = self.start_body(slot);
+                for (_, statement) in body {
+                    self.statement(statement);
+                }
+                self.statement = header;
+                self.end_body(start);
+                self.emit(Instruction::EndLoop);
+            }
+            Statement::Expression(expression) => {
+                self.expression(expression);
+                self.emit(Instruction::Yield);
+            }
+        }
+    }
+
+    /// Compiles the arguments of a call, returning how many there are.
+    fn arguments(&mut self, arguments: &[Expression]) -> u32 {
+        for argument in arguments {
+            self.expression(argument);
+        }
+        arguments.len() as u32
+    }
+
+    fn expression(&mut self, expression: &Expression) {
+        self.depth += 1;
+        self.entered = self.entered.max(self.depth);
+        match expression {
+            Expression::UnaryOperation { operator, expr } => {
+                self.expression(expr);
+                self.emit(match operator {
+                    UnaryOperator::Plus => Instruction::Plus,
+                    UnaryOperator::Minus => Instruction::Negate,
+                });
+            }
+            Expression::BinaryOperations { first, operations } => {
+                self.expression(first);
+                for (operator, operand) in operations {
+                    self.expression(operand);
+                    self.emit(Instruction::Binary(operator.clone()));
+                }
+            }
+            Expression::Cast { expr, target } => {
+                self.expression(expr);
+                self.emit(Instruction::Cast(*target));
+            }
+            // Parentheses only group the operands, which the order of the instructions does.
+            Expression::ParenthesisExpression(expr) => self.expression(expr),
+            Expression::Call {
+                function,
+                arguments,
+            } => {
+                let arguments = self.arguments(arguments);
+                let function = self.name(function);
+                let site = self.site(expression);
+                self.emit(Instruction::Call {
+                    function,
+                    arguments,
+                    site,
+                });
+            }
+            Expression::Field { object, field } => {
+                self.expression(object);
+                let field = self.name(field);
+                let site = self.site(expression);
+                self.emit(Instruction::GetField { field, site });
+            }
+            Expression::MethodCall {
+                object,
+                method,
+                arguments,
+            } => {
+                self.expression(object);
+                // `map` and `filter` loop over the elements, with the lambda as the body.
+                if let Some((parameter, body, result_type)) =
+                    executer::lambda_argument(method, arguments)
+                {
+                    let scope = self.scope([parameter]);
+                    self.emit(Instruction::Iterate { scope });
+                    let slot = self.slot(parameter);
+                    let start = self.start_body(slot);
+                    self.expression(body);
+                    let element_type = if method == "map" {
+                        self.emit(Instruction::Append);
+                        result_type.map(|result_type| self.declared_type(result_type))
+                    } else {
+                        self.emit(Instruction::AppendIf);
+                        None
+                    };
+                    self.end_body(start);
+                    self.emit(Instruction::Collect { element_type });
+                } else {
+                    let arguments = self.arguments(arguments);
+                    let method = self.name(method);
+                    let site = self.site(expression);
+                    self.emit(Instruction::CallMethod {
+                        method,
+                        arguments,
+                        site,
+                    });
+                }
+            }
+            Expression::StaticMember { class, member } => {
+                let class = self.name(class);
+                let member = self.name(member);
+                self.emit(Instruction::GetConstant { class, member });
+            }
+            Expression::StaticCall {
+                class,
+                function,
+                arguments,
+            } => {
+                let arguments = self.arguments(arguments);
+                let class = self.name(class);
+                let function = self.name(function);
+                let site = self.site(expression);
+                self.emit(Instruction::CallStatic {
+                    class,
+                    function,
+                    arguments,
+                    site,
+                });
+            }
+            Expression::Vec {
+                elements,
+                element_type,
+            } => {
+                let count = self.arguments(elements);
+                let element_type = element_type
+                    .as_ref()
+                    .map(|element_type| self.declared_type(element_type));
+                self.emit(Instruction::MakeVec {
+                    count,
+                    element_type,
+                });
+            }
+            Expression::Index { object, index } => {
+                self.expression(object);
+                self.expression(index);
+                self.emit(Instruction::Index);
+            }
+            Expression::Lambda { .. } => {
+                unreachable!(
+                    "The checker only allows lambdas as the argument of `map` and `filter`."
+                )
+            }
+            Expression::Variable(name) => {
+                let slot = self.slot(name);
+                self.emit(Instruction::Load(slot));
+            }
+            Expression::Number(number) => self.constant(Value::Number(number.clone())),
+            Expression::Bool(bool) => self.constant(Value::Bool(*bool)),
+            Expression::Char(char) => self.constant(Value::Char(*char)),
+            Expression::String(string) => self.constant(Value::String(string.clone())),
+        }
+        self.depth -= 1;
+    }
+}
+
+fn arguments(count: u32) -> String {
+    let plural = if count == 1 { "" } else { "s" };
+    format!("{count} argument{plural}")
+}
+
+/// Returns the instructions of `chunk` in a readable form, each statement being introduced by
+/// its line and source.
+pub fn disassemble(chunk: &Chunk) -> String {
+    let mut output = String::new();
+    let mut statement = None;
+    for (offset, instruction) in chunk.code.iter().enumerate() {
+        let statement_of = chunk.statement_of[offset] as usize;
+        if statement != Some(statement_of) {
+            statement = Some(statement_of);
+            let source = &chunk.statements[statement_of];
+            output += &match chunk.lines.get(statement_of) {
+                Some(line) => format!("line {line}: {source}\n"),
+                None => format!("statement {}: {source}\n", statement_of + 1),
+            };
+        }
+        let name = |index: &u32| &chunk.names[*index as usize];
+        let slot = |index: &u32| &chunk.slots[*index as usize];
+        let (mnemonic, operands) = match instruction {
+            Instruction::Constant(index) => {
+                let constant = &chunk.constants[*index as usize];
+                ("constant", format!("{constant}: {}", constant.value_type()))
+            }
+            Instruction::Load(index) => ("load", slot(index).clone()),
+            Instruction::Declare {
+                slot: index,
+                declared_type,
+                mutable,
+            } => {
+                let mut operands = slot(index).clone();
+                if let Some(declared_type) = declared_type {
+                    operands += &format!(": {}", chunk.types[*declared_type as usize]);
+                }
+                if !mutable {
+                    operands += " (immutable)";
+                }
+                ("declare", operands)
+            }
+            Instruction::Store(index) => ("store", slot(index).clone()),
+            Instruction::Plus => ("plus", String::new()),
+            Instruction::Negate => ("negate", String::new()),
+            Instruction::Binary(operator) => ("binary", operator.symbol().to_string()),
+            Instruction::Cast(target) => ("cast", target.to_string()),
+            Instruction::Call {
+                function,
+                arguments: count,
+                ..
+            } => ("call", format!("{}, {}", name(function), arguments(*count))),
+            Instruction::CallMethod {
+                method,
+                arguments: count,
+                ..
+            } => (
+                "call_method",
+                format!("{}, {}", name(method), arguments(*count)),
+            ),
+            Instruction::GetField { field, .. } => ("get_field", name(field).clone()),
+            Instruction::GetConstant { class, member } => {
+                ("get_constant", format!("{}::{}", name(class), name(member)))
+            }
+            Instruction::CallStatic {
+                class,
+                function,
+                arguments: count,
+                ..
+            } => (
+                "call_static",
+                format!("{}::{}, {}", name(class), name(function), arguments(*count)),
+            ),
+            Instruction::Yield => ("yield", String::new()),
+            Instruction::MakeVec {
+                count,
+                element_type,
+            } => {
+                let plural = if *count == 1 { "" } else { "s" };
+                let mut operands = format!("{count} element{plural}");
+                if let Some(element_type) = element_type {
+                    operands += &format!(" of {}", chunk.types[*element_type as usize]);
+                }
+                ("make_vec", operands)
+            }
+            Instruction::Index => ("index", String::new()),
+            Instruction::StoreIndex => ("store_index", String::new()),
+            Instruction::Iterate { scope } => {
+                let scope = chunk.scopes[*scope as usize].iter().map(slot);
+                let names = scope.map(String::as_str).collect::<Vec<_>>();
+                ("iterate", format!("scope {}", names.join(", ")))
+            }
+            Instruction::Next { slot: index, end } => {
+                ("next", format!("{}, else jump to {end}", slot(index)))
+            }
+            Instruction::Jump(target) => ("jump", target.to_string()),
+            Instruction::Append => ("append", String::new()),
+            Instruction::AppendIf => ("append_if", String::new()),
+            Instruction::EndLoop => ("end_loop", String::new()),
+            Instruction::Collect { element_type } => {
+                let operands = match element_type {
+                    Some(element_type) => format!("of {}", chunk.types[*element_type as usize]),
+                    None => String::new(),
+                };
+                ("collect", operands)
+            }
+        };
+        output += format!("{offset:>6}  {mnemonic:<13}{operands}").trim_end();
+        output.push('\n');
+    }
+    output
+}
@@ -0,0 +1,1541 @@
+use std::{
+    cell::Cell,
+    collections::{HashMap, HashSet},
+    ops::Range,
+};
+
+use crate::{
+    array,
+    diagnostic::Diagnostic,
+    error_codes,
+    executer::{self, Environment, RuntimeError, Value},
+    native::NativeFunction,
+    parser::{BinaryOperator, Expression, Number, NumberType, Statement, Type, UnaryOperator},
+};
+
+/// A type error found before executing, along with the tokens it is about.
+#[derive(Debug)]
+pub struct CheckError {
+    pub error: RuntimeError,
+    /// The indexes of the tokens of the expression or name the error is about.
+    pub tokens: Range<usize>,
+    /// Describes the tokens.
+    pub label: String,
+    /// Other tokens explaining the error, like the type of a declaration, and their description.
+    pub context: Option<(Range<usize>, String)>,
+}
+
+impl CheckError {
+    fn new(error: RuntimeError, tokens: Range<usize>, label: String) -> Self {
+        CheckError {
+            error,
+            tokens,
+            label,
+            context: None,
+        }
+    }
+
+    fn with_context(mut self, tokens: Range<usize>, message: String) -> Self {
+        self.context = Some((tokens, message));
+        self
+    }
+
+    /// `spans` are the byte ranges of the tokens, as returned by the lexer.
+    pub fn to_diagnostic(&self, spans: &[Range<usize>]) -> Diagnostic {
+        let mut diagnostic = self.error.to_diagnostic();
+        if let Some(span) = tokens_span(&self.tokens, spans) {
+            diagnostic = diagnostic.with_span(span).with_label(&self.label);
+        }
+        if let Some((tokens, message)) = &self.context {
+            if let Some(span) = tokens_span(tokens, spans) {
+                diagnostic = diagnostic.with_secondary_label(span, message);
+            }
+        }
+        diagnostic
+    }
+}
+
+/// Returns the byte range from the start of the first token of `tokens` to the end of the last.
+fn tokens_span(tokens: &Range<usize>, spans: &[Range<usize>]) -> Option<Range<usize>> {
+    let first = spans.get(tokens.start)?;
+    let last = spans.get(tokens.end.checked_sub(1)?)?;
+    Some(first.start..last.end)
+}
+
+/// The type of an operand, or `None` if it has an error which was already reported, along with
+/// the indexes of its tokens.
+struct Operand {
+    value_type: Option<Type>,
+    tokens: Range<usize>,
+    /// The value of the operand if it only operates on numbers, which the optimizer computes
+    /// before it runs.
+    constant: Option<Number>,
+}
+
+/// What the expressions of a statement are checked against.
+#[derive(Clone, Copy)]
+struct Scope<'a> {
+    environment: &'a Environment,
+    /// The variables whose type couldn't be inferred because of an error in their value, whose
+    /// uses aren't reported.
+    untyped: &'a HashSet<String>,
+    /// The `Vec`s declared empty whose type isn't inferred yet, which can't be used until then.
+    pending: &'a HashMap<String, Pending>,
+    /// The parameters of the lambdas the expression is in, innermost first.
+    locals: Option<&'a Local<'a>>,
+}
+
+/// The parameter of a lambda, whose type is `None` if the type of the elements it is called
+/// with isn't known because of an error.
+struct Local<'a> {
+    name: &'a str,
+    value_type: Option<Type>,
+    outer: Option<&'a Local<'a>>,
+}
+
+impl<'a> Scope<'a> {
+    /// Returns the lambda parameter `name`, if the expression is in a lambda taking it.
+    fn local(&self, name: &str) -> Option<&'a Local<'a>> {
+        let mut local = self.locals;
+        while let Some(current) = local {
+            if current.name == name {
+                return Some(current);
+            }
+            local = current.outer;
+        }
+        None
+    }
+}
+
+/// Where a variable checked by a [`Checker`] was declared.
+#[derive(Debug)]
+struct Declaration {
+    /// The indexes of the tokens telling its type: the type of a declaration, or the value of a
+    /// `let` or `var` declaration.
+    tokens: Range<usize>,
+    inferred: bool,
+    /// The index of the `let` keyword of an immutable variable.
+    immutable: Option<usize>,
+}
+
+/// A `Vec` declared empty by `let <name> = [];` or `var <name> = [];`, whose type is inferred
+/// from the first statement storing an element in it.
+#[derive(Debug)]
+struct Pending {
+    /// The index of the `let` or `var` keyword.
+    start: usize,
+    mutable: bool,
+    /// Whether a use of the `Vec` was reported, which is then enough.
+    reported: Cell<bool>,
+}
+
+impl Pending {
+    /// The indexes of the tokens of the `[]`.
+    fn tokens(&self) -> Range<usize> {
+        self.start + 3..self.start + 5
+    }
+
+    /// The error of a `Vec` whose type couldn't be inferred, unless one of its uses was reported.
+    fn unresolved(&self) -> Option<CheckError> {
+        if self.reported.get() {
+            return None;
+        }
+        Some(CheckError::new(
+            executer::unknown_element_type(),
+            self.tokens(),
+            String::from("type of the elements unknown"),
+        ))
+    }
+}
+
+/// Type checks statements before they are executed, reporting every error of each statement
+/// rather than only the first one.
+///
+/// The tree doesn't record where expressions are in the source: as every token of a statement is
+/// part of exactly one of its expressions, their tokens are found from the index of the first
+/// token of the statement, as returned by [`parse_statements_at`](crate::parser::parse_statements_at).
+#[derive(Debug, Default)]
+pub struct Checker {
+    /// The declarations checked so far, to point at them when a value doesn't fit.
+    declarations: HashMap<String, Declaration>,
+    untyped: HashSet<String>,
+    pending: HashMap<String, Pending>,
+    /// The types of the elements inferred for the `Vec`s declared empty, by the index of the
+    /// first token of their declaration.
+    inferred: HashMap<usize, Type>,
+}
+
+impl Checker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Type checks `statement`, whose first token is at index `start`, declaring the variables
+    /// it introduces in `environment` without a value. Variables declared with `let` or `var`
+    /// get the type of their value.
+    ///
+    /// The types only known from the context, like the type of the elements of a `Vec`
+    /// literal declared with a type, are filled in `statement`. A `Vec` declared empty without
+    /// a type gets the type of the first element pushed, inserted or assigned to it by a
+    /// statement of its own, like `v.push(1);`, which is filled in by [`Checker::finish`].
+    pub fn check_statement(
+        &mut self,
+        statement: &mut Statement,
+        start: usize,
+        environment: &mut Environment,
+    ) -> Result<(), Vec<CheckError>> {
+        let mut errors = vec![];
+        if let Statement::Declaration { name, .. } = statement {
+            // Declaring the variable again gives up on inferring the type of its elements.
+            if let Some(pending) = self.pending.remove(name) {
+                errors.extend(pending.unresolved());
+            }
+        }
+        if let Some((name, mutable)) = empty_vec_declaration(statement) {
+            self.declarations.remove(&name);
+            self.untyped.remove(&name);
+            let pending = Pending {
+                start,
+                mutable,
+                reported: Cell::new(false),
+            };
+            self.pending.insert(name, pending);
+            return match errors.is_empty() {
+                true => Ok(()),
+                false => Err(errors),
+            };
+        }
+        self.infer_element_type(statement, environment);
+        let scope = Scope {
+            environment,
+            untyped: &self.untyped,
+            pending: &self.pending,
+            locals: None,
+        };
+        match statement {
+            Statement::Declaration {
+                declared_type,
+                mutable,
+                name,
+                value,
+            } => {
+                // `<type> <name> = <value>`, `let <name> = <value>` or `var <name> = <value>`
+                let value_start = match declared_type {
+                    Some(declared_type) => start + type_token_count(declared_type) + 2,
+                    None => start + 3,
+                };
+                let operand = expected_type(
+                    value,
+                    value_start,
+                    declared_type.as_ref(),
+                    scope,
+                    &mut errors,
+                );
+                let declaration = match declared_type {
+                    Some(declared_type) => {
+                        check_declared_type(declared_type, name, start, operand, scope, &mut errors)
+                    }
+                    None => operand.value_type.map(|value_type| {
+                        (
+                            value_type,
+                            Declaration {
+                                tokens: operand.tokens,
+                                inferred: true,
+                                immutable: (!*mutable).then_some(start),
+                            },
+                        )
+                    }),
+                };
+                match declaration {
+                    Some((declared_type, declaration)) => {
+                        // The variable is declared even if its value is wrong, so that its uses
+                        // aren't reported as well.
+                        environment.declare_unset(name, declared_type, *mutable);
+                        self.declarations.insert(name.clone(), declaration);
+                        self.untyped.remove(name);
+                    }
+                    None => {
+                        self.declarations.remove(name);
+                        self.untyped.insert(name.clone());
+                    }
+                }
+            }
+            Statement::Assignment { name, value } => {
+                // `<name> = <value>`
+                let target = environment.get_type(name);
+                let operand = expected_type(value, start + 2, target.as_ref(), scope, &mut errors);
+                let declaration = self.declarations.get(name);
+                match target {
+                    _ if self.untyped.contains(name) => {}
+                    _ if self.pending.contains_key(name) => {
+                        errors.push(pending_use(name, start, &self.pending));
+                    }
+                    None => errors.push(CheckError::new(
+                        executer::undefined_variable(name),
+                        start..start + 1,
+                        String::from("not declared"),
+                    )),
+                    Some(_) if !environment.is_mutable(name) => {
+                        let mut error = CheckError::new(
+                            executer::assigned_immutable(name),
+                            start..start + 1,
+                            String::from("cannot be assigned"),
+                        );
+                        if let Some(keyword) =
+                            declaration.and_then(|declaration| declaration.immutable)
+                        {
+                            error = error.with_context(
+                                keyword..keyword + 1,
+                                format!("declare `{name}` with `var` to assign it"),
+                            );
+                        }
+                        errors.push(error);
+                    }
+                    Some(target) => {
+                        if let Some(value_type) = operand.value_type {
+                            let checked = match check_storable(value_type.clone(), &target, name) {
+                                Err(error) => Err((
+                                    error,
+                                    format!("expected `{target}`, found `{value_type}`"),
+                                )),
+                                Ok(()) => check_constant(operand.constant, &target, name)
+                                    .map_err(|error| (error, format!("doesn't fit in `{target}`"))),
+                            };
+                            if let Err((error, label)) = checked {
+                                let mut error = CheckError::new(error, operand.tokens, label);
+                                if let Some(declaration) = declaration {
+                                    let verb = if declaration.inferred {
+                                        "inferred"
+                                    } else {
+                                        "declared"
+                                    };
+                                    error = error.with_context(
+                                        declaration.tokens.clone(),
+                                        format!("`{name}` is {verb} as `{target}` here"),
+                                    );
+                                }
+                                errors.push(error);
+                            }
+                        }
+                    }
+                }
+            }
+            Statement::IndexAssignment {
+                object,
+                index,
+                value,
+            } => {
+                // `<object>[<index>] = <value>`
+                let object = expression_type(object, start, scope, &mut errors);
+                let index = expression_type(index, object.tokens.end + 1, scope, &mut errors);
+                let element = element_type(&object, "index", &mut errors);
+                check_index(&index, &mut errors);
+                let value_start = index.tokens.end + 2;
+                let value = expected_type(value, value_start, element.as_ref(), scope, &mut errors);
+                if let (Some(element), Some(value_type)) = (element, value.value_type) {
+                    if let Err(error) = check_element(&value_type, &element) {
+                        errors.push(CheckError::new(
+                            error,
+                            value.tokens,
+                            format!("expected `{element}`, found `{value_type}`"),
+                        ));
+                    }
+                }
+            }
+            Statement::For {
+                variable,
+                iterable,
+                body,
+            } => {
+                // `for <variable> in <iterable> { <body> }`
+                let iterable = expression_type(iterable, start + 3, scope, &mut errors);
+                let element = element_type(&iterable, "iterate over", &mut errors);
+                let names = executer::loop_scope(variable, body)
+                    .into_iter()
+                    .map(String::from)
+                    .collect::<Vec<_>>();
+                let saved = environment.save(names.iter().map(String::as_str));
+                let saved_declarations = names
+                    .iter()
+                    .map(|name| (name.clone(), self.declarations.remove(name)))
+                    .collect::<Vec<_>>();
+                let saved_untyped = names
+                    .iter()
+                    .filter(|name| self.untyped.contains(*name))
+                    .cloned()
+                    .collect::<Vec<_>>();
+                let saved_pending = names
+                    .iter()
+                    .filter_map(|name| Some((name.clone(), self.pending.remove(name)?)))
+                    .collect::<Vec<_>>();
+                match element {
+                    Some(element) => {
+                        environment.declare_unset(variable, element, false);
+                        let declaration = Declaration {
+                            tokens: start + 1..start + 2,
+                            inferred: true,
+                            immutable: None,
+                        };
+                        self.declarations.insert(variable.clone(), declaration);
+                        self.untyped.remove(variable);
+                    }
+                    None => {
+                        self.untyped.insert(variable.clone());
+                    }
+                }
+                // The statements of the body are checked like statements of their own, all of
+                // them even if some fail.
+                for (start, statement) in body {
+                    if let Err(body_errors) = self.check_statement(statement, *start, environment) {
+                        errors.extend(body_errors);
+                    }
+                }
+                // The variables of the loop only exist in it.
+                environment.restore(saved);
+                for (name, declaration) in saved_declarations {
+                    match declaration {
+                        Some(declaration) => self.declarations.insert(name, declaration),
+                        None => self.declarations.remove(&name),
+                    };
+                }
+                for name in names {
+                    self.untyped.remove(&name);
+                    if let Some(pending) = self.pending.remove(&name) {
+                        errors.extend(pending.unresolved());
+                    }
+                }
+                self.untyped.extend(saved_untyped);
+                self.pending.extend(saved_pending);
+            }
+            Statement::Expression(expression) => {
+                expression_type(expression, start, scope, &mut errors);
+            }
+        }
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+        Ok(())
+    }
+
+    /// Declares the `Vec` declared empty whose type isn't inferred yet with the type of the
+    /// element `statement` stores in it, if it is `<name>.push(<element>)`,
+    /// `<name>.insert(<index>, <element>)` or `<name>[<index>] = <element>` and the element has
+    /// no type error.
+    fn infer_element_type(&mut self, statement: &Statement, environment: &mut Environment) {
+        let (object, element) = match statement {
+            Statement::Expression(Expression::MethodCall {
+                object,
+                method,
+                arguments,
+            }) => match (method.as_str(), arguments.as_slice()) {
+                ("push", [element]) | ("insert", [_, element]) => (&**object, element),
+                _ => return,
+            },
+            Statement::IndexAssignment { object, value, .. } => (object, value),
+            _ => return,
+        };
+        let Expression::Variable(name) = object else {
+            return;
+        };
+        if !self.pending.contains_key(name) {
+            return;
+        }
+        let Ok(element_type) = static_type(element, environment) else {
+            return;
+        };
+        let pending = self.pending.remove(name).expect("The `Vec` is pending");
+        let vec_type = Type::Vec(Box::new(element_type.clone()));
+        environment.declare_unset(name, vec_type, pending.mutable);
+        let declaration = Declaration {
+            tokens: pending.tokens(),
+            inferred: true,
+            immutable: (!pending.mutable).then_some(pending.start),
+        };
+        self.declarations.insert(name.clone(), declaration);
+        self.inferred.insert(pending.start, element_type);
+    }
+
+    /// Ends the checks of `statements`, along with the index of their first token: the `Vec`s
+    /// they declare empty get the type of the elements inferred for them, and the ones whose
+    /// type couldn't be inferred are reported.
+    pub fn finish(&mut self, statements: &mut [(usize, Statement)]) -> Result<(), Vec<CheckError>> {
+        fill_inferred(statements, &self.inferred);
+        self.inferred.clear();
+        let mut errors = self
+            .pending
+            .drain()
+            .filter_map(|(_, pending)| pending.unresolved())
+            .collect::<Vec<_>>();
+        if errors.is_empty() {
+            return Ok(());
+        }
+        errors.sort_by_key(|error| error.tokens.start);
+        Err(errors)
+    }
+}
+
+/// Returns the name of the variable `statement` declares as an empty `Vec` without a type, and
+/// whether it is mutable.
+fn empty_vec_declaration(statement: &Statement) -> Option<(String, bool)> {
+    match statement {
+        Statement::Declaration {
+            declared_type: None,
+            mutable,
+            name,
+            value:
+                Expression::Vec {
+                    elements,
+                    element_type: None,
+                },
+        } if elements.is_empty() => Some((name.clone(), *mutable)),
+        _ => None,
+    }
+}
+
+/// Fills the types of the elements in `inferred`, by the index of the first token of the
+/// declaration, in the empty `Vec`s declared by `statements` and the statements of their body.
+fn fill_inferred(statements: &mut [(usize, Statement)], inferred: &HashMap<usize, Type>) {
+    for (start, statement) in statements {
+        match statement {
+            Statement::Declaration {
+                value: Expression::Vec { element_type, .. },
+                ..
+            } if element_type.is_none() => {
+                *element_type = inferred.get(start).cloned();
+            }
+            Statement::For { body, .. } => fill_inferred(body, inferred),
+            _ => {}
+        }
+    }
+}
+
+/// The error of a use of `name`, the token at index `index`, before the type of its elements is
+/// inferred.
+fn pending_use(name: &str, index: usize, pending: &HashMap<String, Pending>) -> CheckError {
+    let mut error = CheckError::new(
+        executer::unknown_element_type(),
+        index..index + 1,
+        format!("the type of the elements of `{name}` isn't known yet"),
+    );
+    if let Some(pending) = pending.get(name) {
+        pending.reported.set(true);
+        error = error.with_context(
+            pending.tokens(),
+            String::from("inferred from the first element stored in it"),
+        );
+    }
+    error
+}
+
+/// Computes the type `expression` evaluates to without executing it, returning the first type
+/// error if it has any.
+///
+/// This follows the same promotion rules as [`executer::execute`], so an expression that would
+/// fail with a type error at runtime fails here too.
+pub fn static_type(
+    expression: &Expression,
+    environment: &Environment,
+) -> Result<Type, RuntimeError> {
+    let mut errors = vec![];
+    let scope = Scope {
+        environment,
+        untyped: &HashSet::new(),
+        pending: &HashMap::new(),
+        locals: None,
+    };
+    let mut expression = expression.clone();
+    match expression_type(&mut expression, 0, scope, &mut errors).value_type {
+        Some(value_type) => Ok(value_type),
+        None => Err(errors.remove(0).error),
+    }
+}
+
+/// Checks that `value`, along with its type and tokens, can be stored in `name`, declared with
+/// `declared_type` at index `start`, returning its type and declaration if the type exists.
+fn check_declared_type(
+    declared_type: &Type,
+    name: &str,
+    start: usize,
+    operand: Operand,
+    scope: Scope,
+    errors: &mut Vec<CheckError>,
+) -> Option<(Type, Declaration)> {
+    let type_tokens = start..start + type_token_count(declared_type);
+    check_type_classes(declared_type, start, scope, errors)?;
+    if let Some(value_type) = operand.value_type {
+        let checked = match check_storable(value_type.clone(), declared_type, name) {
+            Err(error) => Err((
+                error,
+                format!("expected `{declared_type}`, found `{value_type}`"),
+            )),
+            Ok(()) => check_constant(operand.constant, declared_type, name)
+                .map_err(|error| (error, format!("doesn't fit in `{declared_type}`"))),
+        };
+        if let Err((error, label)) = checked {
+            errors.push(CheckError::new(error, operand.tokens, label).with_context(
+                type_tokens.clone(),
+                String::from("expected due to this type"),
+            ));
+        }
+    }
+    Some((
+        declared_type.clone(),
+        Declaration {
+            tokens: type_tokens,
+            inferred: false,
+            immutable: None,
+        },
+    ))
+}
+
+/// Returns the number of tokens `declared_type` is written with: its name, followed by its type
+/// arguments between `<` and `>` if it is generic.
+fn type_token_count(declared_type: &Type) -> usize {
+    match declared_type {
+        Type::Vec(element) => 3 + type_token_count(element),
+        _ => 1,
+    }
+}
+
+/// Checks that the classes in `declared_type`, written from the token at index `start`, are
+/// registered. The parser accepts any capitalized name as a class.
+fn check_type_classes(
+    declared_type: &Type,
+    start: usize,
+    scope: Scope,
+    errors: &mut Vec<CheckError>,
+) -> Option<()> {
+    match declared_type {
+        Type::Class(class) => check_class(class, start, scope, errors),
+        // `Vec<<element>>`
+        Type::Vec(element) => check_type_classes(element, start + 2, scope, errors),
+        _ => Some(()),
+    }
+}
+
+/// Checks that a value of type `value_type` can be stored in `name` of type `target`. Whether a
+/// number converts without losing information depends on its value, which [`check_constant`]
+/// checks for constants and which is only known when executing otherwise, but floats and
+/// decimals can't be stored in integers without a cast.
+fn check_storable(value_type: Type, target: &Type, name: &str) -> Result<(), RuntimeError> {
+    match (value_type, target) {
+        (Type::Number(value_type), Type::Number(target))
+            if has_fraction(value_type) && !has_fraction(*target) =>
+        {
+            Err(fractional_in_integer(value_type, *target))
+        }
+        (Type::Number(_), Type::Number(_)) => Ok(()),
+        (value_type, target) if value_type == *target => Ok(()),
+        (value_type, target) => Err(RuntimeError::TypeError {
+            code: &error_codes::MISMATCHED_TYPES,
+            message: format!(
+                "Cannot store a value of type `{value_type}` in `{name}` of type `{target}`."
+            ),
+        }),
+    }
+}
+
+/// Whether numbers of `number_type` can have a fractional part.
+fn has_fraction(number_type: NumberType) -> bool {
+    number_type.is_floating_point() || number_type == NumberType::Decimal
+}
+
+fn fractional_in_integer(value_type: NumberType, target: NumberType) -> RuntimeError {
+    RuntimeError::TypeError {
+        code: &error_codes::MISMATCHED_TYPES,
+        message: format!(
+            "Cannot store a `{value_type}` in a `{target}` without an explicit cast like `as {target}`, as it can have a fractional part."
+        ),
+    }
+}
+
+/// Checks that `constant`, the value of an operand if it is a constant, fits in `name` of type
+/// `target`, like `300` which doesn't fit in a `u8`, with the error the executer would give.
+fn check_constant(constant: Option<Number>, target: &Type, name: &str) -> Result<(), RuntimeError> {
+    match constant {
+        Some(number) if matches!(target, Type::Number(_)) => {
+            executer::convert(Value::Number(number), target, name).map(drop)
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Gives back the constant computed by an operation on constants, reporting its error at
+/// `tokens` like the optimizer would. Type errors are left to the executer, like it does.
+fn fold_constant(
+    result: Result<Number, RuntimeError>,
+    tokens: Range<usize>,
+    errors: &mut Vec<CheckError>,
+) -> Option<Number> {
+    match result {
+        Ok(number) => Some(number),
+        Err(RuntimeError::TypeError { .. }) => None,
+        Err(error) => {
+            errors.push(CheckError::new(
+                error,
+                tokens,
+                String::from("computed before running"),
+            ));
+            None
+        }
+    }
+}
+
+/// Checks that a value of type `value_type` can be an element of a `Vec<element_type>`. Like
+/// in declarations, any number can be stored in a number, except for floats and decimals in
+/// integers.
+fn check_element(value_type: &Type, element_type: &Type) -> Result<(), RuntimeError> {
+    match (value_type, element_type) {
+        (Type::Number(value_type), Type::Number(element_type))
+            if has_fraction(*value_type) && !has_fraction(*element_type) =>
+        {
+            Err(fractional_in_integer(*value_type, *element_type))
+        }
+        (Type::Number(_), Type::Number(_)) => Ok(()),
+        (value_type, element_type) if value_type == element_type => Ok(()),
+        (value_type, element_type) => Err(RuntimeError::TypeError {
+            code: &error_codes::MISMATCHED_TYPES,
+            message: format!(
+                "Cannot store a value of type `{value_type}` in a `Vec<{element_type}>`."
+            ),
+        }),
+    }
+}
+
+/// Returns the type of the elements of `operand`, reporting it if it isn't a `Vec` it can
+/// `action`.
+fn element_type(operand: &Operand, action: &str, errors: &mut Vec<CheckError>) -> Option<Type> {
+    match &operand.value_type {
+        Some(Type::Vec(element)) => Some(*element.clone()),
+        Some(found) => {
+            errors.push(CheckError::new(
+                executer::not_a_vec(action, found),
+                operand.tokens.clone(),
+                format!("this is a `{found}`"),
+            ));
+            None
+        }
+        None => None,
+    }
+}
+
+/// Checks that `index` is an integer.
+fn check_index(index: &Operand, errors: &mut Vec<CheckError>) -> Option<()> {
+    match &index.value_type {
+        Some(Type::Number(number_type))
+            if !number_type.is_floating_point() && *number_type != NumberType::Decimal =>
+        {
+            Some(())
+        }
+        Some(found) => {
+            errors.push(CheckError::new(
+                RuntimeError::TypeError {
+                    code: &error_codes::MISMATCHED_TYPES,
+                    message: format!(
+                        "An index must be an integer, found a value of type `{found}`."
+                    ),
+                },
+                index.tokens.clone(),
+                format!("expected an integer, found `{found}`"),
+            ));
+            None
+        }
+        None => None,
+    }
+}
+
+fn expected_number(operation: &str, found: &Type, tokens: Range<usize>) -> CheckError {
+    CheckError::new(
+        RuntimeError::TypeError {
+            code: &error_codes::EXPECTED_NUMBER,
+            message: format!(
+                "Cannot {operation} a value of type `{found}`, which is not a number."
+            ),
+        },
+        tokens,
+        format!("this is a `{found}`"),
+    )
+}
+
+/// Computes the type of `expression` like [`expression_type`], where a value of type `expected`
+/// is needed: `Vec` literals, even in parentheses, then get the type of its elements.
+fn expected_type(
+    expression: &mut Expression,
+    start: usize,
+    expected: Option<&Type>,
+    scope: Scope,
+    errors: &mut Vec<CheckError>,
+) -> Operand {
+    let expected_element = match expected {
+        Some(Type::Vec(element)) => Some(&**element),
+        _ => None,
+    };
+    match expression {
+        Expression::Vec {
+            elements,
+            element_type,
+        } => vec_literal_type(
+            elements,
+            element_type,
+            start,
+            expected_element,
+            scope,
+            errors,
+        ),
+        Expression::ParenthesisExpression(expr) => {
+            // `(<expr>)`
+            let operand = expected_type(expr, start + 1, expected, scope, errors);
+            Operand {
+                value_type: operand.value_type,
+                tokens: start..operand.tokens.end + 1,
+                constant: operand.constant,
+            }
+        }
+        expression => expression_type(expression, start, scope, errors),
+    }
+}
+
+/// Computes the type of the `Vec` literal of `elements`, whose `[` is the token at index
+/// `start`, filling in `element_type`. The elements have the type `expected`, or else the type
+/// of the first one.
+fn vec_literal_type(
+    elements: &mut [Expression],
+    element_type: &mut Option<Type>,
+    start: usize,
+    expected: Option<&Type>,
+    scope: Scope,
+    errors: &mut Vec<CheckError>,
+) -> Operand {
+    // `[<element>, <element>]`, where an element is followed by `,` or `]`.
+    let mut next = start + 1;
+    let mut elements_type = expected.cloned();
+    let mut valid = true;
+    for element in elements.iter_mut() {
+        let element = expected_type(element, next, elements_type.as_ref(), scope, errors);
+        next = element.tokens.end + 1;
+        match (&elements_type, element.value_type) {
+            (_, None) => valid = false,
+            (None, Some(value_type)) => elements_type = Some(value_type),
+            (Some(expected), Some(value_type)) => {
+                if let Err(error) = check_element(&value_type, expected) {
+                    errors.push(CheckError::new(
+                        error,
+                        element.tokens,
+                        format!("expected `{expected}`, found `{value_type}`"),
+                    ));
+                    valid = false;
+                }
+            }
+        }
+    }
+    if elements.is_empty() {
+        next += 1;
+    }
+    let tokens = start..next;
+    let value_type = match elements_type {
+        Some(elements_type) => {
+            *element_type = Some(elements_type.clone());
+            valid.then(|| Type::Vec(Box::new(elements_type)))
+        }
+        None if elements.is_empty() => {
+            errors.push(CheckError::new(
+                executer::unknown_element_type(),
+                tokens.clone(),
+                String::from("type of the elements unknown"),
+            ));
+            None
+        }
+        None => None,
+    };
+    Operand {
+        value_type,
+        tokens,
+        constant: None,
+    }
+}
+
+/// Computes the type of a call to the method `method` of a `Vec<element>`, whose name is the
+/// token at index `method_index`, returning it along with the index of the token after the `)`.
+/// `element` is `None` when the `Vec` has an error.
+fn vec_method_type(
+    element: Option<&Type>,
+    method: &str,
+    arguments: &mut [Expression],
+    method_index: usize,
+    scope: Scope,
+    errors: &mut Vec<CheckError>,
+) -> (Option<Type>, usize) {
+    let method_tokens = method_index..method_index + 1;
+    let lparen_index = method_index + 1;
+    if array::LAMBDA_METHODS.contains(&method) {
+        if let [Expression::Lambda {
+            parameter,
+            body,
+            result_type,
+        }] = arguments
+        {
+            // `(<parameter> => <body>)`
+            let local = Local {
+                name: parameter,
+                value_type: element.cloned(),
+                outer: scope.locals,
+            };
+            let scope = Scope {
+                locals: Some(&local),
+                ..scope
+            };
+            let body = expression_type(body, lparen_index + 3, scope, errors);
+            let end = body.tokens.end + 1;
+            let value_type = match (element, body.value_type) {
+                (None, _) | (_, None) => None,
+                (Some(_), Some(body_type)) if method == "map" => {
+                    *result_type = Some(body_type.clone());
+                    Some(Type::Vec(Box::new(body_type)))
+                }
+                (Some(element), Some(Type::Bool)) => Some(Type::Vec(Box::new(element.clone()))),
+                (Some(_), Some(found)) => {
+                    errors.push(CheckError::new(
+                        RuntimeError::TypeError {
+                            code: &error_codes::MISMATCHED_TYPES,
+                            message: format!(
+                                "The lambda of `filter` should return a `bool`, found a value of type `{found}`."
+                            ),
+                        },
+                        body.tokens,
+                        format!("expected `bool`, found `{found}`"),
+                    ));
+                    None
+                }
+            };
+            return (value_type, end);
+        }
+        let (_, end) = check_arguments(arguments, lparen_index, None, scope, errors);
+        errors.push(CheckError::new(
+            array::expected_lambda(method, arguments.len()),
+            method_tokens,
+            String::from("takes a lambda"),
+        ));
+        return (None, end);
+    }
+
+    let element = element.expect("Only `map` and `filter` are checked without an element type");
+    let (arguments, end) = check_arguments(arguments, lparen_index, Some(element), scope, errors);
+    let vec_type = Type::Vec(Box::new(element.clone()));
+    let Some(parameters) = array::method_parameters(method) else {
+        errors.push(CheckError::new(
+            executer::unknown_member(&vec_type, "method", method),
+            method_tokens,
+            format!("not a method of `{vec_type}`"),
+        ));
+        return (None, end);
+    };
+    if arguments.len() != parameters {
+        errors.push(CheckError::new(
+            array::wrong_argument_count(method, parameters, arguments.len()),
+            method_tokens,
+            format!(
+                "takes {parameters} argument{}",
+                if parameters == 1 { "" } else { "s" }
+            ),
+        ));
+        return (None, end);
+    }
+    let mut valid = arguments
+        .iter()
+        .all(|argument| argument.value_type.is_some());
+    let mut expect = |index: usize, expected: &Type| {
+        let argument = &arguments[index];
+        let Some(found) = &argument.value_type else {
+            return;
+        };
+        if check_element(found, expected).is_err() {
+            errors.push(CheckError::new(
+                RuntimeError::TypeError {
+                    code: &error_codes::MISMATCHED_TYPES,
+                    message: format!(
+                        "Argument {} of `{method}` should be a `{expected}`, found a value of type `{found}`.",
+                        index + 1
+                    ),
+                },
+                argument.tokens.clone(),
+                format!("expected `{expected}`, found `{found}`"),
+            ));
+            valid = false;
+        }
+    };
+    match method {
+        "push" | "contains" => expect(0, element),
+        "insert" => expect(1, element),
+        "join" => expect(0, &Type::String),
+        _ => {}
+    }
+    if matches!(method, "insert" | "remove") && check_index(&arguments[0], errors).is_none() {
+        valid = false;
+    }
+    let unsupported = match method {
+        "sort" if !array::is_ordered(element) => Some(array::unordered(element)),
+        "join" if !array::is_joinable(element) => Some(array::unjoinable(element)),
+        _ => None,
+    };
+    if let Some(error) = unsupported {
+        errors.push(CheckError::new(
+            error,
+            method_tokens,
+            format!("not a method of `{vec_type}`"),
+        ));
+        valid = false;
+    }
+    let value_type = match method {
+        "pop" | "remove" => element.clone(),
+        "len" => Type::Number(NumberType::Int64),
+        "contains" => Type::Bool,
+        "join" => Type::String,
+        _ => vec_type,
+    };
+    (valid.then_some(value_type), end)
+}
+
+/// Computes the type of `expression`, whose first token is at index `start`, adding its errors
+/// to `errors`. Operations on operands with errors aren't checked, so that an error is only
+/// reported once.
+fn expression_type(
+    expression: &mut Expression,
+    start: usize,
+    scope: Scope,
+    errors: &mut Vec<CheckError>,
+) -> Operand {
+    let environment = scope.environment;
+    let mut constant = None;
+    let (value_type, end) = match expression {
+        Expression::UnaryOperation { operator, expr } => {
+            // `<operator><expr>`
+            let operand = expression_type(expr, start + 1, scope, errors);
+            let tokens = start..operand.tokens.end;
+            let value_type = match operand.value_type {
+                Some(Type::Number(number_type))
+                    if matches!(operator, UnaryOperator::Minus)
+                        && !number_type.is_signed()
+                        && !number_type.is_floating_point() =>
+                {
+                    errors.push(CheckError::new(
+                        RuntimeError::TypeError {
+                            code: &error_codes::NEGATED_UNSIGNED,
+                            message: format!(
+                                "Cannot negate a value of unsigned type `{number_type}`."
+                            ),
+                        },
+                        tokens.clone(),
+                        format!("`{number_type}` can't be negative"),
+                    ));
+                    None
+                }
+                Some(Type::Number(number_type)) => Some(Type::Number(number_type)),
+                Some(found) => {
+                    errors.push(expected_number(
+                        "apply an unary operator to",
+                        &found,
+                        operand.tokens,
+                    ));
+                    None
+                }
+                None => None,
+            };
+            if let (Some(_), Some(number)) = (&value_type, operand.constant) {
+                let result = match operator {
+                    UnaryOperator::Plus => Ok(number),
+                    UnaryOperator::Minus => executer::negate(&number, environment.promote_overflow),
+                };
+                constant = fold_constant(result, tokens.clone(), errors);
+            }
+            (value_type, tokens.end)
+        }
+        Expression::BinaryOperations { first, operations } => {
+            // `<first> <operator> <operand> ...`, each operator applying to the ones before it
+            let mut left = expression_type(first, start, scope, errors);
+            for (operator, operand) in operations {
+                let mut right = expression_type(operand, left.tokens.end + 1, scope, errors);
+                let end = right.tokens.end;
+                let constants = left.constant.take().zip(right.constant.take());
+                let value_type = operation_type(operator, left, right, errors);
+                let constant = match (&value_type, constants) {
+                    (Some(_), Some((left, right))) => {
+                        let promote_overflow = environment.promote_overflow;
+                        let result =
+                            executer::binary_operation(operator, &left, &right, promote_overflow);
+                        fold_constant(result, start..end, errors)
+                    }
+                    _ => None,
+                };
+                left = Operand {
+                    value_type,
+                    tokens: start..end,
+                    constant,
+                };
+            }
+            constant = left.constant;
+            (left.value_type, left.tokens.end)
+        }
+        Expression::Cast { expr, target } => {
+            // `<expr> as <target>`
+            let operand = expression_type(expr, start, scope, errors);
+            let value_type = match operand.value_type {
+                Some(Type::Number(_)) => Some(Type::Number(*target)),
+                Some(found) => {
+                    errors.push(expected_number("cast", &found, operand.tokens.clone()));
+                    None
+                }
+                None => None,
+            };
+            if value_type.is_some() {
+                constant = operand.constant.map(|number| number.cast(*target));
+            }
+            (value_type, operand.tokens.end + 2)
+        }
+        Expression::ParenthesisExpression(expr) => {
+            // `(<expr>)`
+            let operand = expression_type(expr, start + 1, scope, errors);
+            constant = operand.constant;
+            (operand.value_type, operand.tokens.end + 1)
+        }
+        Expression::Call {
+            function,
+            arguments,
+        } => {
+            // `<function>(<arguments>)`
+            let name_tokens = start..start + 1;
+            let (arguments, end) = check_arguments(arguments, start + 1, None, scope, errors);
+            let value_type = match environment.get_function(function) {
+                Some(function) => check_call(function, name_tokens, &arguments, scope, errors),
+                None => {
+                    errors.push(CheckError::new(
+                        executer::undefined_function(function),
+                        name_tokens,
+                        String::from("not registered"),
+                    ));
+                    None
+                }
+            };
+            (value_type, end)
+        }
+        Expression::Field { object, field } => {
+            // `<object>.<field>`
+            let object = expression_type(object, start, scope, errors);
+            let field_index = object.tokens.end + 1;
+            let value_type = object.value_type.and_then(|object_type| {
+                let getter =
+                    executer::get_member(environment, &object_type, field, "field", |class| {
+                        &class.fields
+                    });
+                match getter {
+                    Ok(getter) => Some(getter.return_type.clone()),
+                    Err(error) => {
+                        errors.push(CheckError::new(
+                            error,
+                            field_index..field_index + 1,
+                            format!("not a field of `{object_type}`"),
+                        ));
+                        None
+                    }
+                }
+            });
+            (value_type, field_index + 1)
+        }
+        Expression::MethodCall {
+            object,
+            method,
+            arguments,
+        } => {
+            // `<object>.<method>(<arguments>)`
+            let object = expression_type(object, start, scope, errors);
+            let method_index = object.tokens.end + 1;
+            match &object.value_type {
+                Some(Type::Vec(element)) => {
+                    return typed_operand(
+                        start,
+                        vec_method_type(
+                            Some(element),
+                            method,
+                            arguments,
+                            method_index,
+                            scope,
+                            errors,
+                        ),
+                    );
+                }
+                None if array::LAMBDA_METHODS.contains(&method.as_str()) => {
+                    return typed_operand(
+                        start,
+                        vec_method_type(None, method, arguments, method_index, scope, errors),
+                    );
+                }
+                _ => {}
+            }
+            let method_tokens = method_index..method_index + 1;
+            let (arguments, end) =
+                check_arguments(arguments, method_index + 1, None, scope, errors);
+            let value_type = object.value_type.and_then(|object_type| {
+                let method =
+                    executer::get_member(environment, &object_type, method, "method", |class| {
+                        &class.methods
+                    });
+                match method {
+                    Ok(method) => check_call(method, method_tokens, &arguments, scope, errors),
+                    Err(error) => {
+                        errors.push(CheckError::new(
+                            error,
+                            method_tokens,
+                            format!("not a method of `{object_type}`"),
+                        ));
+                        None
+                    }
+                }
+            });
+            (value_type, end)
+        }
+        Expression::StaticMember { class, member } => {
+            // `<class>::<member>`
+            let value_type =
+                check_class(class, start, scope, errors).and_then(
+                    |()| match executer::get_constant(environment, class, member) {
+                        Ok(constant) => Some(Value::value_type(constant)),
+                        Err(error) => {
+                            errors.push(CheckError::new(
+                                error,
+                                start + 2..start + 3,
+                                format!("not a constant of `{class}`"),
+                            ));
+                            None
+                        }
+                    },
+                );
+            (value_type, start + 3)
+        }
+        Expression::StaticCall {
+            class,
+            function,
+            arguments,
+        } => {
+            // `<class>::<function>(<arguments>)`
+            let function_tokens = start + 2..start + 3;
+            let (arguments, end) = check_arguments(arguments, start + 3, None, scope, errors);
+            let value_type = check_class(class, start, scope, errors).and_then(|()| {
+                match executer::get_static_function(environment, class, function) {
+                    Ok(function) => {
+                        check_call(function, function_tokens, &arguments, scope, errors)
+                    }
+                    Err(error) => {
+                        errors.push(CheckError::new(
+                            error,
+                            function_tokens,
+                            format!("not a static function of `{class}`"),
+                        ));
+                        None
+                    }
+                }
+            });
+            (value_type, end)
+        }
+        Expression::Vec {
+            elements,
+            element_type,
+        } => {
+            let operand = vec_literal_type(elements, element_type, start, None, scope, errors);
+            (operand.value_type, operand.tokens.end)
+        }
+        Expression::Index { object, index } => {
+            // `<object>[<index>]`
+            let object = expression_type(object, start, scope, errors);
+            let index = expression_type(index, object.tokens.end + 1, scope, errors);
+            let element = element_type(&object, "index", errors);
+            let value_type = check_index(&index, errors).and(element);
+            (value_type, index.tokens.end + 1)
+        }
+        Expression::Lambda {
+            parameter, body, ..
+        } => {
+            // `<parameter> => <body>`, whose body is still checked for its own errors.
+            let local = Local {
+                name: parameter,
+                value_type: None,
+                outer: scope.locals,
+            };
+            let scope = Scope {
+                locals: Some(&local),
+                ..scope
+            };
+            let body = expression_type(body, start + 2, scope, errors);
+            errors.push(CheckError::new(
+                executer::misplaced_lambda(),
+                start..body.tokens.end,
+                String::from("not the argument of `map` or `filter`"),
+            ));
+            (None, body.tokens.end)
+        }
+        Expression::Variable(name) if scope.local(name).is_some() => {
+            let local = scope.local(name).expect("The local was found");
+            (local.value_type.clone(), start + 1)
+        }
+        Expression::Variable(name) if scope.untyped.contains(name) => (None, start + 1),
+        Expression::Variable(name) if scope.pending.contains_key(name) => {
+            errors.push(pending_use(name, start, scope.pending));
+            (None, start + 1)
+        }
+        Expression::Variable(name) => {
+            let value_type = environment.get_type(name);
+            if value_type.is_none() {
+                errors.push(CheckError::new(
+                    executer::undefined_variable(name),
+                    start..start + 1,
+                    String::from("not declared"),
+                ));
+            }
+            (value_type, start + 1)
+        }
+        Expression::Number(number) => {
+            constant = Some(number.clone());
+            (Some(Type::Number(number.number_type())), start + 1)
+        }
+        Expression::Bool(_) => (Some(Type::Bool), start + 1),
+        Expression::Char(_) => (Some(Type::Char), start + 1),
+        Expression::String(_) => (Some(Type::String), start + 1),
+    };
+    Operand {
+        value_type,
+        tokens: start..end,
+        constant,
+    }
+}
+
+/// Computes the type of `<left> <operator> <right>`, adding its errors to `errors`.
+fn operation_type(
+    operator: &BinaryOperator,
+    left: Operand,
+    right: Operand,
+    errors: &mut Vec<CheckError>,
+) -> Option<Type> {
+    let operator_index = left.tokens.end;
+    match (left.value_type, right.value_type) {
+        (Some(Type::Number(left)), Some(Type::Number(right))) => {
+            match executer::promote(left, right, operator) {
+                Ok(result) => Some(Type::Number(result)),
+                Err(error) => {
+                    errors.push(CheckError::new(
+                        error,
+                        operator_index..operator_index + 1,
+                        format!("operands of type `{left}` and `{right}`"),
+                    ));
+                    None
+                }
+            }
+        }
+        (left_type, right_type) => {
+            // Both operands are reported when neither is a number.
+            let operation = format!("apply `{}` to", operator.symbol());
+            for (operand_type, tokens) in [(left_type, left.tokens), (right_type, right.tokens)] {
+                if let Some(found) = operand_type.filter(|found| !matches!(found, Type::Number(_)))
+                {
+                    errors.push(expected_number(&operation, &found, tokens));
+                }
+            }
+            None
+        }
+    }
+}
+
+/// Makes the operand starting at the token at index `start`, from its type and the index of the
+/// token after it.
+fn typed_operand(start: usize, (value_type, end): (Option<Type>, usize)) -> Operand {
+    Operand {
+        value_type,
+        tokens: start..end,
+        constant: None,
+    }
+}
+
+/// Checks that `class`, the token at index `start`, is registered.
+fn check_class(
+    class: &str,
+    start: usize,
+    scope: Scope,
+    errors: &mut Vec<CheckError>,
+) -> Option<()> {
+    let environment = scope.environment;
+    match environment.get_class(class) {
+        Ok(_) => Some(()),
+        Err(error) => {
+            errors.push(CheckError::new(
+                error,
+                start..start + 1,
+                String::from("unknown class"),
+            ));
+            None
+        }
+    }
+}
+
+/// Checks `arguments`, whose `(` is the token at index `lparen_index`, returning them along with
+/// the index of the token after the `)`. `Vec` literals get `expected` as the type of their
+/// elements.
+fn check_arguments(
+    arguments: &mut [Expression],
+    lparen_index: usize,
+    expected: Option<&Type>,
+    scope: Scope,
+    errors: &mut Vec<CheckError>,
+) -> (Vec<Operand>, usize) {
+    // `(<argument>, <argument>)`, where an argument is followed by `,` or `)`.
+    let mut next = lparen_index + 1;
+    let expected = expected.map(|element| Type::Vec(Box::new(element.clone())));
+    let arguments = arguments
+        .iter_mut()
+        .map(|argument| {
+            let argument = expected_type(argument, next, expected.as_ref(), scope, errors);
+            next = argument.tokens.end + 1;
+            argument
+        })
+        .collect::<Vec<_>>();
+    if arguments.is_empty() {
+        next += 1;
+    }
+    (arguments, next)
+}
+
+/// Checks that `arguments` can be passed to `function`, named by `name_tokens`, returning the
+/// type it returns. Like in declarations, any number can be passed for a number parameter, and
+/// whether it fits is only known when executing. `Vec` parameters take the `Vec`s whose elements
+/// could be stored in them, which are converted element by element.
+fn check_call(
+    function: &NativeFunction,
+    name_tokens: Range<usize>,
+    arguments: &[Operand],
+    scope: Scope,
+    errors: &mut Vec<CheckError>,
+) -> Option<Type> {
+    let environment = scope.environment;
+    if let Err(error) = environment
+        .sandbox
+        .check_capability(function.capability, &function.name)
+    {
+        errors.push(CheckError::new(
+            error,
+            name_tokens,
+            String::from("disabled by the sandbox"),
+        ));
+        return None;
+    }
+    if arguments.len() != function.parameters.len() {
+        errors.push(CheckError::new(
+            function.wrong_argument_count(arguments.len()),
+            name_tokens,
+            format!(
+                "takes {} argument{}",
+                function.parameters.len(),
+                if function.parameters.len() == 1 {
+                    ""
+                } else {
+                    "s"
+                }
+            ),
+        ));
+        return None;
+    }
+    let mut valid = true;
+    for (index, (argument, parameter)) in arguments.iter().zip(&function.parameters).enumerate() {
+        match (&argument.value_type, parameter) {
+            (None, _) => valid = false,
+            (Some(Type::Number(_)), Type::Number(_)) => {}
+            (Some(Type::Vec(found)), Type::Vec(element))
+                if check_element(found, element).is_ok() => {}
+            (Some(found), parameter) if found == parameter => {}
+            (Some(found), parameter) => {
+                errors.push(CheckError::new(
+                    RuntimeError::TypeError {
+                        code: &error_codes::MISMATCHED_TYPES,
+                        message: format!(
+                            "Argument {} of `{}` should be a `{parameter}`, found a value of type `{found}`.",
+                            index + 1,
+                            function.name
+                        ),
+                    },
+                    argument.tokens.clone(),
+                    format!("expected `{parameter}`, found `{found}`"),
+                ));
+                valid = false;
+            }
+        }
+    }
+    valid.then(|| function.return_type.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Interpreter;
+
+    /// Type checks `source`, returning the code of each error along with the part of the source
+    /// it points at.
+    fn errors(source: &str) -> Vec<(&'static str, &str)> {
+        let diagnostics = Interpreter::new().compile(source).err().unwrap_or_default();
+        diagnostics
+            .iter()
+            .map(|diagnostic| {
+                let span = diagnostic.span.clone().expect("Type errors have a span");
+                (diagnostic.code.code, &source[span])
+            })
+            .collect()
+    }
+
+    #[test]
+    fn rejects_mixing_signed_and_unsigned_integers() {
+        let source = "i32 a = 1; u8 b = 2u8; a + b; a * 2u8;";
+        assert_eq!(errors(source), [("T0022", "+"), ("T0022", "*")]);
+        assert!(errors("i32 a = 1; u8 b = 2u8; a + b as i32; a as u8 + b;").is_empty());
+    }
+
+    #[test]
+    fn rejects_stores_that_can_lose_the_fractional_part() {
+        let source = "i32 a = 1.5; f64 x = 2f64; i64 b = x; decimal d = 1d; u8 c = d;";
+        let codes = errors(source)
+            .into_iter()
+            .map(|(code, _)| code)
+            .collect::<Vec<_>>();
+        assert_eq!(codes, ["T0021", "T0021", "T0021"]);
+        assert!(errors("f64 x = 2.5f64; i32 a = x as i32; f64 y = 1;").is_empty());
+    }
+
+    #[test]
+    fn rejects_constants_out_of_the_range_of_their_variable() {
+        assert_eq!(
+            errors("u8 a = 300; i8 b = -129; u8 c = -1;"),
+            [("T0021", "300"), ("T0021", "-129"), ("T0021", "-1")]
+        );
+        assert!(errors("u8 a = 255; i8 b = -128; i64 c = 2147483648;").is_empty());
+    }
+
+    #[test]
+    fn reports_every_error_before_running() {
+        let source = "u8 a = 300; i32 b = 1.5; i32 c = 1; c + 1u8;";
+        assert_eq!(errors(source).len(), 3);
+    }
+
+    #[test]
+    fn rejects_assigning_let_variables() {
+        assert_eq!(errors("let a = 1; a = 2;"), [("T0042", "a")]);
+        assert!(errors("var a = 1; a = 2;").is_empty());
+    }
+
+    #[test]
+    fn infers_the_elements_of_an_empty_vec_from_the_first_one_added() {
+        assert!(errors("var v = []; v.push(1); v.push(2);").is_empty());
+        assert!(errors("var v = []; v.insert(0, \"a\"); v.len();").is_empty());
+        let codes = |source| {
+            errors(source)
+                .into_iter()
+                .map(|(code, _)| code)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(codes("var v = []; v.push(1); v.push(\"a\");"), ["T0021"]);
+        // Nothing tells the type of the elements before the `Vec` is used.
+        assert_eq!(codes("var v = []; v.len();"), ["T0046"]);
+        assert_eq!(codes("var v = [];"), ["T0046"]);
+    }
+}
@@ -0,0 +1,231 @@
+use tray::{diagnostic::ErrorFormat, Engine};
+
+pub const DEFAULT_MAX_ERRORS: usize = 20;
+
+pub const USAGE: &str = "\
+Usage: tray [options] [command]
+
+Commands:
+  run <file> [args...]  Execute a file, whose `args` variable holds the arguments after it
+  repl                  Start an interactive session (the default)
+  check <file>          Lex, parse and type check a file without executing it
+  check --fix <file>    Apply the suggested fixes to a file after showing a diff of them
+  fmt [files...]        Format files in place, or stdin to stdout without files
+  tokens <file>         Print the tokens of a file
+  ast <file>            Print the parse tree of a file
+  build <file>          Compile a file to a `.trayc` module, which `run` uses while it is newer
+  disasm <file>         Print the bytecode of a file or of a `.trayc` module
+  explain <code>        Explain an error code like `T0003`
+  <file>                Same as `run <file>`
+
+A file name of `-` reads the program from stdin. `run` also executes `.trayc` modules.
+
+Options:
+  -e <code>             Execute <code> and print the value of its expressions
+  --promote-overflow    Turn integer overflows into `bigint` results instead of errors
+  --error-format <fmt>  Write errors as `human` text (the default) or as one `json` object
+                        per line
+  --max-errors <n>      Report at most <n> errors (20 by default)
+  --engine <engine>     Execute with the bytecode `vm` (the default) or the `tree`-walking
+                        interpreter
+  --differential        Execute with both engines and fail if their results differ
+  --dump-ast            Print the parse tree before and after the optimizations to stderr
+  -h, --help            Print this help
+  -V, --version         Print the version
+
+Exit codes: 0 on success, 1 on a runtime error, 2 on a compile or usage error.";
+
+#[derive(Debug)]
+pub enum Command {
+    /// The arguments after the file are given to the program as its `args` variable.
+    Run {
+        file: String,
+        arguments: Vec<String>,
+    },
+    Eval {
+        source: String,
+    },
+    Repl,
+    /// With `fix`, the suggested fixes are applied to the file after showing them as a diff.
+    Check {
+        file: String,
+        fix: bool,
+    },
+    Fmt {
+        files: Vec<String>,
+    },
+    Tokens {
+        file: String,
+    },
+    Ast {
+        file: String,
+    },
+    /// Writes the bytecode of the file to a `.trayc` module next to it.
+    Build {
+        file: String,
+    },
+    Disasm {
+        file: String,
+    },
+    Explain {
+        code: String,
+    },
+    Help,
+    Version,
+}
+
+#[derive(Debug)]
+pub struct Options {
+    pub command: Command,
+    pub promote_overflow: bool,
+    pub error_format: ErrorFormat,
+    /// How many errors are reported at most before the others are only counted.
+    pub max_errors: usize,
+    pub engine: Engine,
+    /// Whether programs are executed with both engines to compare their results.
+    pub differential: bool,
+    /// Whether the parse tree is printed before and after being optimized.
+    pub dump_ast: bool,
+}
+
+/// Parses the command line arguments, without the program name. Returns a message describing
+/// the problem if they are invalid.
+pub fn parse_arguments(arguments: impl IntoIterator<Item = String>) -> Result<Options, String> {
+    let mut arguments = arguments.into_iter();
+    let mut promote_overflow = false;
+    let mut error_format = ErrorFormat::Human;
+    let mut max_errors = DEFAULT_MAX_ERRORS;
+    let mut engine = Engine::default();
+    let mut differential = false;
+    let mut dump_ast = false;
+    let command = loop {
+        let Some(argument) = arguments.next() else {
+            break Command::Repl;
+        };
+        match argument.as_str() {
+            "--promote-overflow" => promote_overflow = true,
+            "--error-format" => {
+                error_format = parse_error_format(arguments.next().as_deref().unwrap_or(""))?
+            }
+            option if option.starts_with("--error-format=") => {
+                error_format = parse_error_format(&option["--error-format=".len()..])?
+            }
+            "--max-errors" => {
+                max_errors = parse_max_errors(arguments.next().as_deref().unwrap_or(""))?
+            }
+            option if option.starts_with("--max-errors=") => {
+                max_errors = parse_max_errors(&option["--max-errors=".len()..])?
+            }
+            "--engine" => engine = parse_engine(arguments.next().as_deref().unwrap_or(""))?,
+            option if option.starts_with("--engine=") => {
+                engine = parse_engine(&option["--engine=".len()..])?
+            }
+            "--differential" => differential = true,
+            "--dump-ast" => dump_ast = true,
+            "-h" | "--help" => break Command::Help,
+            "-V" | "--version" => break Command::Version,
+            "-e" => match arguments.next() {
+                Some(source) => break Command::Eval { source },
+                None => return Err(String::from("`-e` expects the code to execute.")),
+            },
+            "repl" => break Command::Repl,
+            "run" => {
+                break Command::Run {
+                    file: expect_file(&mut arguments, "run")?,
+                    arguments: arguments.by_ref().collect(),
+                };
+            }
+            "check" => {
+                let mut file = expect_file(&mut arguments, "check")?;
+                let fix = file == "--fix";
+                if fix {
+                    file = expect_file(&mut arguments, "check")?;
+                    if file == "-" {
+                        return Err(String::from(
+                            "`check --fix` can't write the fixes to stdin.",
+                        ));
+                    }
+                }
+                break Command::Check { file, fix };
+            }
+            "tokens" => {
+                break Command::Tokens {
+                    file: expect_file(&mut arguments, "tokens")?,
+                }
+            }
+            "ast" => {
+                break Command::Ast {
+                    file: expect_file(&mut arguments, "ast")?,
+                }
+            }
+            "build" => {
+                let file = expect_file(&mut arguments, "build")?;
+                if file == "-" {
+                    return Err(String::from("`build` can't write a module for stdin."));
+                }
+                break Command::Build { file };
+            }
+            "disasm" => {
+                break Command::Disasm {
+                    file: expect_file(&mut arguments, "disasm")?,
+                }
+            }
+            "explain" => match arguments.next() {
+                Some(code) => break Command::Explain { code },
+                None => return Err(String::from("`explain` expects an error code.")),
+            },
+            "fmt" => {
+                break Command::Fmt {
+                    files: arguments.by_ref().collect(),
+                }
+            }
+            option if option.starts_with('-') && option != "-" => {
+                return Err(format!("Unknown option `{option}`."));
+            }
+            _ => {
+                break Command::Run {
+                    file: argument,
+                    arguments: arguments.by_ref().collect(),
+                }
+            }
+        }
+    };
+
+    if let Some(argument) = arguments.next() {
+        return Err(format!("Unexpected argument `{argument}`."));
+    }
+    Ok(Options {
+        command,
+        promote_overflow,
+        error_format,
+        max_errors,
+        engine,
+        differential,
+        dump_ast,
+    })
+}
+
+fn parse_engine(name: &str) -> Result<Engine, String> {
+    Engine::from_name(name)
+        .ok_or_else(|| format!("Unknown engine `{name}`. Expected `vm` or `tree`."))
+}
+
+fn parse_max_errors(count: &str) -> Result<usize, String> {
+    count
+        .parse()
+        .map_err(|_| format!("`--max-errors` expects a number, found `{count}`."))
+}
+
+fn parse_error_format(name: &str) -> Result<ErrorFormat, String> {
+    ErrorFormat::from_name(name)
+        .ok_or_else(|| format!("Unknown error format `{name}`. Expected `human` or `json`."))
+}
+
+fn expect_file(
+    arguments: &mut impl Iterator<Item = String>,
+    command: &str,
+) -> Result<String, String> {
+    arguments
+        .next()
+        .ok_or_else(|| format!("`{command}` expects a file name."))
+}
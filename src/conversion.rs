@@ -0,0 +1,147 @@
+use crate::{
+    array::Array,
+    bigint::BigInt,
+    decimal::Decimal,
+    error_codes,
+    executer::{RuntimeError, Value},
+    native::TrayType,
+    parser::{Number, NumberType, Type},
+};
+
+/// Converts a Rust value into a tray [`Value`].
+pub trait IntoTray {
+    fn into_tray(self) -> Value;
+}
+
+/// Converts a tray [`Value`] back into a Rust value. Numbers are converted between types as long
+/// as they don't lose information, like a tray `i8` read as an `i64`.
+pub trait FromTray: Sized {
+    fn from_tray(value: Value) -> Result<Self, RuntimeError>;
+}
+
+fn mismatched_types(value: &Value, expected: Type) -> RuntimeError {
+    RuntimeError::TypeError {
+        code: &error_codes::MISMATCHED_TYPES,
+        message: format!(
+            "Expected a `{expected}`, found {value} of type `{}`.",
+            value.value_type()
+        ),
+    }
+}
+
+impl IntoTray for Value {
+    fn into_tray(self) -> Value {
+        self
+    }
+}
+
+impl FromTray for Value {
+    fn from_tray(value: Value) -> Result<Self, RuntimeError> {
+        Ok(value)
+    }
+}
+
+/// Implements the conversions between the Rust number type `$type` and the tray number
+/// `Number::$variant`.
+macro_rules! number_conversion {
+    ($type:ty, $variant:ident) => {
+        impl IntoTray for $type {
+            fn into_tray(self) -> Value {
+                Value::Number(Number::$variant(self))
+            }
+        }
+
+        impl FromTray for $type {
+            fn from_tray(value: Value) -> Result<Self, RuntimeError> {
+                let expected = Type::Number(NumberType::$variant);
+                match value.clone().convert_to(expected.clone()) {
+                    Some(Value::Number(Number::$variant(number))) => Ok(number),
+                    _ => Err(mismatched_types(&value, expected)),
+                }
+            }
+        }
+    };
+}
+
+number_conversion!(f32, Float32);
+number_conversion!(f64, Float64);
+number_conversion!(i8, Int8);
+number_conversion!(i16, Int16);
+number_conversion!(i32, Int32);
+number_conversion!(i64, Int64);
+number_conversion!(i128, Int128);
+number_conversion!(u8, UInt8);
+number_conversion!(u16, UInt16);
+number_conversion!(u32, UInt32);
+number_conversion!(u64, UInt64);
+number_conversion!(u128, UInt128);
+number_conversion!(BigInt, BigInt);
+number_conversion!(Decimal, Decimal);
+
+impl IntoTray for bool {
+    fn into_tray(self) -> Value {
+        Value::Bool(self)
+    }
+}
+
+impl FromTray for bool {
+    fn from_tray(value: Value) -> Result<Self, RuntimeError> {
+        match value {
+            Value::Bool(bool) => Ok(bool),
+            value => Err(mismatched_types(&value, Type::Bool)),
+        }
+    }
+}
+
+impl IntoTray for char {
+    fn into_tray(self) -> Value {
+        Value::Char(self)
+    }
+}
+
+impl FromTray for char {
+    fn from_tray(value: Value) -> Result<Self, RuntimeError> {
+        match value {
+            Value::Char(char) => Ok(char),
+            value => Err(mismatched_types(&value, Type::Char)),
+        }
+    }
+}
+
+impl IntoTray for String {
+    fn into_tray(self) -> Value {
+        Value::String(self)
+    }
+}
+
+impl IntoTray for &str {
+    fn into_tray(self) -> Value {
+        Value::String(self.to_string())
+    }
+}
+
+impl FromTray for String {
+    fn from_tray(value: Value) -> Result<Self, RuntimeError> {
+        match value {
+            Value::String(string) => Ok(string),
+            value => Err(mismatched_types(&value, Type::String)),
+        }
+    }
+}
+
+impl<T: IntoTray + TrayType> IntoTray for Vec<T> {
+    fn into_tray(self) -> Value {
+        let elements = self.into_iter().map(IntoTray::into_tray).collect();
+        Value::Vec(Array::new(T::tray_type(), elements))
+    }
+}
+
+/// Converts a `Vec` element by element, so a tray `Vec<i8>` can be read as a `Vec<i64>`.
+impl<T: FromTray + TrayType> FromTray for Vec<T> {
+    fn from_tray(value: Value) -> Result<Self, RuntimeError> {
+        match &value {
+            Value::Vec(array) => array.to_vec().into_iter().map(T::from_tray).collect(),
+            _ => Err(mismatched_types(&value, Vec::<T>::tray_type())),
+        }
+    }
+}
@@ -0,0 +1,274 @@
+use std::{
+    cmp::Ordering,
+    fmt::Display,
+    ops::{Add, Mul, Neg, Sub},
+    str::FromStr,
+};
+
+use crate::bigint::BigInt;
+
+/// Number of fractional digits kept when a division doesn't terminate.
+pub const DIVISION_SCALE: u32 = 28;
+
+/// An exact base-10 number, stored as `mantissa / 10^scale`.
+///
+/// Addition, subtraction and multiplication are always exact and keep trailing zeros the way they
+/// were written (`1.10 + 1.20` is `2.30`), which is what you want for amounts of money. Division
+/// rounds half to even at [`DIVISION_SCALE`] digits.
+#[derive(Debug, Clone)]
+pub struct Decimal {
+    mantissa: BigInt,
+    scale: u32,
+}
+
+impl Decimal {
+    /// Converts a float through its shortest round-tripping decimal representation, so `0.1f64`
+    /// becomes `0.1`. Non-finite values become zero.
+    pub fn from_f64(value: f64) -> Self {
+        if !value.is_finite() {
+            return Decimal::from(BigInt::zero());
+        }
+        format!("{value}")
+            .parse()
+            .unwrap_or_else(|_| Decimal::from(BigInt::zero()))
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.mantissa.is_zero()
+    }
+
+    /// Returns how many bytes the value takes on the heap.
+    pub fn heap_size(&self) -> usize {
+        self.mantissa.heap_size()
+    }
+
+    /// Drops the fractional part, rounding toward zero.
+    pub fn truncate(&self) -> BigInt {
+        self.mantissa
+            .div_rem(&BigInt::pow10(self.scale))
+            .expect("10^scale is never zero")
+            .0
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        f64::from_str(&self.to_string()).unwrap_or(f64::NAN)
+    }
+
+    fn rescale(&self, scale: u32) -> BigInt {
+        &self.mantissa * &BigInt::pow10(scale - self.scale)
+    }
+
+    /// Removes trailing fractional zeros, so `0.500` becomes `0.5`.
+    fn normalize(mut self) -> Self {
+        let ten = BigInt::from_u128(10);
+        while self.scale > 0 {
+            let (quotient, remainder) = self.mantissa.div_rem(&ten).expect("ten is not zero");
+            if !remainder.is_zero() {
+                break;
+            }
+            self.mantissa = quotient;
+            self.scale -= 1;
+        }
+        self
+    }
+
+    /// Divides by `other`, or returns `None` when `other` is zero.
+    pub fn checked_div(&self, other: &Decimal) -> Option<Decimal> {
+        // self / other = (m1 * 10^(s2 + DIVISION_SCALE)) / (m2 * 10^s1) / 10^DIVISION_SCALE
+        let numerator = &self.mantissa * &BigInt::pow10(other.scale + DIVISION_SCALE);
+        let denominator = &other.mantissa * &BigInt::pow10(self.scale);
+        let (mut quotient, remainder) = numerator.div_rem(&denominator)?;
+
+        let twice_remainder = &remainder.abs() * &BigInt::from_u128(2);
+        let round_away = match twice_remainder.cmp(&denominator.abs()) {
+            Ordering::Greater => true,
+            Ordering::Equal => !quotient.is_even(),
+            Ordering::Less => false,
+        };
+        if round_away {
+            let step = BigInt::from_i128(if numerator.is_negative() != denominator.is_negative() {
+                -1
+            } else {
+                1
+            });
+            quotient = &quotient + &step;
+        }
+
+        Some(
+            Decimal {
+                mantissa: quotient,
+                scale: DIVISION_SCALE,
+            }
+            .normalize(),
+        )
+    }
+}
+
+impl From<BigInt> for Decimal {
+    fn from(value: BigInt) -> Self {
+        Decimal {
+            mantissa: value,
+            scale: 0,
+        }
+    }
+}
+
+impl Add for &Decimal {
+    type Output = Decimal;
+
+    fn add(self, other: &Decimal) -> Decimal {
+        let scale = self.scale.max(other.scale);
+        Decimal {
+            mantissa: &self.rescale(scale) + &other.rescale(scale),
+            scale,
+        }
+    }
+}
+
+impl Sub for &Decimal {
+    type Output = Decimal;
+
+    fn sub(self, other: &Decimal) -> Decimal {
+        self + &-other
+    }
+}
+
+impl Mul for &Decimal {
+    type Output = Decimal;
+
+    fn mul(self, other: &Decimal) -> Decimal {
+        Decimal {
+            mantissa: &self.mantissa * &other.mantissa,
+            scale: self.scale + other.scale,
+        }
+    }
+}
+
+impl Neg for &Decimal {
+    type Output = Decimal;
+
+    fn neg(self) -> Decimal {
+        Decimal {
+            mantissa: -&self.mantissa,
+            scale: self.scale,
+        }
+    }
+}
+
+impl Ord for Decimal {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let scale = self.scale.max(other.scale);
+        self.rescale(scale).cmp(&other.rescale(scale))
+    }
+}
+
+impl PartialOrd for Decimal {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Decimals compare by value, so `1.0 == 1.00`.
+impl PartialEq for Decimal {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Decimal {}
+
+impl FromStr for Decimal {
+    type Err = ();
+
+    /// Parses an optionally `-` prefixed number with at most one `.`.
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        let (integral, fractional) = string.split_once('.').unwrap_or((string, ""));
+        if !fractional.bytes().all(|byte| byte.is_ascii_digit()) {
+            return Err(());
+        }
+        let digits = format!("{integral}{fractional}");
+        Ok(Decimal {
+            mantissa: BigInt::from_str(&digits)?,
+            scale: fractional.len() as u32,
+        })
+    }
+}
+
+impl Display for Decimal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let digits = self.mantissa.abs().to_string();
+        let scale = self.scale as usize;
+        let digits = if digits.len() <= scale {
+            "0".repeat(scale - digits.len() + 1) + &digits
+        } else {
+            digits
+        };
+        if self.mantissa.is_negative() {
+            f.write_str("-")?;
+        }
+        let (integral, fractional) = digits.split_at(digits.len() - scale);
+        if fractional.is_empty() {
+            f.write_str(integral)
+        } else {
+            write!(f, "{integral}.{fractional}")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decimal(value: &str) -> Decimal {
+        value.parse().unwrap()
+    }
+
+    #[test]
+    fn exact_operations_keep_the_scale() {
+        assert_eq!((&decimal("1.10") + &decimal("1.20")).to_string(), "2.30");
+        assert_eq!((&decimal("0.1") - &decimal("0.30")).to_string(), "-0.20");
+        assert_eq!((&decimal("1.5") * &decimal("-0.25")).to_string(), "-0.375");
+        assert_eq!(decimal("1.0"), decimal("1.00"));
+    }
+
+    #[test]
+    fn division_rounds_half_to_even() {
+        let divide = |left: &str, right: &str| decimal(left).checked_div(&decimal(right)).unwrap();
+        assert_eq!(divide("1", "4").to_string(), "0.25");
+        assert_eq!(
+            divide("1", "3").to_string(),
+            format!("0.{}", "3".repeat(28))
+        );
+        assert_eq!(
+            divide("2", "3").to_string(),
+            format!("0.{}7", "6".repeat(27))
+        );
+        assert_eq!(
+            divide("-2", "3").to_string(),
+            format!("-0.{}7", "6".repeat(27))
+        );
+        // Exactly halfway at the last digit: 0.5e-28 rounds to even, 1.5e-28 away from zero.
+        let tiny = format!("0.{}5", "0".repeat(28));
+        assert!(decimal(&tiny).checked_div(&decimal("1")).unwrap().is_zero());
+        let tiny = format!("0.{}15", "0".repeat(27));
+        assert_eq!(
+            divide(&tiny, "1").to_string(),
+            format!("0.{}2", "0".repeat(27))
+        );
+        assert!(decimal("1").checked_div(&decimal("0.0")).is_none());
+    }
+
+    #[test]
+    fn truncates_toward_zero() {
+        assert_eq!(decimal("2.99").truncate(), BigInt::from_i128(2));
+        assert_eq!(decimal("-2.99").truncate(), BigInt::from_i128(-2));
+    }
+
+    #[test]
+    fn converts_floats_through_their_shortest_representation() {
+        assert_eq!(Decimal::from_f64(0.1).to_string(), "0.1");
+        assert!(Decimal::from_f64(f64::NAN).is_zero());
+        assert_eq!(decimal("-0.05").to_string(), "-0.05");
+        assert!("1.2.3".parse::<Decimal>().is_err());
+    }
+}
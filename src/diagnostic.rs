@@ -0,0 +1,553 @@
+use std::{env, fmt::Write, io::IsTerminal, ops::Range};
+
+use crate::error_codes::ErrorCode;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+}
+
+impl Severity {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+        }
+    }
+
+    /// The ANSI style of the severity's name and of the primary label.
+    fn style(&self) -> &'static str {
+        match self {
+            Severity::Error => BOLD_RED,
+        }
+    }
+}
+
+const BOLD: &str = "1";
+const BOLD_RED: &str = "1;31";
+const BOLD_BLUE: &str = "1;34";
+
+/// Whether diagnostics written to stderr should be colored: only when it is a terminal and the
+/// `NO_COLOR` environment variable isn't set.
+pub fn stderr_supports_color() -> bool {
+    std::io::stderr().is_terminal() && env::var_os("NO_COLOR").is_none_or(|value| value.is_empty())
+}
+
+/// Wraps `text` in the ANSI escape codes of `style` if `color` is enabled.
+fn paint(color: bool, style: &str, text: &str) -> String {
+    if color {
+        format!("\x1b[{style}m{text}\x1b[0m")
+    } else {
+        text.to_string()
+    }
+}
+
+/// How diagnostics are written to stderr, chosen with `--error-format`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorFormat {
+    /// Text like rustc's, with the labeled source lines, colored on terminals.
+    Human,
+    /// One JSON object per line, for editors and CI.
+    Json,
+}
+
+impl ErrorFormat {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "human" => Some(ErrorFormat::Human),
+            "json" => Some(ErrorFormat::Json),
+            _ => None,
+        }
+    }
+}
+
+/// A problem found in a source file, by the lexer, the parser, the type checker or while
+/// executing.
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: &'static ErrorCode,
+    pub message: String,
+    /// The byte range of the source the diagnostic is about, if it's about a specific part.
+    pub span: Option<Range<usize>>,
+    /// Describes what is wrong at `span`, like "string started here".
+    pub label: Option<String>,
+    /// Other parts of the source that explain the diagnostic, like where a string should have
+    /// ended.
+    pub secondary_labels: Vec<Label>,
+    pub notes: Vec<String>,
+    pub suggestions: Vec<Suggestion>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Label {
+    pub span: Range<usize>,
+    pub message: String,
+}
+
+/// A machine-applicable fix: replacing `span` of the source with `replacement` resolves the
+/// diagnostic. An empty span inserts the replacement.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Suggestion {
+    /// Describes the fix to the user, like "escape the backslash".
+    pub message: String,
+    pub span: Range<usize>,
+    pub replacement: String,
+}
+
+impl Suggestion {
+    pub fn replace(span: Range<usize>, replacement: &str, message: &str) -> Self {
+        Suggestion {
+            message: message.to_string(),
+            span,
+            replacement: replacement.to_string(),
+        }
+    }
+
+    pub fn insert(position: usize, text: &str, message: &str) -> Self {
+        Suggestion::replace(position..position, text, message)
+    }
+}
+
+/// Returns `source` with `suggestions` applied. A suggestion overlapping one that comes before it
+/// is left out, so the others can still be applied and the rest fixed in another pass.
+pub fn apply_suggestions(source: &str, suggestions: &[Suggestion]) -> String {
+    let mut suggestions = suggestions.iter().collect::<Vec<_>>();
+    suggestions.sort_by_key(|suggestion| (suggestion.span.start, suggestion.span.end));
+    let mut output = String::with_capacity(source.len());
+    let mut copied = 0;
+    for suggestion in suggestions {
+        let start = char_boundary(source, suggestion.span.start);
+        let end = char_boundary(source, suggestion.span.end.max(start));
+        if start < copied {
+            continue;
+        }
+        output += &source[copied..start];
+        output += &suggestion.replacement;
+        copied = end;
+    }
+    output + &source[copied..]
+}
+
+/// A 1-based line and column, counted in characters.
+#[derive(Debug, Clone, Copy)]
+struct Position {
+    line: usize,
+    column: usize,
+}
+
+fn position(source: &str, offset: usize) -> Position {
+    let before = &source[..offset];
+    let line_start = before.rfind('\n').map_or(0, |index| index + 1);
+    Position {
+        line: before.matches('\n').count() + 1,
+        column: before[line_start..].chars().count() + 1,
+    }
+}
+
+/// Clamps `offset` to `source` and moves it back to the start of the character it is in.
+fn char_boundary(source: &str, offset: usize) -> usize {
+    let mut offset = offset.min(source.len());
+    while !source.is_char_boundary(offset) {
+        offset -= 1;
+    }
+    offset
+}
+
+/// Returns `span` clamped to `source`, covering at least one character when possible.
+fn clamp_span(source: &str, span: &Range<usize>) -> Range<usize> {
+    let start = char_boundary(source, span.start);
+    let mut end = char_boundary(source, span.end.max(start));
+    if end == start {
+        end = source[start..]
+            .chars()
+            .next()
+            .map_or(start, |char| start + char.len_utf8());
+    }
+    start..end
+}
+
+/// The width `text` takes in a terminal, with tabs shown as four spaces.
+fn display_width(text: &str) -> usize {
+    text.chars()
+        .map(|char| if char == '\t' { 4 } else { 1 })
+        .sum()
+}
+
+/// A label placed on a line of the rendered snippet.
+struct LineLabel<'a> {
+    /// 0-based line number.
+    line: usize,
+    /// Where the underline starts and how long it is, in terminal columns.
+    column: usize,
+    width: usize,
+    message: Option<&'a str>,
+    primary: bool,
+}
+
+impl Diagnostic {
+    pub fn error(code: &'static ErrorCode, message: String) -> Self {
+        Diagnostic {
+            severity: Severity::Error,
+            code,
+            message,
+            span: None,
+            label: None,
+            secondary_labels: vec![],
+            notes: vec![],
+            suggestions: vec![],
+        }
+    }
+
+    pub fn with_span(mut self, span: Range<usize>) -> Self {
+        self.span = Some(span);
+        self
+    }
+
+    /// Describes the primary span.
+    pub fn with_label(mut self, message: &str) -> Self {
+        self.label = Some(message.to_string());
+        self
+    }
+
+    pub fn with_secondary_label(mut self, span: Range<usize>, message: &str) -> Self {
+        self.secondary_labels.push(Label {
+            span,
+            message: message.to_string(),
+        });
+        self
+    }
+
+    pub fn with_note(mut self, note: String) -> Self {
+        self.notes.push(note);
+        self
+    }
+
+    pub fn with_suggestion(mut self, suggestion: Suggestion) -> Self {
+        self.suggestions.push(suggestion);
+        self
+    }
+
+    /// Returns the span clamped to `source`, covering at least one character when possible.
+    fn span_in(&self, source: &str) -> Option<Range<usize>> {
+        Some(clamp_span(source, self.span.as_ref()?))
+    }
+
+    /// Writes the diagnostic to stderr in `format`. `file` is the name of the file `source` was
+    /// read from.
+    pub fn emit(&self, format: ErrorFormat, file: &str, source: &str) {
+        match format {
+            ErrorFormat::Human => eprint!("{}", self.render(file, source, stderr_supports_color())),
+            ErrorFormat::Json => eprintln!("{}", self.to_json(file, source)),
+        }
+    }
+
+    /// Places the primary and secondary labels on the lines of `source`, sorted by line with the
+    /// primary label first.
+    fn line_labels<'a>(&'a self, source: &str) -> Vec<LineLabel<'a>> {
+        let primary = self
+            .span_in(source)
+            .map(|span| (span, self.label.as_deref(), true));
+        let secondary = self.secondary_labels.iter().map(|label| {
+            (
+                clamp_span(source, &label.span),
+                Some(label.message.as_str()),
+                false,
+            )
+        });
+        let mut labels = primary
+            .into_iter()
+            .chain(secondary)
+            .map(|(span, message, primary)| {
+                let line_start = source[..span.start]
+                    .rfind('\n')
+                    .map_or(0, |index| index + 1);
+                let line_end = source[span.start..]
+                    .find('\n')
+                    .map_or(source.len(), |index| span.start + index);
+                LineLabel {
+                    line: source[..span.start].matches('\n').count(),
+                    column: display_width(&source[line_start..span.start]),
+                    width: display_width(&source[span.start..span.end.min(line_end)]).max(1),
+                    message,
+                    primary,
+                }
+            })
+            .collect::<Vec<_>>();
+        labels.sort_by_key(|label| (label.line, !label.primary));
+        labels
+    }
+
+    /// Renders the diagnostic like rustc: the severity, code and message, the location in `file`,
+    /// the lines of `source` with labels under the parts they are about, then the notes and the
+    /// suggested fixes. `color` enables ANSI colors.
+    pub fn render(&self, file: &str, source: &str, color: bool) -> String {
+        let severity_style = self.severity.style();
+        let mut output = format!(
+            "{}{}\n",
+            paint(
+                color,
+                severity_style,
+                &format!("{}[{}]", self.severity.name(), self.code.code)
+            ),
+            paint(color, BOLD, &format!(": {}", self.message))
+        );
+
+        let labels = self.line_labels(source);
+        let lines = source.split('\n').collect::<Vec<_>>();
+        let last_line = labels.iter().map(|label| label.line).max().unwrap_or(0);
+        let gutter_width = (last_line + 1).to_string().len();
+        let gutter =
+            |line_number: &str| paint(color, BOLD_BLUE, &format!("{line_number:>gutter_width$} |"));
+        let padding = " ".repeat(gutter_width);
+        let source_line = |line: usize| {
+            let text = lines.get(line).copied().unwrap_or_default().trim_end();
+            let gutter = gutter(&(line + 1).to_string());
+            if text.is_empty() {
+                format!("{gutter}\n")
+            } else {
+                format!("{gutter} {}\n", text.replace('\t', "    "))
+            }
+        };
+
+        let location = self
+            .span_in(source)
+            .or_else(|| Some(clamp_span(source, &self.secondary_labels.first()?.span)));
+        if let Some(span) = location {
+            let start = position(source, span.start);
+            let _ = writeln!(
+                output,
+                "{padding}{} {file}:{}:{}",
+                paint(color, BOLD_BLUE, "-->"),
+                start.line,
+                start.column
+            );
+            let _ = writeln!(output, "{}", gutter(""));
+            let mut previous_line = None;
+            for label in &labels {
+                if previous_line != Some(label.line) {
+                    // A single line between two labeled ones is shown, longer gaps are elided.
+                    match previous_line {
+                        Some(line) if line + 2 == label.line => {
+                            output += &source_line(line + 1);
+                        }
+                        Some(line) if line + 2 < label.line => {
+                            let _ = writeln!(output, "{}", paint(color, BOLD_BLUE, "..."));
+                        }
+                        _ => {}
+                    }
+                    output += &source_line(label.line);
+                    previous_line = Some(label.line);
+                }
+                let (marker, style) = if label.primary {
+                    ("^", severity_style)
+                } else {
+                    ("-", BOLD_BLUE)
+                };
+                let underline = match label.message {
+                    Some(message) => format!("{} {message}", marker.repeat(label.width)),
+                    None => marker.repeat(label.width),
+                };
+                let _ = writeln!(
+                    output,
+                    "{} {}{}",
+                    gutter(""),
+                    " ".repeat(label.column),
+                    paint(color, style, &underline)
+                );
+            }
+        }
+
+        // Notes and help follow the snippet in its gutter, like rustc does.
+        let prefix = if labels.is_empty() {
+            String::new()
+        } else {
+            format!("{padding} {} ", paint(color, BOLD_BLUE, "="))
+        };
+        for note in &self.notes {
+            let _ = writeln!(output, "{prefix}{}: {note}", paint(color, BOLD, "note"));
+        }
+        for suggestion in &self.suggestions {
+            let _ = writeln!(
+                output,
+                "{prefix}{}: {}",
+                paint(color, BOLD, "help"),
+                suggestion.message
+            );
+        }
+        output
+    }
+
+    /// Serializes the diagnostic as a single line JSON object. Lines and columns start at 1 and
+    /// the end of the span is exclusive.
+    pub fn to_json(&self, file: &str, source: &str) -> String {
+        let span = self
+            .span_in(source)
+            .map_or(String::from("null"), |span| span_json(source, &span));
+        let label = self
+            .label
+            .as_deref()
+            .map_or(String::from("null"), json_string);
+        let secondary_labels = self
+            .secondary_labels
+            .iter()
+            .map(|label| {
+                format!(
+                    "{{\"message\":{},\"span\":{}}}",
+                    json_string(&label.message),
+                    span_json(source, &clamp_span(source, &label.span))
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(",");
+        let notes = self
+            .notes
+            .iter()
+            .map(|note| json_string(note))
+            .collect::<Vec<_>>()
+            .join(",");
+        let suggestions = self
+            .suggestions
+            .iter()
+            .map(|suggestion| {
+                let start = char_boundary(source, suggestion.span.start);
+                let end = char_boundary(source, suggestion.span.end.max(start));
+                format!(
+                    "{{\"message\":{},\"span\":{},\"replacement\":{}}}",
+                    json_string(&suggestion.message),
+                    span_json(source, &(start..end)),
+                    json_string(&suggestion.replacement)
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(",");
+        format!(
+            "{{\"severity\":{},\"code\":{},\"message\":{},\"file\":{},\"span\":{span},\"label\":{label},\"secondary_labels\":[{secondary_labels}],\"notes\":[{notes}],\"suggestions\":[{suggestions}]}}",
+            json_string(self.severity.name()),
+            json_string(self.code.code),
+            json_string(&self.message),
+            json_string(file),
+        )
+    }
+}
+
+/// Serializes `span` with the byte offset, line and column of its start and end.
+fn span_json(source: &str, span: &Range<usize>) -> String {
+    let (start, end) = (position(source, span.start), position(source, span.end));
+    format!(
+        "{{\"start\":{{\"offset\":{},\"line\":{},\"column\":{}}},\"end\":{{\"offset\":{},\"line\":{},\"column\":{}}}}}",
+        span.start, start.line, start.column, span.end, end.line, end.column
+    )
+}
+
+/// Quotes and escapes `string` as a JSON string.
+fn json_string(string: &str) -> String {
+    let mut output = String::with_capacity(string.len() + 2);
+    output.push('"');
+    for char in string.chars() {
+        match char {
+            '"' => output.push_str("\\\""),
+            '\\' => output.push_str("\\\\"),
+            '\n' => output.push_str("\\n"),
+            '\r' => output.push_str("\\r"),
+            '\t' => output.push_str("\\t"),
+            char if char.is_control() => {
+                let _ = write!(output, "\\u{:04x}", char as u32);
+            }
+            char => output.push(char),
+        }
+    }
+    output.push('"');
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error_codes;
+
+    #[test]
+    fn escapes_quotes_backslashes_and_control_characters() {
+        assert_eq!(
+            json_string("say \"hi\" \\ é\n\r\t\x01\x1f\x7f"),
+            r#""say \"hi\" \\ é\n\r\t\u0001\u001f\u007f""#
+        );
+        assert_eq!(json_string(""), r#""""#);
+    }
+
+    #[test]
+    fn serializes_a_diagnostic_on_one_line() {
+        let source = "let s = \"é\x01\";\nlet t = 'a";
+        let message = String::from("Expected `\"` or `\\`,\nfound\ttab");
+        let diagnostic = Diagnostic::error(&error_codes::INVALID_NUMBER, message)
+            .with_span(9..13)
+            .with_label("string \"started\" here")
+            .with_secondary_label(23..25, "ends\r")
+            .with_note(String::from("bell \x07"))
+            .with_suggestion(Suggestion::insert(25, "'", "close it with `'`"));
+        let json = diagnostic.to_json("C:\\dir\\a \"b\".tray", source);
+        assert!(!json.chars().any(char::is_control));
+        // Columns count characters, so `é` is one column although it is two bytes.
+        let expected = concat!(
+            r#"{"severity":"error","code":"T0002","message":"Expected `\"` or `\\`,\nfound\ttab","#,
+            r#""file":"C:\\dir\\a \"b\".tray","#,
+            r#""span":{"start":{"offset":9,"line":1,"column":10},"end":{"offset":13,"line":1,"column":13}},"#,
+            r#""label":"string \"started\" here","#,
+            r#""secondary_labels":[{"message":"ends\r","span":{"start":{"offset":23,"line":2,"column":9},"end":{"offset":25,"line":2,"column":11}}}],"#,
+            r#""notes":["bell \u0007"],"#,
+            r#""suggestions":[{"message":"close it with `'`","span":{"start":{"offset":25,"line":2,"column":11},"end":{"offset":25,"line":2,"column":11}},"replacement":"'"}]}"#,
+        );
+        assert_eq!(json, expected);
+    }
+
+    #[test]
+    fn applies_suggestions_in_the_order_of_the_source() {
+        let source = "let a = 1; a b";
+        let suggestions = [
+            Suggestion::insert(14, ";", "add a `;`"),
+            Suggestion::replace(0..3, "var", "use `var`"),
+            Suggestion::insert(12, " +", "add an operator"),
+            Suggestion::insert(14, " 1", "add an operand"),
+        ];
+        // Insertions at the same position keep their order.
+        assert_eq!(
+            apply_suggestions(source, &suggestions),
+            "var a = 1; a + b; 1"
+        );
+    }
+
+    #[test]
+    fn leaves_out_overlapping_suggestions() {
+        let suggestions = [
+            Suggestion::replace(4..9, "quick", "first"),
+            Suggestion::replace(6..12, "slow", "overlaps the first"),
+            Suggestion::replace(10..13, "dog", "after the first"),
+        ];
+        assert_eq!(
+            apply_suggestions("the quack fox", &suggestions),
+            "the quick dog"
+        );
+    }
+
+    #[test]
+    fn clamps_suggestions_to_characters_of_the_source() {
+        // Offsets inside `é` move to its start, and the ones past the end to the end.
+        let suggestions = [
+            Suggestion::replace(3..4, "e", "starts inside a character"),
+            Suggestion::insert(100, "!", "past the end"),
+        ];
+        assert_eq!(apply_suggestions("caé", &suggestions), "cae!");
+    }
+
+    #[test]
+    fn applied_suggestions_fix_their_diagnostics() {
+        let source = "i32 a = 1.5i32; String s = \"C:\\d\";";
+        let diagnostics = crate::Interpreter::new().compile(source).unwrap_err();
+        let suggestions = diagnostics
+            .into_iter()
+            .flat_map(|diagnostic| diagnostic.suggestions)
+            .collect::<Vec<_>>();
+        let fixed = apply_suggestions(source, &suggestions);
+        assert_eq!(fixed, "i32 a = 1.5 as i32; String s = \"C:\\\\d\";");
+        assert!(crate::Interpreter::new().compile(&fixed).is_ok());
+    }
+}
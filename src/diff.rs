@@ -0,0 +1,176 @@
+/// How many unchanged lines are shown around each change.
+const CONTEXT_LINES: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Edit {
+    Keep,
+    Remove,
+    Add,
+}
+
+/// Returns the edits turning `old` into `new`, keeping as many lines as possible.
+///
+/// The lines the two start and end with are kept without comparing the rest, so that the
+/// quadratic comparison only covers the lines between the first and the last change, which are
+/// few for the fixes of `check --fix`.
+fn edits(old: &[&str], new: &[&str]) -> Vec<Edit> {
+    let prefix = old
+        .iter()
+        .zip(new)
+        .take_while(|(old, new)| old == new)
+        .count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(old, new)| old == new)
+        .count();
+    let mut edits = vec![Edit::Keep; prefix];
+    edits.extend(changed_edits(
+        &old[prefix..old.len() - suffix],
+        &new[prefix..new.len() - suffix],
+    ));
+    edits.extend(vec![Edit::Keep; suffix]);
+    edits
+}
+
+/// Returns the edits turning `old` into `new` like [`edits`], comparing every line of `old` with
+/// every line of `new`.
+fn changed_edits(old: &[&str], new: &[&str]) -> Vec<Edit> {
+    // `kept[i][j]` is the length of the longest common subsequence of `old[i..]` and `new[j..]`.
+    let mut kept = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            kept[i][j] = if old[i] == new[j] {
+                kept[i + 1][j + 1] + 1
+            } else {
+                kept[i + 1][j].max(kept[i][j + 1])
+            };
+        }
+    }
+
+    let (mut i, mut j) = (0, 0);
+    let mut edits = vec![];
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            edits.push(Edit::Keep);
+            i += 1;
+            j += 1;
+        } else if j == new.len() || (i < old.len() && kept[i + 1][j] >= kept[i][j + 1]) {
+            edits.push(Edit::Remove);
+            i += 1;
+        } else {
+            edits.push(Edit::Add);
+            j += 1;
+        }
+    }
+    edits
+}
+
+/// Formats the lines of a hunk starting after `index` lines, where an empty range is given by the
+/// line before it.
+fn hunk_range(index: usize, count: usize) -> String {
+    match count {
+        0 => format!("{index},0"),
+        count => format!("{},{count}", index + 1),
+    }
+}
+
+/// Returns a unified diff from `old` to `new`, the contents of the file `name`, or an empty
+/// string if they have the same lines.
+pub fn unified_diff(name: &str, old: &str, new: &str) -> String {
+    let old_lines = old.lines().collect::<Vec<_>>();
+    let new_lines = new.lines().collect::<Vec<_>>();
+    let edits = edits(&old_lines, &new_lines);
+    let changes = edits
+        .iter()
+        .enumerate()
+        .filter(|(_, edit)| **edit != Edit::Keep)
+        .map(|(index, _)| index)
+        .collect::<Vec<_>>();
+    if changes.is_empty() {
+        return String::new();
+    }
+
+    // Changes whose context overlaps are shown in the same hunk.
+    let mut hunks: Vec<(usize, usize)> = vec![];
+    for change in changes {
+        let start = change.saturating_sub(CONTEXT_LINES);
+        let end = (change + CONTEXT_LINES + 1).min(edits.len());
+        match hunks.last_mut() {
+            Some(hunk) if start <= hunk.1 => hunk.1 = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+
+    let mut output = format!("--- {name}\n+++ {name}\n");
+    let (mut old_index, mut new_index, mut edit_index) = (0, 0, 0);
+    for (start, end) in hunks {
+        while edit_index < start {
+            match edits[edit_index] {
+                Edit::Keep => {
+                    old_index += 1;
+                    new_index += 1;
+                }
+                Edit::Remove => old_index += 1,
+                Edit::Add => new_index += 1,
+            }
+            edit_index += 1;
+        }
+        let hunk = &edits[start..end];
+        let old_count = hunk.iter().filter(|edit| **edit != Edit::Add).count();
+        let new_count = hunk.iter().filter(|edit| **edit != Edit::Remove).count();
+        output += &format!(
+            "@@ -{} +{} @@\n",
+            hunk_range(old_index, old_count),
+            hunk_range(new_index, new_count)
+        );
+        for edit in hunk {
+            match edit {
+                Edit::Keep => {
+                    output += &format!(" {}\n", old_lines[old_index]);
+                    old_index += 1;
+                    new_index += 1;
+                }
+                Edit::Remove => {
+                    output += &format!("-{}\n", old_lines[old_index]);
+                    old_index += 1;
+                }
+                Edit::Add => {
+                    output += &format!("+{}\n", new_lines[new_index]);
+                    new_index += 1;
+                }
+            }
+        }
+        edit_index = end;
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shows_changes_with_their_context() {
+        let old = "a\nb\nc\nd\ne\nf\ng\nh\ni\n";
+        let new = "a\nb\nc\nd\nE\nf\ng\nh\ni\nj\n";
+        assert_eq!(
+            unified_diff("file.tray", old, new),
+            "--- file.tray\n+++ file.tray\n@@ -2,8 +2,9 @@\n b\n c\n d\n-e\n+E\n f\n g\n h\n i\n+j\n"
+        );
+        assert_eq!(unified_diff("file.tray", old, old), "");
+    }
+
+    #[test]
+    fn only_compares_the_changed_lines() {
+        let old = (0..100_000)
+            .map(|line| format!("{line}\n"))
+            .collect::<String>();
+        let new = old.replacen("50000\n", "fifty thousand\n", 1);
+        assert_eq!(
+            unified_diff("file.tray", &old, &new),
+            "--- file.tray\n+++ file.tray\n@@ -49998,7 +49998,7 @@\n 49997\n 49998\n 49999\n-50000\n+fifty thousand\n 50001\n 50002\n 50003\n"
+        );
+    }
+}
@@ -0,0 +1,453 @@
+/// A kind of diagnostic. Its code is stable and shown with every diagnostic of that kind, and
+/// `tray explain <code>` prints its explanation and examples.
+#[derive(Debug)]
+pub struct ErrorCode {
+    pub code: &'static str,
+    pub title: &'static str,
+    pub explanation: &'static str,
+    /// Code causing the error, and the same code fixed. Errors that don't come from the code
+    /// itself have no examples.
+    pub examples: Option<(&'static str, &'static str)>,
+}
+
+pub static UNRECOGNIZED_CHARACTER: ErrorCode = ErrorCode {
+    code: "T0001",
+    title: "unrecognized character",
+    explanation: "The source contains a character that isn't part of the language, outside of a string or char literal.",
+    examples: Some(("i32 price = 5 $;", "i32 price = 5;")),
+};
+
+pub static INVALID_NUMBER: ErrorCode = ErrorCode {
+    code: "T0002",
+    title: "number literal out of range",
+    explanation: "A number literal doesn't fit in its type. Unsuffixed integers are `i32`, `i64` or `i128` depending on their length, so a suffix is needed for larger types, or `n` for a `bigint`.",
+    examples: Some(("u8 level = 300u8;", "u16 level = 300u16;")),
+};
+
+pub static FLOAT_WITH_INTEGER_SUFFIX: ErrorCode = ErrorCode {
+    code: "T0003",
+    title: "floating point literal with an integer suffix",
+    explanation: "A number with a `.` is a floating point number, so it can only have the `f32`, `f64` or `d` suffixes. Converting it to an integer type needs an explicit cast, which truncates it toward zero.",
+    examples: Some(("i32 count = 1.5i32;", "i32 count = 1.5 as i32;")),
+};
+
+pub static UNKNOWN_NUMBER_SUFFIX: ErrorCode = ErrorCode {
+    code: "T0004",
+    title: "unknown number suffix",
+    explanation: "Letters right after a number are its suffix, which must be one of `i8`, `i16`, `i32`, `i64`, `i128`, `u8`, `u16`, `u32`, `u64`, `u128`, `f32`, `f64`, `n` (bigint) or `d` (decimal). A space is needed between a number and a name.",
+    examples: Some(("u8 small = 10u7;", "u8 small = 10u8;")),
+};
+
+pub static UNDERSCORE_AFTER_DOT: ErrorCode = ErrorCode {
+    code: "T0005",
+    title: "`_` right after the `.` of a number",
+    explanation:
+        "Underscores can separate the digits of a number, but cannot directly follow its `.`.",
+    examples: Some(("f64 rate = 1._5;", "f64 rate = 1.5;")),
+};
+
+pub static MULTIPLE_DOTS: ErrorCode = ErrorCode {
+    code: "T0006",
+    title: "number with multiple `.`",
+    explanation: "A number can have at most one `.` separating its integral and fractional parts.",
+    examples: Some(("f32 version = 1.2.3;", "f32 version = 1.23;")),
+};
+
+pub static INVALID_HEX_ESCAPE: ErrorCode = ErrorCode {
+    code: "T0007",
+    title: "invalid `\\x` escape",
+    explanation: "`\\x` escapes an ASCII character in a string or char literal. It must be followed by exactly two hexadecimal digits, for a value between `00` and `7F`.",
+    examples: Some(("String bell = \"\\x7\";", "String bell = \"\\x07\";")),
+};
+
+pub static INVALID_UNICODE_ESCAPE: ErrorCode = ErrorCode {
+    code: "T0008",
+    title: "invalid `\\u` escape",
+    explanation: "`\\u` escapes a unicode character in a string or char literal. It must be followed by 1 to 6 hexadecimal digits between braces, for a valid unicode scalar value between `0` and `10FFFF`.",
+    examples: Some(("char heart = '\\u2764';", "char heart = '\\u{2764}';")),
+};
+
+pub static INVALID_ESCAPE: ErrorCode = ErrorCode {
+    code: "T0009",
+    title: "unknown escape sequence",
+    explanation: "A `\\` in a string or char literal starts an escape sequence. The valid ones are `\\n`, `\\r`, `\\t`, `\\0`, `\\\\`, `\\'`, `\\\"`, `\\x` and `\\u`. A backslash that is part of the text must be escaped as `\\\\`.",
+    examples: Some(("String path = \"C:\\tray\\scripts\";", "String path = \"C:\\\\tray\\\\scripts\";")),
+};
+
+pub static EMPTY_CHAR: ErrorCode = ErrorCode {
+    code: "T0010",
+    title: "empty char literal",
+    explanation: "A char literal must contain exactly one character.",
+    examples: Some(("char separator = '';", "char separator = ' ';")),
+};
+
+pub static UNTERMINATED_CHAR: ErrorCode = ErrorCode {
+    code: "T0011",
+    title: "unterminated char literal",
+    explanation: "A char literal must contain a single character followed by a closing `'`. Text with several characters is a string, written between double quotes.",
+    examples: Some(("String name = 'tray';", "String name = \"tray\";")),
+};
+
+pub static UNTERMINATED_STRING: ErrorCode = ErrorCode {
+    code: "T0012",
+    title: "unterminated string literal",
+    explanation: "The file ended inside a string literal, so its closing `\"` is missing. A `\"` inside a string must be escaped as `\\\"`.",
+    examples: Some(("String greeting = \"hello;", "String greeting = \"hello\";")),
+};
+
+pub static EXPECTED_EXPRESSION: ErrorCode = ErrorCode {
+    code: "T0013",
+    title: "expected an expression",
+    explanation: "An expression like a number, a variable or a parenthesized expression was expected, but the statement ended or another token was found.",
+    examples: Some(("i32 total = 1 + ;", "i32 total = 1 + 2;")),
+};
+
+pub static UNEXPECTED_TOKEN: ErrorCode = ErrorCode {
+    code: "T0014",
+    title: "unexpected token after a statement",
+    explanation: "A statement was complete but was followed by another token. Statements must be separated by `;`, and values must be combined with an operator.",
+    examples: Some(("i32 total = 1 2;", "i32 total = 1 + 2;")),
+};
+
+pub static UNCLOSED_PARENTHESIS: ErrorCode = ErrorCode {
+    code: "T0015",
+    title: "unclosed parenthesis",
+    explanation: "Every `(` must be closed by a `)` before the end of its statement.",
+    examples: Some(("i32 total = (1 + 2 * 3;", "i32 total = (1 + 2) * 3;")),
+};
+
+pub static UNKNOWN_TYPE: ErrorCode = ErrorCode {
+    code: "T0016",
+    title: "unknown type",
+    explanation: "A declaration or cast names a type that doesn't exist. The types are `i8` to `i128`, `u8` to `u128`, `f32`, `f64`, `bigint`, `decimal`, `bool`, `char` and `String`, and `int` and `byte` are aliases of `i32` and `u8`. Capitalized names are classes, which must be registered by the program embedding tray.",
+    examples: Some(("integer count = 1;", "i32 count = 1;")),
+};
+
+pub static EXPECTED_TYPE: ErrorCode = ErrorCode {
+    code: "T0017",
+    title: "expected a type",
+    explanation: "A type must be written after `as`, which converts a number to another number type, and between the `<` and `>` of type arguments.",
+    examples: Some(("i64 wide = 5 as;", "i64 wide = 5 as i64;")),
+};
+
+pub static MISSING_INITIAL_VALUE: ErrorCode = ErrorCode {
+    code: "T0018",
+    title: "declaration without an initial value",
+    explanation:
+        "Variables must be given a value when they are declared, with `=` after their name.",
+    examples: Some(("i32 count;", "i32 count = 0;")),
+};
+
+pub static EXPECTED_NUMBER: ErrorCode = ErrorCode {
+    code: "T0019",
+    title: "operation on a value that isn't a number",
+    explanation: "Arithmetic operators, unary `+` and `-`, and `as` casts only work on numbers, not on strings or chars.",
+    examples: Some(("i32 doubled = \"4\" * 2;", "i32 doubled = 4 * 2;")),
+};
+
+pub static UNDEFINED_VARIABLE: ErrorCode = ErrorCode {
+    code: "T0020",
+    title: "undefined variable",
+    explanation: "A variable was used or assigned before being declared. Variables are declared with their type, like `i32 name = value;`.",
+    examples: Some(("total = 5;", "i32 total = 5;")),
+};
+
+pub static MISMATCHED_TYPES: ErrorCode = ErrorCode {
+    code: "T0021",
+    title: "value doesn't fit the type of the variable",
+    explanation: "A value stored in a variable must have the variable's type. Numbers are converted automatically only when no information is lost, otherwise an explicit `as` cast is needed. Floats and decimals always need one to be stored in an integer, as they can have a fractional part.",
+    examples: Some(("u8 small = 300;", "u16 small = 300;")),
+};
+
+pub static MIXED_SIGNEDNESS: ErrorCode = ErrorCode {
+    code: "T0022",
+    title: "signed and unsigned integers mixed",
+    explanation: "Signed and unsigned integers cannot be used in the same operation, because neither type can hold every value of the other. One of them must be cast explicitly.",
+    examples: Some(("i32 sum = 1u8 + 2i32;", "i32 sum = 1u8 as i32 + 2i32;")),
+};
+
+pub static DECIMAL_WITH_FLOAT: ErrorCode = ErrorCode {
+    code: "T0023",
+    title: "decimal mixed with a floating point number",
+    explanation: "A `decimal` is exact while floating point numbers aren't, so mixing them in an operation would silently lose exactness. One of them must be cast explicitly.",
+    examples: Some(("decimal price = 1.5d + 0.1f64;", "decimal price = 1.5d + 0.1d;")),
+};
+
+pub static DIVISION_BY_ZERO: ErrorCode = ErrorCode {
+    code: "T0024",
+    title: "division by zero",
+    explanation: "A number was divided by zero. This is checked when executing, so it can also come from a variable that holds zero.",
+    examples: Some(("i32 zero = 0;\n10 / zero;", "i32 two = 2;\n10 / two;")),
+};
+
+pub static OVERFLOW: ErrorCode = ErrorCode {
+    code: "T0025",
+    title: "integer overflow",
+    explanation: "The result of an operation doesn't fit in its integer type. A wider type or a `bigint` can hold it, and `--promote-overflow` turns overflowing results into `bigint`s automatically.",
+    examples: Some(("255u8 + 1u8;", "255u16 + 1u16;")),
+};
+
+pub static NEGATED_UNSIGNED: ErrorCode = ErrorCode {
+    code: "T0026",
+    title: "negation of an unsigned integer",
+    explanation: "Unsigned integers cannot be negative, so they cannot be negated. The value must be cast to a signed type first.",
+    examples: Some(("-(5u8);", "-(5u8 as i16);")),
+};
+
+pub static INTERRUPTED: ErrorCode = ErrorCode {
+    code: "T0027",
+    title: "execution interrupted",
+    explanation:
+        "The execution was stopped before it finished, usually because Ctrl-C was pressed.",
+    examples: None,
+};
+
+pub static IO_ERROR: ErrorCode = ErrorCode {
+    code: "T0028",
+    title: "file could not be read or written",
+    explanation: "A source file could not be read, or a formatted file could not be written back. Check that the file exists and that its permissions allow it.",
+    examples: Some(("tray run scirpt.tray", "tray run script.tray")),
+};
+
+pub static UNDEFINED_FUNCTION: ErrorCode = ErrorCode {
+    code: "T0029",
+    title: "undefined function",
+    explanation: "A function was called that doesn't exist. tray scripts can't define functions, they call the ones registered by the program embedding tray with `Interpreter::register_fn`.",
+    examples: Some(("let n = len([1, 2]);", "let n = [1, 2].len();")),
+};
+
+pub static EXPECTED_NAME: ErrorCode = ErrorCode {
+    code: "T0030",
+    title: "expected a name after `.`, `::`, `let` or `var`",
+    explanation: "`.` accesses a field or method of an object, `::` a static member of a class, and `let` and `var` declare a variable, whose name must follow them.",
+    examples: Some(("let = 3;", "let x = 3;")),
+};
+
+pub static EXPECTED_COMMA: ErrorCode = ErrorCode {
+    code: "T0031",
+    title: "expected `,` or `)` after an argument",
+    explanation: "The arguments of a call are separated by `,` and end with `)`. Likewise, the type arguments of a generic type are separated by `,` and end with `>`, and the elements of a `Vec` literal are separated by `,` and end with `]`.",
+    examples: Some(("[1, 2].insert(0 5);", "[1, 2].insert(0, 5);")),
+};
+
+pub static WRONG_ARGUMENT_COUNT: ErrorCode = ErrorCode {
+    code: "T0032",
+    title: "wrong number of arguments",
+    explanation: "A function or method was called with more or fewer arguments than it takes.",
+    examples: Some(("[1, 2].push(3, 4);", "[1, 2].push(3);")),
+};
+
+pub static UNKNOWN_MEMBER: ErrorCode = ErrorCode {
+    code: "T0033",
+    title: "unknown member",
+    explanation: "A field, method or static member was accessed that the class doesn't have. Classes only have the members registered for them by the program embedding tray.",
+    examples: Some(("[1, 2].size();", "[1, 2].len();")),
+};
+
+pub static NATIVE_ERROR: ErrorCode = ErrorCode {
+    code: "T0034",
+    title: "error returned by a native function",
+    explanation: "A function, method or static function implemented in Rust by the program embedding tray returned an error. The message comes from that program, and the stack trace shows the calls that led to it. In the example, `half` is registered to fail for odd numbers.",
+    examples: Some(("half(3);", "half(4);")),
+};
+
+pub static OUT_OF_FUEL: ErrorCode = ErrorCode {
+    code: "T0035",
+    title: "out of fuel",
+    explanation: "The script took more steps than the fuel limit set by the program embedding tray allows for one run. A step is an expression evaluated by the tree-walking engine, or an instruction executed by the virtual machine.",
+    examples: None,
+};
+
+pub static TIMED_OUT: ErrorCode = ErrorCode {
+    code: "T0036",
+    title: "timed out",
+    explanation: "The script ran for longer than the timeout set by the program embedding tray.",
+    examples: None,
+};
+
+pub static CALL_DEPTH_EXCEEDED: ErrorCode = ErrorCode {
+    code: "T0037",
+    title: "expressions nested too deeply",
+    explanation: "Expressions can be nested in each other at most 256 levels deep, like operands in parentheses or in a long chain of operations, and the program embedding tray can lower that limit when running them. Splitting the expression over several variables keeps it under the limit.",
+    examples: None,
+};
+
+pub static OUT_OF_MEMORY: ErrorCode = ErrorCode {
+    code: "T0038",
+    title: "out of memory",
+    explanation: "The values held by the variables of the script, along with the value being computed, would take more memory than the limit set by the program embedding tray.",
+    examples: None,
+};
+
+pub static STRING_TOO_LONG: ErrorCode = ErrorCode {
+    code: "T0039",
+    title: "string too long",
+    explanation: "A string is longer than the limit set by the program embedding tray, counted in bytes of UTF-8.",
+    examples: None,
+};
+
+pub static CAPABILITY_DISABLED: ErrorCode = ErrorCode {
+    code: "T0040",
+    title: "capability disabled",
+    explanation: "The script called a function that accesses the file system, processes or environment variables, which the program embedding tray doesn't allow.",
+    examples: None,
+};
+
+pub static INVALID_MODULE: ErrorCode = ErrorCode {
+    code: "T0041",
+    title: "invalid compiled module",
+    explanation: "A `.trayc` file isn't a module written by `tray build`, was written by another version of tray or is corrupted. Run `tray build` on its source again.",
+    examples: None,
+};
+
+pub static ASSIGNED_IMMUTABLE: ErrorCode = ErrorCode {
+    code: "T0042",
+    title: "assignment to a variable declared with `let`",
+    explanation: "Variables declared with `let` keep their initial value. Declare them with `var` or with a type to be able to assign them.",
+    examples: Some(("let count = 1;\ncount = 2;", "var count = 1;\ncount = 2;")),
+};
+
+pub static WRONG_TYPE_ARGUMENTS: ErrorCode = ErrorCode {
+    code: "T0043",
+    title: "wrong number of type arguments",
+    explanation: "`Vec<T>` takes the type of its elements between `<` and `>`. Other types aren't generic and take no type arguments.",
+    examples: Some(("Vec numbers = [1, 2];", "Vec<i32> numbers = [1, 2];")),
+};
+
+pub static INDEX_OUT_OF_BOUNDS: ErrorCode = ErrorCode {
+    code: "T0044",
+    title: "index out of bounds",
+    explanation: "The elements of a `Vec` of length `n` have the indexes `0` to `n - 1`. Reading, assigning or removing an element at another index fails when executing, as does `pop` on an empty `Vec`, while `insert` also accepts `n` to add an element at the end.",
+    examples: Some((
+        "Vec<i32> numbers = [1, 2, 3];\nnumbers[3];",
+        "Vec<i32> numbers = [1, 2, 3];\nnumbers[2];",
+    )),
+};
+
+pub static NOT_A_VEC: ErrorCode = ErrorCode {
+    code: "T0045",
+    title: "value isn't a `Vec`",
+    explanation: "Only `Vec` values can be indexed with `[<index>]` and iterated with `for`.",
+    examples: Some((
+        "i32 count = 3;\nfor number in count { number }",
+        "Vec<i32> numbers = [1, 2, 3];\nfor number in numbers { number }",
+    )),
+};
+
+pub static UNKNOWN_ELEMENT_TYPE: ErrorCode = ErrorCode {
+    code: "T0046",
+    title: "unknown element type of an empty `Vec`",
+    explanation: "The type of the elements of a `Vec` literal is the type the `Vec` is stored as, or the type of its first element. An empty `[]` has no element to tell it. Declared with `let` or `var`, it gets the type of the first element a statement of its own pushes, inserts or assigns to it, like `v.push(1);`, and can't be used before. Otherwise it needs a declared type.",
+    examples: Some(("let names = [];", "Vec<String> names = [];")),
+};
+
+pub static MISPLACED_LAMBDA: ErrorCode = ErrorCode {
+    code: "T0047",
+    title: "lambda outside of `map` or `filter`",
+    explanation: "A lambda like `x => x * 2` is only a value while it is passed to the `map` or `filter` methods of a `Vec`, which call it with each element.",
+    examples: Some((
+        "Vec<i32> numbers = [1, 2];\nlet double = x => x * 2;",
+        "Vec<i32> numbers = [1, 2];\nlet doubled = numbers.map(x => x * 2);",
+    )),
+};
+
+pub static UNCLOSED_BRACKET: ErrorCode = ErrorCode {
+    code: "T0048",
+    title: "unclosed bracket or brace",
+    explanation: "Every `[` must be closed by a `]` before the end of its statement, and every `{` must be closed by a `}`.",
+    examples: Some((
+        "Vec<i32> numbers = [1, 2, 3;",
+        "Vec<i32> numbers = [1, 2, 3];",
+    )),
+};
+
+pub static MALFORMED_LOOP: ErrorCode = ErrorCode {
+    code: "T0049",
+    title: "malformed `for` loop",
+    explanation: "A `for` loop is written `for <name> in <vec> { <statements> }`, the statements being run for each element of the `Vec` with the element in `<name>`.",
+    examples: Some((
+        "Vec<i32> numbers = [1, 2];\nfor number numbers { number }",
+        "Vec<i32> numbers = [1, 2];\nfor number in numbers { number }",
+    )),
+};
+
+/// Every error code, in order.
+pub static ERROR_CODES: &[&ErrorCode] = &[
+    &UNRECOGNIZED_CHARACTER,
+    &INVALID_NUMBER,
+    &FLOAT_WITH_INTEGER_SUFFIX,
+    &UNKNOWN_NUMBER_SUFFIX,
+    &UNDERSCORE_AFTER_DOT,
+    &MULTIPLE_DOTS,
+    &INVALID_HEX_ESCAPE,
+    &INVALID_UNICODE_ESCAPE,
+    &INVALID_ESCAPE,
+    &EMPTY_CHAR,
+    &UNTERMINATED_CHAR,
+    &UNTERMINATED_STRING,
+    &EXPECTED_EXPRESSION,
+    &UNEXPECTED_TOKEN,
+    &UNCLOSED_PARENTHESIS,
+    &UNKNOWN_TYPE,
+    &EXPECTED_TYPE,
+    &MISSING_INITIAL_VALUE,
+    &EXPECTED_NUMBER,
+    &UNDEFINED_VARIABLE,
+    &MISMATCHED_TYPES,
+    &MIXED_SIGNEDNESS,
+    &DECIMAL_WITH_FLOAT,
+    &DIVISION_BY_ZERO,
+    &OVERFLOW,
+    &NEGATED_UNSIGNED,
+    &INTERRUPTED,
+    &IO_ERROR,
+    &UNDEFINED_FUNCTION,
+    &EXPECTED_NAME,
+    &EXPECTED_COMMA,
+    &WRONG_ARGUMENT_COUNT,
+    &UNKNOWN_MEMBER,
+    &NATIVE_ERROR,
+    &OUT_OF_FUEL,
+    &TIMED_OUT,
+    &CALL_DEPTH_EXCEEDED,
+    &OUT_OF_MEMORY,
+    &STRING_TOO_LONG,
+    &CAPABILITY_DISABLED,
+    &INVALID_MODULE,
+    &ASSIGNED_IMMUTABLE,
+    &WRONG_TYPE_ARGUMENTS,
+    &INDEX_OUT_OF_BOUNDS,
+    &NOT_A_VEC,
+    &UNKNOWN_ELEMENT_TYPE,
+    &MISPLACED_LAMBDA,
+    &UNCLOSED_BRACKET,
+    &MALFORMED_LOOP,
+];
+
+/// Finds an error code, ignoring case so that `t0003` works too.
+pub fn find(code: &str) -> Option<&'static ErrorCode> {
+    ERROR_CODES
+        .iter()
+        .copied()
+        .find(|error_code| error_code.code.eq_ignore_ascii_case(code))
+}
+
+impl ErrorCode {
+    /// Returns the long form explanation printed by `tray explain`.
+    pub fn long_explanation(&self) -> String {
+        let mut output = format!("{}: {}\n\n{}\n", self.code, self.title, self.explanation);
+        if let Some((example, fixed)) = self.examples {
+            let indent = |code: &str| {
+                code.lines()
+                    .map(|line| format!("    {line}\n"))
+                    .collect::<String>()
+            };
+            output += &format!(
+                "\nErroneous code example:\n\n{}\nFixed:\n\n{}",
+                indent(example),
+                indent(fixed)
+            );
+        }
+        output
+    }
+}
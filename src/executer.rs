@@ -1,87 +1,1144 @@
-use crate::parser;
-#[derive(Debug)]
-pub enum Result {
-    Number(parser::Number),
+use std::{collections::HashMap, fmt::Display, rc::Rc};
+
+use crate::{
+    array::{self, Array},
+    bigint::BigInt,
+    decimal::Decimal,
+    diagnostic::Diagnostic,
+    error_codes::{self, ErrorCode},
+    formatter,
+    native::{Class, NativeFunction, Object},
+    parser::{self, BinaryOperator, Expression, Number, NumberType, Statement, Type},
+    sandbox::{Limit, Sandbox},
+};
+
+#[derive(Debug, Clone)]
+pub enum Value {
+    Number(Number),
+    Bool(bool),
     Char(char),
     String(String),
+    /// A value of a class registered by the program embedding tray.
+    Object(Object),
+    Vec(Array),
 }
 
-pub fn execute(expr: &parser::Expression) -> Result {
-    match expr {
-        parser::Expression::UnaryOperation { operator, expr } => match operator {
-            parser::UnaryOperator::Plus => {
-                let result = execute(expr);
+impl Value {
+    pub fn value_type(&self) -> Type {
+        match self {
+            Value::Number(number) => Type::Number(number.number_type()),
+            Value::Bool(_) => Type::Bool,
+            Value::Char(_) => Type::Char,
+            Value::String(_) => Type::String,
+            Value::Object(object) => Type::Class(object.class().to_string()),
+            Value::Vec(array) => array.value_type(),
+        }
+    }
 
-                match result {
-                    Result::Number(_) => result,
-                    _ => panic!("tried to execute unary plus operator on non number expression."),
-                }
+    /// Returns how many bytes the value takes on the heap.
+    pub fn heap_size(&self) -> usize {
+        match self {
+            Value::Number(Number::BigInt(number)) => number.heap_size(),
+            Value::Number(Number::Decimal(number)) => number.heap_size(),
+            Value::Number(_) | Value::Bool(_) | Value::Char(_) => 0,
+            Value::String(string) => string.capacity(),
+            Value::Object(object) => object.heap_size(),
+            Value::Vec(array) => array.heap_size(),
+        }
+    }
+
+    /// Converts the value to `target` if they are the same type or if `target` is a number type
+    /// that can hold the value without losing information. A `Vec` converts if each of its
+    /// elements does.
+    pub fn convert_to(self, target: Type) -> Option<Value> {
+        match (self, target) {
+            (Value::Number(number), Type::Number(number_type)) => {
+                number.convert_lossless(number_type).map(Value::Number)
             }
-            parser::UnaryOperator::Minus => {
-                let result = execute(expr);
-                match result {
-                    Result::Number(result) => match result {
-                        parser::Number::Float32(v) => {
-                            Result::Number(parser::Number::Float32(-1. * v))
-                        }
-                        parser::Number::Float64(v) => {
-                            Result::Number(parser::Number::Float64(-1. * v))
-                        }
-                        parser::Number::Int32(v) => Result::Number(parser::Number::Int32(-1 * v)),
-                        parser::Number::Int64(v) => Result::Number(parser::Number::Int64(-1 * v)),
-                        parser::Number::Int128(v) => Result::Number(parser::Number::Int128(-1 * v)),
-                    },
-                    _ => panic!("tried to apply unary minus operator to a non number expression"),
-                }
+            (Value::Vec(array), Type::Vec(element_type)) => {
+                array.convert_to(*element_type).map(Value::Vec)
             }
-        },
-        parser::Expression::BinaryOperation {
+            (value, target) if value.value_type() == target => Some(value),
+            _ => None,
+        }
+    }
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Number(number) => write!(f, "{number}"),
+            Value::Bool(bool) => write!(f, "{bool}"),
+            Value::Char(char) => write!(f, "'{char}'"),
+            Value::String(string) => write!(f, "\"{string}\""),
+            Value::Object(object) => write!(f, "{object}"),
+            Value::Vec(array) => write!(f, "{array}"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum RuntimeError {
+    TypeError {
+        code: &'static ErrorCode,
+        message: String,
+    },
+    ArithmeticError {
+        code: &'static ErrorCode,
+        message: String,
+    },
+    NameError {
+        code: &'static ErrorCode,
+        message: String,
+    },
+    /// An index outside of a `Vec`.
+    IndexError {
+        code: &'static ErrorCode,
+        message: String,
+    },
+    /// A native function returned an error.
+    NativeError {
+        code: &'static ErrorCode,
+        message: String,
+        /// The calls that led to the error, innermost first.
+        trace: Vec<String>,
+    },
+    /// The script went over one of the [`Limits`](crate::sandbox::Limits) of the environment.
+    LimitExceeded {
+        code: &'static ErrorCode,
+        limit: Limit,
+        message: String,
+    },
+    /// The execution was stopped by an [`InterruptHandle`](crate::sandbox::InterruptHandle), usually because the user pressed Ctrl-C.
+    Interrupted,
+}
+
+impl Display for RuntimeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RuntimeError::TypeError { message, .. } => write!(f, "Type Error: {message}"),
+            RuntimeError::ArithmeticError { message, .. } => {
+                write!(f, "Arithmetic Error: {message}")
+            }
+            RuntimeError::NameError { message, .. } => write!(f, "Name Error: {message}"),
+            RuntimeError::IndexError { message, .. } => write!(f, "Index Error: {message}"),
+            RuntimeError::NativeError { message, .. } => write!(f, "Native Error: {message}"),
+            RuntimeError::LimitExceeded { message, .. } => write!(f, "Limit Error: {message}"),
+            RuntimeError::Interrupted => f.write_str("Interrupted"),
+        }
+    }
+}
+
+impl RuntimeError {
+    pub fn code(&self) -> &'static ErrorCode {
+        match self {
+            RuntimeError::TypeError { code, .. }
+            | RuntimeError::ArithmeticError { code, .. }
+            | RuntimeError::NameError { code, .. }
+            | RuntimeError::IndexError { code, .. }
+            | RuntimeError::NativeError { code, .. }
+            | RuntimeError::LimitExceeded { code, .. } => code,
+            RuntimeError::Interrupted => &error_codes::INTERRUPTED,
+        }
+    }
+
+    pub fn to_diagnostic(&self) -> Diagnostic {
+        let mut diagnostic = Diagnostic::error(self.code(), self.to_string());
+        if let RuntimeError::NativeError { trace, .. } = self {
+            for frame in trace {
+                diagnostic = diagnostic.with_note(frame.clone());
+            }
+        }
+        diagnostic
+    }
+
+    /// Adds `frame` to the stack trace of a native error.
+    fn in_frame(mut self, frame: impl FnOnce() -> String) -> Self {
+        if let RuntimeError::NativeError { trace, .. } = &mut self {
+            trace.push(frame());
+        }
+        self
+    }
+
+    /// Adds the frame of the statement written by `statement` to the stack trace of a native
+    /// error.
+    pub(crate) fn in_statement(self, statement: impl FnOnce() -> String) -> Self {
+        self.in_frame(|| format!("in statement `{}`", statement()))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Variable {
+    declared_type: Type,
+    /// `None` when the program is only being checked and never executed.
+    value: Option<Value>,
+    /// Whether the variable can be assigned, which variables declared with `let` can't.
+    mutable: bool,
+}
+
+/// The variables, native functions and classes visible to the code being executed, and the
+/// limits on what it can do.
+#[derive(Debug, Clone, Default)]
+pub struct Environment {
+    variables: HashMap<String, Variable>,
+    functions: HashMap<String, Rc<NativeFunction>>,
+    classes: HashMap<String, Rc<Class>>,
+    pub sandbox: Sandbox,
+    /// When enabled, integer operations that overflow their fixed-width type produce a `bigint`
+    /// instead of an [`RuntimeError::ArithmeticError`].
+    pub promote_overflow: bool,
+    /// The sum of the [`Value::heap_size`] of the variables, when they were stored.
+    heap_bytes: usize,
+    /// The statement the last run failed in, numbered like the statements of a
+    /// [`Chunk`](crate::bytecode::Chunk).
+    pub(crate) failed_statement: Option<usize>,
+}
+
+/// Variables saved by [`Environment::save`], to be put back by [`Environment::restore`].
+#[derive(Debug, Default)]
+pub(crate) struct SavedVariables(Vec<(String, Option<Variable>)>);
+
+impl Environment {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Declares `name`, replacing any previous variable with the same name.
+    pub fn declare(&mut self, name: &str, declared_type: Type, value: Value) {
+        self.declare_variable(name, declared_type, value, true);
+    }
+
+    /// Declares `name` like [`Environment::declare`], as a variable which can't be assigned
+    /// unless it is `mutable`.
+    pub(crate) fn declare_variable(
+        &mut self,
+        name: &str,
+        declared_type: Type,
+        value: Value,
+        mutable: bool,
+    ) {
+        self.heap_bytes += value.heap_size();
+        let previous = self.variables.insert(
+            name.to_string(),
+            Variable {
+                declared_type,
+                value: Some(value),
+                mutable,
+            },
+        );
+        self.forget(previous.and_then(|variable| variable.value));
+    }
+
+    /// Stops counting `value`, which no variable holds anymore, in the heap size. A `Vec` may
+    /// have grown since it was stored, so this never goes below zero.
+    fn forget(&mut self, value: Option<Value>) {
+        if let Some(value) = value {
+            self.heap_bytes = self.heap_bytes.saturating_sub(value.heap_size());
+        }
+    }
+
+    /// Saves the variables named `names`, or that they don't exist, before a loop declares
+    /// them.
+    pub(crate) fn save<'a>(&self, names: impl IntoIterator<Item = &'a str>) -> SavedVariables {
+        SavedVariables(
+            names
+                .into_iter()
+                .map(|name| (name.to_string(), self.variables.get(name).cloned()))
+                .collect(),
+        )
+    }
+
+    /// Puts back the variables saved by [`Environment::save`], removing the ones which didn't
+    /// exist then.
+    pub(crate) fn restore(&mut self, saved: SavedVariables) {
+        for (name, variable) in saved.0 {
+            let previous = match variable {
+                Some(variable) => {
+                    if let Some(value) = &variable.value {
+                        self.heap_bytes += value.heap_size();
+                    }
+                    self.variables.insert(name, variable)
+                }
+                None => self.variables.remove(&name),
+            };
+            self.forget(previous.and_then(|variable| variable.value));
+        }
+    }
+
+    /// Returns the type, value and mutability of the variable `name`, if it has a value.
+    pub(crate) fn get_variable(&self, name: &str) -> Option<(&Type, &Value, bool)> {
+        let variable = self.variables.get(name)?;
+        Some((
+            &variable.declared_type,
+            variable.value.as_ref()?,
+            variable.mutable,
+        ))
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.variables
+            .get(name)
+            .and_then(|variable| variable.value.as_ref())
+    }
+
+    /// Declares `name` with a type but no value, for checking code without executing it.
+    pub(crate) fn declare_unset(&mut self, name: &str, declared_type: Type, mutable: bool) {
+        let previous = self.variables.insert(
+            name.to_string(),
+            Variable {
+                declared_type,
+                value: None,
+                mutable,
+            },
+        );
+        self.forget(previous.and_then(|variable| variable.value));
+    }
+
+    pub fn get_type(&self, name: &str) -> Option<Type> {
+        self.variables
+            .get(name)
+            .map(|variable| variable.declared_type.clone())
+    }
+
+    /// Whether `name` is a variable which can be assigned.
+    pub fn is_mutable(&self, name: &str) -> bool {
+        self.variables
+            .get(name)
+            .is_some_and(|variable| variable.mutable)
+    }
+
+    /// Registers `function`, replacing any function with the same name.
+    pub fn register_function(&mut self, function: NativeFunction) {
+        self.functions
+            .insert(function.name.clone(), Rc::new(function));
+    }
+
+    pub fn get_function(&self, name: &str) -> Option<&NativeFunction> {
+        self.functions.get(name).map(Rc::as_ref)
+    }
+
+    /// Registers `class`, replacing any class with the same name.
+    pub fn register_class(&mut self, class: Class) {
+        self.classes.insert(class.name.to_string(), Rc::new(class));
+    }
+
+    pub(crate) fn get_class(&self, name: &str) -> Result<&Class, RuntimeError> {
+        self.classes
+            .get(name)
+            .map(Rc::as_ref)
+            .ok_or_else(|| RuntimeError::NameError {
+                code: &error_codes::UNKNOWN_TYPE,
+                message: format!("Cannot find a class named `{name}`."),
+            })
+    }
+
+    /// Returns the sum of the [`Value::heap_size`] of the variables.
+    pub fn heap_bytes(&self) -> usize {
+        self.heap_bytes
+    }
+
+    pub(crate) fn clear_variables(&mut self) {
+        self.variables.clear();
+        self.heap_bytes = 0;
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.variables.keys().map(String::as_str)
+    }
+
+    /// Returns the names of the fields and methods of the values of type `value_type`.
+    pub fn member_names(&self, value_type: &Type) -> Vec<&str> {
+        match value_type {
+            Type::Vec(_) => array::method_names().collect(),
+            Type::Class(name) => self.classes.get(name).map_or(vec![], |class| {
+                class
+                    .fields
+                    .keys()
+                    .chain(class.methods.keys())
+                    .map(String::as_str)
+                    .collect()
+            }),
+            _ => vec![],
+        }
+    }
+
+    /// Returns the names of the functions and constants of the class `name`.
+    pub fn static_member_names(&self, name: &str) -> Vec<&str> {
+        self.classes.get(name).map_or(vec![], |class| {
+            class
+                .functions
+                .keys()
+                .chain(class.constants.keys())
+                .map(String::as_str)
+                .collect()
+        })
+    }
+
+    pub(crate) fn assign(&mut self, name: &str, value: Value) -> Result<(), RuntimeError> {
+        let variable = self
+            .variables
+            .get_mut(name)
+            .ok_or_else(|| undefined_variable(name))?;
+        if !variable.mutable {
+            return Err(assigned_immutable(name));
+        }
+        let value = convert(value, &variable.declared_type, name)?;
+        self.heap_bytes += value.heap_size();
+        let previous = variable.value.replace(value);
+        self.forget(previous);
+        Ok(())
+    }
+}
+
+pub fn undefined_function(name: &str) -> RuntimeError {
+    RuntimeError::NameError {
+        code: &error_codes::UNDEFINED_FUNCTION,
+        message: format!("Cannot find a function named `{name}`."),
+    }
+}
+
+pub fn assigned_immutable(name: &str) -> RuntimeError {
+    RuntimeError::NameError {
+        code: &error_codes::ASSIGNED_IMMUTABLE,
+        message: format!("Cannot assign to `{name}`, which is declared with `let`."),
+    }
+}
+
+pub fn undefined_variable(name: &str) -> RuntimeError {
+    RuntimeError::NameError {
+        code: &error_codes::UNDEFINED_VARIABLE,
+        message: format!("Cannot find a variable named `{name}`."),
+    }
+}
+
+pub(crate) fn convert(value: Value, target: &Type, name: &str) -> Result<Value, RuntimeError> {
+    value
+        .clone()
+        .convert_to(target.clone())
+        .ok_or_else(|| RuntimeError::TypeError {
+            code: &error_codes::MISMATCHED_TYPES,
+            message: format!(
+                "Cannot store {value} of type `{}` in `{name}` of type `{target}`.",
+                value.value_type()
+            ),
+        })
+}
+
+/// Returns the type both operands of a binary operation are converted to before it is applied.
+///
+/// Floating point types win over integral types, and wider types win over narrower ones.
+/// Signed and unsigned integers are never promoted into each other: mixing them requires an
+/// explicit `as` cast. `decimal` wins over every integral type, `bigint` wins over the
+/// fixed-width integers, and `decimal` cannot be mixed with floating point types without a cast
+/// because it would silently lose its exactness.
+pub fn promote(
+    left: NumberType,
+    right: NumberType,
+    operator: &BinaryOperator,
+) -> Result<NumberType, RuntimeError> {
+    if left == NumberType::Decimal || right == NumberType::Decimal {
+        if left.is_floating_point() || right.is_floating_point() {
+            return Err(RuntimeError::TypeError {
+                code: &error_codes::DECIMAL_WITH_FLOAT,
+                message: format!(
+                    "Cannot apply `{}` to `{left}` and `{right}` because a decimal would lose its exactness. Use an explicit cast like `as decimal` or `as f64`.",
+                    operator.symbol()
+                ),
+            });
+        }
+        return Ok(NumberType::Decimal);
+    }
+
+    if left.is_floating_point() || right.is_floating_point() {
+        if left == NumberType::Float64 || right == NumberType::Float64 {
+            return Ok(NumberType::Float64);
+        }
+        return Ok(NumberType::Float32);
+    }
+
+    if left == NumberType::BigInt || right == NumberType::BigInt {
+        return Ok(NumberType::BigInt);
+    }
+
+    if left.is_signed() != right.is_signed() {
+        return Err(RuntimeError::TypeError {
+            code: &error_codes::MIXED_SIGNEDNESS,
+            message: format!(
+                "Cannot apply `{}` to `{left}` and `{right}` because signed and unsigned integers cannot be mixed. Use an explicit cast like `as {left}` or `as {right}`.",
+                operator.symbol()
+            ),
+        });
+    }
+
+    if left.bits() >= right.bits() {
+        Ok(left)
+    } else {
+        Ok(right)
+    }
+}
+
+fn division_by_zero(left: &Number) -> RuntimeError {
+    RuntimeError::ArithmeticError {
+        code: &error_codes::DIVISION_BY_ZERO,
+        message: format!("Cannot divide `{left}` by zero."),
+    }
+}
+
+fn bigint_operation(
+    operator: &BinaryOperator,
+    left: &BigInt,
+    right: &BigInt,
+) -> Option<BigInt> {
+    Some(match operator {
+        BinaryOperator::Plus => left + right,
+        BinaryOperator::Minus => left - right,
+        BinaryOperator::Divide => left.div_rem(right)?.0,
+        BinaryOperator::Multiply => left * right,
+    })
+}
+
+fn decimal_operation(
+    operator: &BinaryOperator,
+    left: &Decimal,
+    right: &Decimal,
+) -> Option<Decimal> {
+    Some(match operator {
+        BinaryOperator::Plus => left + right,
+        BinaryOperator::Minus => left - right,
+        BinaryOperator::Divide => left.checked_div(right)?,
+        BinaryOperator::Multiply => left * right,
+    })
+}
+
+/// Applies `operator` to two numbers after promoting them with [`promote`].
+///
+/// Integral operations are checked: overflowing the promoted type or dividing by zero is
+/// reported as an [`RuntimeError::ArithmeticError`] instead of wrapping or panicking, unless
+/// `promote_overflow` is enabled, in which case overflows are computed as a `bigint`.
+pub fn binary_operation(
+    operator: &BinaryOperator,
+    left: &Number,
+    right: &Number,
+    promote_overflow: bool,
+) -> Result<Number, RuntimeError> {
+    let number_type = promote(left.number_type(), right.number_type(), operator)?;
+
+    if number_type == NumberType::BigInt {
+        return bigint_operation(operator, &left.to_bigint(), &right.to_bigint())
+            .map(Number::BigInt)
+            .ok_or_else(|| division_by_zero(left));
+    }
+    if number_type == NumberType::Decimal {
+        return decimal_operation(operator, &left.to_decimal(), &right.to_decimal())
+            .map(Number::Decimal)
+            .ok_or_else(|| division_by_zero(left));
+    }
+
+    if number_type == NumberType::Float32 {
+        let (left, right) = (left.as_f32(), right.as_f32());
+        return Ok(Number::Float32(match operator {
+            BinaryOperator::Plus => left + right,
+            BinaryOperator::Minus => left - right,
+            BinaryOperator::Divide => left / right,
+            BinaryOperator::Multiply => left * right,
+        }));
+    }
+    if number_type == NumberType::Float64 {
+        let (left, right) = (left.as_f64(), right.as_f64());
+        return Ok(Number::Float64(match operator {
+            BinaryOperator::Plus => left + right,
+            BinaryOperator::Minus => left - right,
+            BinaryOperator::Divide => left / right,
+            BinaryOperator::Multiply => left * right,
+        }));
+    }
+
+    if *operator == BinaryOperator::Divide && right.is_zero() {
+        return Err(division_by_zero(left));
+    }
+
+    let result = if number_type.is_signed() {
+        let (l, r) = (left.as_i128(), right.as_i128());
+        match operator {
+            BinaryOperator::Plus => l.checked_add(r),
+            BinaryOperator::Minus => l.checked_sub(r),
+            BinaryOperator::Divide => l.checked_div(r),
+            BinaryOperator::Multiply => l.checked_mul(r),
+        }
+        .and_then(|result| Number::from_i128(result, number_type))
+    } else {
+        let (l, r) = (left.as_u128(), right.as_u128());
+        match operator {
+            BinaryOperator::Plus => l.checked_add(r),
+            BinaryOperator::Minus => l.checked_sub(r),
+            BinaryOperator::Divide => l.checked_div(r),
+            BinaryOperator::Multiply => l.checked_mul(r),
+        }
+        .and_then(|result| Number::from_u128(result, number_type))
+    };
+
+    if result.is_none() && promote_overflow {
+        return binary_operation(operator, &left.cast(NumberType::BigInt), right, false);
+    }
+
+    result.ok_or_else(|| RuntimeError::ArithmeticError {
+        code: &error_codes::OVERFLOW,
+        message: format!(
+            "`{left} {} {right}` overflows the range of `{number_type}`.",
+            operator.symbol()
+        ),
+    })
+}
+
+/// Negates a number, failing for unsigned types and for the minimum value of signed ones unless
+/// `promote_overflow` is enabled.
+pub fn negate(number: &Number, promote_overflow: bool) -> Result<Number, RuntimeError> {
+    let number_type = number.number_type();
+    match number {
+        Number::Float32(v) => Ok(Number::Float32(-v)),
+        Number::Float64(v) => Ok(Number::Float64(-v)),
+        Number::BigInt(v) => Ok(Number::BigInt(-v)),
+        Number::Decimal(v) => Ok(Number::Decimal(-v)),
+        _ if !number_type.is_signed() => Err(RuntimeError::TypeError {
+            code: &error_codes::NEGATED_UNSIGNED,
+            message: format!("Cannot negate `{number}` because `{number_type}` is unsigned."),
+        }),
+        _ => number
+            .as_i128()
+            .checked_neg()
+            .and_then(|result| Number::from_i128(result, number_type))
+            .or_else(|| promote_overflow.then(|| Number::BigInt(-&number.to_bigint())))
+            .ok_or_else(|| RuntimeError::ArithmeticError {
+                code: &error_codes::OVERFLOW,
+                message: format!("`-{number}` overflows the range of `{number_type}`."),
+            }),
+    }
+}
+
+pub(crate) fn unknown_member(object_type: &Type, kind: &str, name: &str) -> RuntimeError {
+    RuntimeError::NameError {
+        code: &error_codes::UNKNOWN_MEMBER,
+        message: format!("`{object_type}` has no {kind} named `{name}`."),
+    }
+}
+
+/// Returns the field or method `name` of values of type `object_type`, `members` selecting
+/// which of the two it is from the members of their class.
+pub(crate) fn get_member<'a>(
+    environment: &'a Environment,
+    object_type: &Type,
+    name: &str,
+    kind: &str,
+    members: fn(&Class) -> &HashMap<String, NativeFunction>,
+) -> Result<&'a NativeFunction, RuntimeError> {
+    let Type::Class(class) = object_type else {
+        return Err(unknown_member(object_type, kind, name));
+    };
+    members(environment.get_class(class)?)
+        .get(name)
+        .ok_or_else(|| unknown_member(object_type, kind, name))
+}
+
+pub(crate) fn get_constant<'a>(
+    environment: &'a Environment,
+    class: &str,
+    name: &str,
+) -> Result<&'a Value, RuntimeError> {
+    environment
+        .get_class(class)?
+        .constants
+        .get(name)
+        .ok_or_else(|| unknown_member(&Type::Class(class.to_string()), "constant", name))
+}
+
+pub(crate) fn get_static_function<'a>(
+    environment: &'a Environment,
+    class: &str,
+    name: &str,
+) -> Result<&'a NativeFunction, RuntimeError> {
+    environment
+        .get_class(class)?
+        .functions
+        .get(name)
+        .ok_or_else(|| unknown_member(&Type::Class(class.to_string()), "static function", name))
+}
+
+/// Calls `function` with `receiver`, the object of a method or field, and `arguments`. Native
+/// errors get a stack frame naming `call`, the expression calling it.
+fn call_native(
+    function: &NativeFunction,
+    receiver: Option<Value>,
+    arguments: Vec<Value>,
+    call: &Expression,
+    environment: &Environment,
+) -> Result<Value, RuntimeError> {
+    environment
+        .sandbox
+        .check_capability(function.capability, &function.name)?;
+    if arguments.len() != function.parameters.len() {
+        return Err(function.wrong_argument_count(arguments.len()));
+    }
+    let values = receiver.into_iter().chain(arguments).collect();
+    call_with_trace(function, values, || formatter::format_expression(call))
+}
+
+/// Calls `function` with `values`, which must start with the object for a method or field.
+/// Native errors get a stack frame naming the call, written by `call`.
+pub(crate) fn call_with_trace(
+    function: &NativeFunction,
+    values: Vec<Value>,
+    call: impl FnOnce() -> String,
+) -> Result<Value, RuntimeError> {
+    function.call(values).map_err(|err| {
+        err.in_frame(|| {
+            format!(
+                "in native function `{}`, called as `{}`",
+                function.name,
+                call()
+            )
+        })
+    })
+}
+
+// The operations below are shared by the tree-walker and the virtual machine, so that both give
+// the same results and errors.
+
+pub(crate) fn unary_plus(value: Value) -> Result<Value, RuntimeError> {
+    match value {
+        Value::Number(_) => Ok(value),
+        value => Err(RuntimeError::TypeError {
+            code: &error_codes::EXPECTED_NUMBER,
+            message: format!("tried to execute unary plus operator on non number value {value}."),
+        }),
+    }
+}
+
+pub(crate) fn unary_minus(value: Value, promote_overflow: bool) -> Result<Value, RuntimeError> {
+    match value {
+        Value::Number(number) => Ok(Value::Number(negate(&number, promote_overflow)?)),
+        value => Err(RuntimeError::TypeError {
+            code: &error_codes::EXPECTED_NUMBER,
+            message: format!("tried to apply unary minus operator to non number value {value}."),
+        }),
+    }
+}
+
+pub(crate) fn binary(
+    operator: &BinaryOperator,
+    left: &Value,
+    right: &Value,
+    promote_overflow: bool,
+) -> Result<Value, RuntimeError> {
+    if let (Value::Number(left), Value::Number(right)) = (left, right) {
+        Ok(Value::Number(binary_operation(
             operator,
             left,
             right,
+            promote_overflow,
+        )?))
+    } else {
+        Err(RuntimeError::TypeError {
+            code: &error_codes::EXPECTED_NUMBER,
+            message: format!(
+                "tried to do a binary operation on non number values {left} and {right}."
+            ),
+        })
+    }
+}
+
+pub(crate) fn cast(value: Value, target: NumberType) -> Result<Value, RuntimeError> {
+    match value {
+        Value::Number(number) => Ok(Value::Number(number.cast(target))),
+        value => Err(RuntimeError::TypeError {
+            code: &error_codes::EXPECTED_NUMBER,
+            message: format!("Cannot cast non number value {value} to `{target}`."),
+        }),
+    }
+}
+
+/// Makes the `Vec` of a literal, whose elements are converted to `element_type` or to the type
+/// of the first element when the literal wasn't type checked.
+pub(crate) fn vec_literal(
+    element_type: Option<&Type>,
+    elements: Vec<Value>,
+) -> Result<Value, RuntimeError> {
+    let element_type = element_type
+        .cloned()
+        .or_else(|| elements.first().map(Value::value_type))
+        .ok_or_else(unknown_element_type)?;
+    Ok(Value::Vec(Array::collect(element_type, elements)?))
+}
+
+pub(crate) fn unknown_element_type() -> RuntimeError {
+    RuntimeError::TypeError {
+        code: &error_codes::UNKNOWN_ELEMENT_TYPE,
+        message: String::from(
+            "Cannot tell the type of the elements of an empty `[]`. Declare it with a type like `Vec<i32>`.",
+        ),
+    }
+}
+
+/// Returns the error for using a value of type `found` where a `Vec` is needed to `action`.
+pub(crate) fn not_a_vec(action: &str, found: &Type) -> RuntimeError {
+    RuntimeError::TypeError {
+        code: &error_codes::NOT_A_VEC,
+        message: format!("Cannot {action} a value of type `{found}`, which is not a `Vec`."),
+    }
+}
+
+pub(crate) fn misplaced_lambda() -> RuntimeError {
+    RuntimeError::TypeError {
+        code: &error_codes::MISPLACED_LAMBDA,
+        message: String::from(
+            "A lambda can only be passed to the `map` and `filter` methods of a `Vec`.",
+        ),
+    }
+}
+
+/// Returns the element at `index` of `object`, which must be a `Vec`.
+pub(crate) fn index(object: &Value, index: &Value) -> Result<Value, RuntimeError> {
+    match object {
+        Value::Vec(array) => array.get(index),
+        object => Err(not_a_vec("index", &object.value_type())),
+    }
+}
+
+/// Replaces the element at `index` of `object`, which must be a `Vec`, by `value`.
+pub(crate) fn store_index(object: &Value, index: &Value, value: Value) -> Result<(), RuntimeError> {
+    match object {
+        Value::Vec(array) => array.set(index, value),
+        object => Err(not_a_vec("index", &object.value_type())),
+    }
+}
+
+/// Returns the `Vec` a `for` loop iterates over.
+pub(crate) fn iterate(iterable: Value) -> Result<Array, RuntimeError> {
+    match iterable {
+        Value::Vec(array) => Ok(array),
+        iterable => Err(not_a_vec("iterate over", &iterable.value_type())),
+    }
+}
+
+/// Evaluates `expr`, within the limits of the sandbox of `environment`.
+pub fn execute(expr: &Expression, environment: &mut Environment) -> Result<Value, RuntimeError> {
+    environment.sandbox.enter()?;
+    let result = evaluate(expr, environment);
+    environment.sandbox.leave();
+    let value = result?;
+    environment
+        .sandbox
+        .check_value(&value, environment.heap_bytes())?;
+    Ok(value)
+}
+
+/// Evaluates each of `expressions`, in order.
+fn execute_all(
+    expressions: &[Expression],
+    environment: &mut Environment,
+) -> Result<Vec<Value>, RuntimeError> {
+    expressions
+        .iter()
+        .map(|expression| execute(expression, environment))
+        .collect()
+}
+
+fn evaluate(expr: &Expression, environment: &mut Environment) -> Result<Value, RuntimeError> {
+    match expr {
+        Expression::UnaryOperation { operator, expr } => match operator {
+            parser::UnaryOperator::Plus => unary_plus(execute(expr, environment)?),
+            parser::UnaryOperator::Minus => {
+                unary_minus(execute(expr, environment)?, environment.promote_overflow)
+            }
+        },
+        Expression::BinaryOperations { first, operations } => {
+            // Every operation takes a step of the run, like an expression.
+            let mut value = execute(first, environment)?;
+            for (operator, operand) in operations {
+                environment.sandbox.consume_fuel()?;
+                let operand = execute(operand, environment)?;
+                value = binary(operator, &value, &operand, environment.promote_overflow)?;
+                environment
+                    .sandbox
+                    .check_value(&value, environment.heap_bytes())?;
+            }
+            Ok(value)
+        }
+        Expression::Cast { expr, target } => cast(execute(expr, environment)?, *target),
+        Expression::ParenthesisExpression(expr) => execute(expr, environment),
+        // The arguments are evaluated before the function is looked up, like in the virtual
+        // machine.
+        Expression::Call {
+            function,
+            arguments,
         } => {
-            let left = execute(left);
-            let right = execute(right);
-
-            if let (Result::Number(left), Result::Number(right)) = (left, right) {
-                if left.is_floating_point(&right) {
-                    match operator {
-                        parser::BinaryOperator::Plus => {
-                            Result::Number(parser::Number::Float64(left.as_f64() + right.as_f64()))
-                        }
-                        parser::BinaryOperator::Minus => {
-                            Result::Number(parser::Number::Float64(left.as_f64() - right.as_f64()))
-                        }
-                        parser::BinaryOperator::Divide => {
-                            Result::Number(parser::Number::Float64(left.as_f64() / right.as_f64()))
-                        }
-                        parser::BinaryOperator::Multiply => {
-                            Result::Number(parser::Number::Float64(left.as_f64() * right.as_f64()))
-                        }
-                    }
-                } else {
-                    match operator {
-                        parser::BinaryOperator::Plus => {
-                            Result::Number(parser::Number::Int128(left.as_i128() + right.as_i128()))
-                        }
-                        parser::BinaryOperator::Minus => {
-                            Result::Number(parser::Number::Int128(left.as_i128() - right.as_i128()))
-                        }
-                        parser::BinaryOperator::Divide => {
-                            Result::Number(parser::Number::Int128(left.as_i128() / right.as_i128()))
-                        }
-                        parser::BinaryOperator::Multiply => {
-                            Result::Number(parser::Number::Int128(left.as_i128() * right.as_i128()))
-                        }
-                    }
+            let arguments = execute_all(arguments, environment)?;
+            let function = environment
+                .get_function(function)
+                .ok_or_else(|| undefined_function(function))?;
+            call_native(function, None, arguments, expr, environment)
+        }
+        Expression::Field { object, field } => {
+            let object = execute(object, environment)?;
+            let getter = get_member(environment, &object.value_type(), field, "field", |class| {
+                &class.fields
+            })?;
+            call_native(getter, Some(object), vec![], expr, environment)
+        }
+        Expression::MethodCall {
+            object,
+            method,
+            arguments,
+        } => {
+            let object = execute(object, environment)?;
+            if let Some(lambda) = lambda_argument(method, arguments) {
+                return call_lambda_method(object, method, lambda, environment);
+            }
+            let arguments = execute_all(arguments, environment)?;
+            if let Value::Vec(array) = &object {
+                return array.call_method(method, arguments);
+            }
+            let method = get_member(
+                environment,
+                &object.value_type(),
+                method,
+                "method",
+                |class| &class.methods,
+            )?;
+            call_native(method, Some(object), arguments, expr, environment)
+        }
+        Expression::StaticMember { class, member } => {
+            get_constant(environment, class, member).cloned()
+        }
+        Expression::StaticCall {
+            class,
+            function,
+            arguments,
+        } => {
+            let arguments = execute_all(arguments, environment)?;
+            let function = get_static_function(environment, class, function)?;
+            call_native(function, None, arguments, expr, environment)
+        }
+        Expression::Vec {
+            elements,
+            element_type,
+        } => {
+            let elements = execute_all(elements, environment)?;
+            vec_literal(element_type.as_ref(), elements)
+        }
+        Expression::Index { object, index } => {
+            let object = execute(object, environment)?;
+            let index = execute(index, environment)?;
+            self::index(&object, &index)
+        }
+        Expression::Lambda { .. } => Err(misplaced_lambda()),
+        Expression::Variable(name) => environment
+            .get(name)
+            .cloned()
+            .ok_or_else(|| undefined_variable(name)),
+        Expression::Number(value) => Ok(Value::Number(value.clone())),
+        Expression::Bool(bool) => Ok(Value::Bool(*bool)),
+        Expression::Char(char) => Ok(Value::Char(*char)),
+        Expression::String(string) => Ok(Value::String(string.clone())),
+    }
+}
+
+/// The lambda given to `map` or `filter`: its parameter, body and the type of its results.
+pub(crate) type Lambda<'a> = (&'a str, &'a Expression, Option<&'a Type>);
+
+/// Returns the lambda of a call to `method` with `arguments`, if it calls `map` or `filter`
+/// with a lambda. Other calls to them fail like calls to unknown methods.
+pub(crate) fn lambda_argument<'a>(method: &str, arguments: &'a [Expression]) -> Option<Lambda<'a>> {
+    match arguments {
+        [Expression::Lambda {
+            parameter,
+            body,
+            result_type,
+        }] if array::LAMBDA_METHODS.contains(&method) => {
+            Some((parameter, body, result_type.as_ref()))
+        }
+        _ => None,
+    }
+}
+
+/// Calls `map` or `filter`, `name`, on `object`. The lambda is evaluated for each element, with
+/// its parameter declared as the element.
+fn call_lambda_method(
+    object: Value,
+    name: &str,
+    (parameter, body, result_type): Lambda,
+    environment: &mut Environment,
+) -> Result<Value, RuntimeError> {
+    let array = iterate(object)?;
+    let saved = environment.save([parameter]);
+    let mut results = vec![];
+    let mut result = Ok(());
+    for element in array.to_vec() {
+        environment.declare_variable(
+            parameter,
+            array.element_type().clone(),
+            element.clone(),
+            false,
+        );
+        result = execute(body, environment).and_then(|value| {
+            if name == "map" {
+                results.push(value);
+            } else if array::filter_result(value)? {
+                results.push(element);
+            }
+            Ok(())
+        });
+        if result.is_err() {
+            break;
+        }
+    }
+    environment.restore(saved);
+    result?;
+    match name {
+        "map" => vec_literal(result_type, results),
+        _ => vec_literal(Some(array.element_type()), results),
+    }
+}
+
+/// Executes a statement, calling `on_value` with the value of expression statements.
+///
+/// When it fails, the statement the error happened in is remembered, numbered from 0 for
+/// `statement` itself like the statements of a [`Chunk`](crate::bytecode::Chunk).
+pub fn execute_statement(
+    statement: &Statement,
+    environment: &mut Environment,
+    mut on_value: impl FnMut(Value),
+) -> Result<(), RuntimeError> {
+    environment.failed_statement = None;
+    run_statement(statement, 0, environment, &mut on_value)
+}
+
+/// Returns how many statements `statement` is made of: itself, and the statements of its body
+/// for loops.
+pub(crate) fn statement_count(statement: &Statement) -> usize {
+    match statement {
+        Statement::For { body, .. } => {
+            1 + body
+                .iter()
+                .map(|(_, statement)| statement_count(statement))
+                .sum::<usize>()
+        }
+        _ => 1,
+    }
+}
+
+/// Executes `statement`, whose number is `index`. Errors get a stack frame naming the
+/// statement, except for loops, whose statements are in frames of their own.
+fn run_statement(
+    statement: &Statement,
+    index: usize,
+    environment: &mut Environment,
+    on_value: &mut dyn FnMut(Value),
+) -> Result<(), RuntimeError> {
+    if let Statement::For {
+        variable,
+        iterable,
+        body,
+    } = statement
+    {
+        let array = execute(iterable, environment)
+            .and_then(iterate)
+            .map_err(|err| err.in_statement(|| formatter::format_loop_header(variable, iterable)));
+        let result = array.and_then(|array| {
+            let saved = environment.save(loop_scope(variable, body));
+            let result = run_loop(&array, variable, (index, body), environment, on_value);
+            environment.restore(saved);
+            result
+        });
+        // Errors of the statements of the body were placed by them already.
+        if result.is_err() {
+            environment.failed_statement.get_or_insert(index);
+        }
+        return result;
+    }
+    let value = run_simple_statement(statement, environment).map_err(|err| {
+        environment.failed_statement = Some(index);
+        err.in_statement(|| formatter::format_statement(statement))
+    })?;
+    if let Some(value) = value {
+        on_value(value);
+    }
+    Ok(())
+}
+
+fn run_simple_statement(
+    statement: &Statement,
+    environment: &mut Environment,
+) -> Result<Option<Value>, RuntimeError> {
+    match statement {
+        Statement::Declaration {
+            declared_type,
+            mutable,
+            name,
+            value,
+        } => {
+            let value = execute(value, environment)?;
+            let (declared_type, value) = match declared_type {
+                Some(declared_type) => {
+                    (declared_type.clone(), convert(value, declared_type, name)?)
                 }
-            } else {
-                panic!("tried to do a binary operation on non number expressions");
+                None => (value.value_type(), value),
+            };
+            environment.declare_variable(name, declared_type, value, *mutable);
+            Ok(None)
+        }
+        Statement::Assignment { name, value } => {
+            let value = execute(value, environment)?;
+            environment.assign(name, value)?;
+            Ok(None)
+        }
+        Statement::IndexAssignment {
+            object,
+            index,
+            value,
+        } => {
+            let object = execute(object, environment)?;
+            let index = execute(index, environment)?;
+            let value = execute(value, environment)?;
+            store_index(&object, &index, value)?;
+            Ok(None)
+        }
+        Statement::For { .. } => unreachable!("Loops are run by `run_statement`."),
+        Statement::Expression(expression) => execute(expression, environment).map(Some),
+    }
+}
+
+/// Returns the names a loop declares: its variable and the variables declared by its body,
+/// which are put back as they were before the loop once it ends.
+pub(crate) fn loop_scope<'a>(variable: &'a str, body: &'a [(usize, Statement)]) -> Vec<&'a str> {
+    let mut names = vec![variable];
+    for (_, statement) in body {
+        if let Statement::Declaration { name, .. } = statement {
+            if !names.contains(&name.as_str()) {
+                names.push(name);
             }
         }
-        parser::Expression::ParenthesisExpression(expr) => execute(expr),
-        parser::Expression::Number(value) => Result::Number(value.clone()),
-        parser::Expression::Char(char) => Result::Char(*char),
-        parser::Expression::String(string) => Result::String(string.clone()),
     }
+    names
+}
+
+/// Runs `body` for each element of `array`, declared as `variable`. The elements are the ones
+/// the array has when the loop starts. `index` is the number of the loop, which the statements
+/// of its body follow.
+fn run_loop(
+    array: &Array,
+    variable: &str,
+    (index, body): (usize, &[(usize, Statement)]),
+    environment: &mut Environment,
+    on_value: &mut dyn FnMut(Value),
+) -> Result<(), RuntimeError> {
+    for element in array.to_vec() {
+        environment.sandbox.consume_fuel()?;
+        environment.declare_variable(variable, array.element_type().clone(), element, false);
+        let mut next = index + 1;
+        for (_, statement) in body {
+            run_statement(statement, next, environment, on_value)?;
+            next += statement_count(statement);
+        }
+    }
+    Ok(())
 }
@@ -0,0 +1,214 @@
+use crate::{
+    lexer,
+    parser::{self, Expression, Number, Statement, UnaryOperator},
+};
+
+/// Prints `statements` back as source code in the canonical style: one statement per line, one
+/// space around binary operators and `=`, and number suffixes only where they are needed.
+///
+/// Parentheses are kept as written, and added where the optimizer removed them. Type aliases like
+/// `int` are replaced by the name they stand for.
+pub fn format_statements(statements: &[Statement]) -> String {
+    let mut output = String::new();
+    for statement in statements {
+        output += &format_statement(statement);
+        output.push('\n');
+    }
+    output
+}
+
+pub fn format_statement(statement: &Statement) -> String {
+    match statement {
+        Statement::Declaration {
+            declared_type,
+            mutable,
+            name,
+            value,
+        } => {
+            let declared_type = match declared_type {
+                Some(declared_type) => declared_type.to_string(),
+                None if *mutable => String::from("var"),
+                None => String::from("let"),
+            };
+            format!("{declared_type} {name} = {};", format_expression(value))
+        }
+        Statement::Assignment { name, value } => format!("{name} = {};", format_expression(value)),
+        Statement::IndexAssignment {
+            object,
+            index,
+            value,
+        } => format!(
+            "{}[{}] = {};",
+            format_operand(object, MEMBER_PRECEDENCE),
+            format_expression(index),
+            format_expression(value)
+        ),
+        Statement::For {
+            variable,
+            iterable,
+            body,
+        } => {
+            let mut output = format!("{} {{\n", format_loop_header(variable, iterable));
+            for line in body.iter().flat_map(|(_, statement)| {
+                format_statement(statement)
+                    .lines()
+                    .map(str::to_string)
+                    .collect::<Vec<_>>()
+            }) {
+                output += &format!("    {line}\n");
+            }
+            output + "}"
+        }
+        Statement::Expression(expression) => format!("{};", format_expression(expression)),
+    }
+}
+
+/// Formats the first line of a `for` loop, without its `{`.
+pub fn format_loop_header(variable: &str, iterable: &Expression) -> String {
+    format!("for {variable} in {}", format_expression(iterable))
+}
+
+pub fn format_expression(expression: &Expression) -> String {
+    match expression {
+        Expression::UnaryOperation { operator, expr } => {
+            let symbol = match operator {
+                UnaryOperator::Plus => "+",
+                UnaryOperator::Minus => "-",
+            };
+            format!("{symbol}{}", format_operand(expr, UNARY_PRECEDENCE))
+        }
+        Expression::BinaryOperations { first, operations } => {
+            let mut output = format_expression(first);
+            let mut priority = precedence(first);
+            for (operator, operand) in operations {
+                // The optimizer removes parentheses, so the operations before one of a higher
+                // priority can need them again.
+                if priority < operator.priority() {
+                    output = format!("({output})");
+                }
+                output += &format!(
+                    " {} {}",
+                    operator.symbol(),
+                    format_operand(operand, operator.priority() + 1)
+                );
+                priority = operator.priority();
+            }
+            output
+        }
+        Expression::Cast { expr, target } => {
+            format!("{} as {target}", format_operand(expr, CAST_PRECEDENCE))
+        }
+        Expression::ParenthesisExpression(expr) => format!("({})", format_expression(expr)),
+        Expression::Call {
+            function,
+            arguments,
+        } => format!("{function}({})", format_arguments(arguments)),
+        Expression::Field { object, field } => {
+            format!("{}.{field}", format_operand(object, MEMBER_PRECEDENCE))
+        }
+        Expression::MethodCall {
+            object,
+            method,
+            arguments,
+        } => format!(
+            "{}.{method}({})",
+            format_operand(object, MEMBER_PRECEDENCE),
+            format_arguments(arguments)
+        ),
+        Expression::StaticMember { class, member } => format!("{class}::{member}"),
+        Expression::StaticCall {
+            class,
+            function,
+            arguments,
+        } => format!("{class}::{function}({})", format_arguments(arguments)),
+        Expression::Vec { elements, .. } => format!("[{}]", format_arguments(elements)),
+        Expression::Index { object, index } => format!(
+            "{}[{}]",
+            format_operand(object, MEMBER_PRECEDENCE),
+            format_expression(index)
+        ),
+        Expression::Lambda {
+            parameter, body, ..
+        } => format!("{parameter} => {}", format_expression(body)),
+        Expression::Variable(name) => name.clone(),
+        Expression::Number(number) => format_number(number),
+        Expression::Bool(bool) => bool.to_string(),
+        Expression::Char(char) => format!("'{}'", char.escape_debug()),
+        Expression::String(string) => format!("\"{}\"", string.escape_debug()),
+    }
+}
+
+// How tightly expressions bind, above the priorities of the binary operators.
+const CAST_PRECEDENCE: u8 = 3;
+const UNARY_PRECEDENCE: u8 = 4;
+const MEMBER_PRECEDENCE: u8 = 5;
+
+fn precedence(expression: &Expression) -> u8 {
+    match expression {
+        // The body of a lambda goes as far as it can.
+        Expression::Lambda { .. } => 0,
+        // The last operation is applied to the result of the others.
+        Expression::BinaryOperations { operations, .. } => operations
+            .last()
+            .map_or(MEMBER_PRECEDENCE, |(operator, _)| operator.priority()),
+        Expression::Cast { .. } => CAST_PRECEDENCE,
+        Expression::UnaryOperation { .. } => UNARY_PRECEDENCE,
+        // Negative constants are only made by the optimizer, and are written like a negation.
+        Expression::Number(number) if format_number(number).starts_with('-') => UNARY_PRECEDENCE,
+        _ => MEMBER_PRECEDENCE,
+    }
+}
+
+/// Formats the operand of an operation binding with `precedence`, in parentheses if the operand
+/// binds less tightly. Only trees from the optimizer need them, as parsed trees keep their
+/// parentheses.
+fn format_operand(expression: &Expression, precedence: u8) -> String {
+    let formatted = format_expression(expression);
+    if self::precedence(expression) < precedence {
+        return format!("({formatted})");
+    }
+    formatted
+}
+
+fn format_arguments(arguments: &[Expression]) -> String {
+    arguments
+        .iter()
+        .map(format_expression)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Writes `number` without a suffix if the lexer would give it the same type that way, and with
+/// one otherwise.
+fn format_number(number: &Number) -> String {
+    let mut digits = match number {
+        // `Debug` would use an exponent for very large or small floats, which the lexer doesn't
+        // read.
+        Number::Float32(value) => format!("{value}"),
+        Number::Float64(value) => format!("{value}"),
+        number => number.to_string(),
+    };
+    if number.number_type().is_floating_point() && !digits.contains('.') {
+        digits += ".0";
+    }
+
+    // The lexer reads a `-` as an operator, so negative numbers are suffixed like their absolute
+    // value.
+    let unsigned_digits = digits.strip_prefix('-').unwrap_or(&digits).to_string();
+    let unsuffixed_type = lexer::parse_one(unsigned_digits)
+        .ok()
+        .and_then(|tokens| parser::parse_statements(&tokens).0.pop())
+        .and_then(|statement| match statement {
+            Statement::Expression(Expression::Number(literal)) => Some(literal.number_type()),
+            _ => None,
+        });
+    if unsuffixed_type == Some(number.number_type()) {
+        return digits;
+    }
+    let suffix = match number {
+        Number::BigInt(_) => "n",
+        Number::Decimal(_) => "d",
+        number => number.number_type().name(),
+    };
+    digits + suffix
+}
@@ -0,0 +1,421 @@
+use std::{fmt::Display, ops::Range, path::Path};
+
+use crate::{
+    bytecode::{self, Chunk, Instruction},
+    checker::Checker,
+    conversion::{FromTray, IntoTray},
+    diagnostic::Diagnostic,
+    executer::{self, Environment, RuntimeError, Value},
+    module,
+    native::{ClassBuilder, IntoNativeFunction, TrayClass},
+    optimizer,
+    parser::Statement,
+    sandbox::{Capability, InterruptHandle, Limits},
+    vm,
+};
+
+/// Why evaluating tray code from Rust failed.
+#[derive(Debug)]
+pub enum Error {
+    /// The source has lexer, parser or type errors, and nothing was executed.
+    Compile(Vec<Diagnostic>),
+    Runtime(RuntimeError),
+    /// The file given to [`Interpreter::eval_file`] couldn't be read.
+    Io(std::io::Error),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Compile(diagnostics) => {
+                let messages = diagnostics
+                    .iter()
+                    .map(|diagnostic| format!("[{}] {}", diagnostic.code.code, diagnostic.message))
+                    .collect::<Vec<_>>();
+                f.write_str(&messages.join("\n"))
+            }
+            Error::Runtime(err) => write!(f, "[{}] {err}", err.code().code),
+            Error::Io(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<RuntimeError> for Error {
+    fn from(err: RuntimeError) -> Self {
+        Error::Runtime(err)
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+/// How statements are executed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Engine {
+    /// Compiles the statements to bytecode, run by the virtual machine of [`vm`].
+    #[default]
+    Vm,
+    /// Walks the parse tree with [`executer::execute`]. This is slower, but simple enough to be
+    /// the reference the virtual machine is compared with.
+    Tree,
+}
+
+impl Engine {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "vm" => Some(Engine::Vm),
+            "tree" => Some(Engine::Tree),
+            _ => None,
+        }
+    }
+}
+
+/// Runs tray code for a Rust program. Global variables declared by a script, or set with
+/// [`Interpreter::set_global`], stay visible to the code evaluated after it.
+///
+/// Scripts run within the [`Limits`] set with [`Interpreter::set_limits`], and going over one
+/// of them fails with a [`RuntimeError::LimitExceeded`].
+#[derive(Debug, Default)]
+pub struct Interpreter {
+    environment: Environment,
+    engine: Engine,
+}
+
+impl Interpreter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn engine(&self) -> Engine {
+        self.engine
+    }
+
+    pub fn set_engine(&mut self, engine: Engine) {
+        self.engine = engine;
+    }
+
+    pub fn limits(&self) -> &Limits {
+        &self.environment.sandbox.limits
+    }
+
+    /// Replaces the limits of the scripts run after this, and starts a new run.
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.environment.sandbox.limits = limits;
+        self.environment.sandbox.start_run();
+    }
+
+    /// Gives back all of their fuel and time to the statements run with
+    /// [`Interpreter::execute`], which share them until this is called, and forgets earlier
+    /// interrupts.
+    pub fn start_run(&mut self) {
+        self.environment.sandbox.start_run();
+    }
+
+    /// Returns a handle which stops the current run of this interpreter with
+    /// [`RuntimeError::Interrupted`], from another thread or a signal handler. Other
+    /// interpreters aren't affected.
+    ///
+    /// ```
+    /// let mut interpreter = tray::Interpreter::new();
+    /// interpreter.interrupt_handle().interrupt();
+    /// // Nothing was running, so the next run starts afresh.
+    /// assert!(interpreter.eval_str("1 + 1").is_ok());
+    /// ```
+    pub fn interrupt_handle(&self) -> InterruptHandle {
+        self.environment.sandbox.interrupt_handle()
+    }
+
+    /// Returns the globals, functions and classes visible to scripts.
+    pub fn environment(&self) -> &Environment {
+        &self.environment
+    }
+
+    /// Forgets every global variable, keeping the registered functions and classes, the limits
+    /// and the settings.
+    pub fn clear_globals(&mut self) {
+        self.environment.clear_variables();
+    }
+
+    pub fn promote_overflow(&self) -> bool {
+        self.environment.promote_overflow
+    }
+
+    /// When enabled, integer operations that overflow their fixed-width type produce a `bigint`
+    /// instead of an [`RuntimeError::ArithmeticError`].
+    pub fn set_promote_overflow(&mut self, enabled: bool) {
+        self.environment.promote_overflow = enabled;
+    }
+
+    /// Lexes, parses, type checks `source` against the current globals and optimizes it,
+    /// without executing it. Returns the errors of every step, in the order of the source: the
+    /// statements that could be parsed are still checked after a syntax error.
+    pub fn compile(&self, source: &str) -> Result<Vec<Statement>, Vec<Diagnostic>> {
+        self.compile_with_lines(source)
+            .map(|(statements, _)| statements)
+    }
+
+    /// Compiles `source` to bytecode like [`Interpreter::compile`], along with the line of each
+    /// statement. The chunk can be run with [`Interpreter::execute_chunk`] or saved with
+    /// [`module::encode`].
+    pub fn compile_chunk(&self, source: &str) -> Result<Chunk, Vec<Diagnostic>> {
+        let (statements, lines) = self.compile_with_lines(source)?;
+        let mut chunk = bytecode::compile(&statements);
+        chunk.lines = lines;
+        Ok(chunk)
+    }
+
+    /// Compiles `source` like [`Interpreter::compile`], along with the 1-based line each
+    /// statement starts on.
+    fn compile_with_lines(
+        &self,
+        source: &str,
+    ) -> Result<(Vec<Statement>, Vec<u32>), Vec<Diagnostic>> {
+        let ((lexed, statements), mut diagnostics) =
+            crate::parse_recovering(source, self.limits().max_nesting);
+        let lines = crate::statement_lines(source, &lexed, &statements);
+        match self.check(statements, &lexed.spans) {
+            Ok(statements) if diagnostics.is_empty() => Ok((statements, lines)),
+            result => {
+                diagnostics.extend(result.err().unwrap_or_default());
+                diagnostics.sort_by_key(|diagnostic| {
+                    diagnostic
+                        .span
+                        .as_ref()
+                        .map_or(usize::MAX, |span| span.start)
+                });
+                Err(diagnostics)
+            }
+        }
+    }
+
+    /// Type checks `statements`, along with the index of their first token, against the current
+    /// globals, then optimizes them with [`optimizer::optimize_statement`], which reports the
+    /// errors of constant operations. `spans` are the byte ranges of the tokens.
+    fn check(
+        &self,
+        statements: Vec<(usize, Statement)>,
+        spans: &[Range<usize>],
+    ) -> Result<Vec<Statement>, Vec<Diagnostic>> {
+        let mut environment = self.environment.clone();
+        let mut checker = Checker::new();
+        let mut optimized = vec![];
+        let mut diagnostics = vec![];
+        for (start, mut statement) in statements {
+            // The statement is optimized with the types the variables have before it, which only
+            // change when it declares a variable again.
+            let redeclared = match &statement {
+                Statement::Declaration { name, .. } => environment.get_type(name).is_some(),
+                _ => false,
+            };
+            let before = redeclared.then(|| environment.clone());
+            if let Err(errors) = checker.check_statement(&mut statement, start, &mut environment) {
+                diagnostics.extend(errors.iter().map(|error| error.to_diagnostic(spans)));
+                continue;
+            }
+            let result =
+                optimizer::optimize_statement(statement, before.as_ref().unwrap_or(&environment));
+            match result {
+                Ok(statement) => optimized.push((start, statement)),
+                // The checker reports the operations failing on constants where they are, so
+                // this only points at the statement.
+                Err(err) => {
+                    let diagnostic = err.to_diagnostic();
+                    diagnostics.push(match spans.get(start) {
+                        Some(span) => diagnostic.with_span(span.clone()),
+                        None => diagnostic,
+                    });
+                }
+            }
+        }
+        if let Err(errors) = checker.finish(&mut optimized) {
+            diagnostics.extend(errors.iter().map(|error| error.to_diagnostic(spans)));
+        }
+        if !diagnostics.is_empty() {
+            return Err(diagnostics);
+        }
+        Ok(optimized
+            .into_iter()
+            .map(|(_, statement)| statement)
+            .collect())
+    }
+
+    /// Executes a statement returned by [`Interpreter::compile`], returning its value if it is
+    /// an expression. The fuel and time limits are counted from the last
+    /// [`Interpreter::start_run`].
+    pub fn execute(&mut self, statement: &Statement) -> Result<Option<Value>, RuntimeError> {
+        let mut result = None;
+        self.execute_all(std::slice::from_ref(statement), |value| {
+            result = Some(value)
+        })?;
+        Ok(result)
+    }
+
+    /// Executes statements returned by [`Interpreter::compile`] with the engine of the
+    /// interpreter, calling `on_value` with the value of each expression statement.
+    pub fn execute_all(
+        &mut self,
+        statements: &[Statement],
+        mut on_value: impl FnMut(Value),
+    ) -> Result<(), RuntimeError> {
+        match self.engine {
+            Engine::Vm => vm::run(
+                &bytecode::compile(statements),
+                &mut self.environment,
+                on_value,
+            ),
+            Engine::Tree => {
+                let mut index = 0;
+                for statement in statements {
+                    let result = executer::execute_statement(
+                        statement,
+                        &mut self.environment,
+                        &mut on_value,
+                    );
+                    if result.is_err() {
+                        // The statement is numbered from 0 by the tree-walker.
+                        let failed = &mut self.environment.failed_statement;
+                        *failed = failed.map(|failed| index + failed);
+                        return result;
+                    }
+                    index += executer::statement_count(statement);
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Returns the statement the last run of [`Interpreter::execute_all`] or
+    /// [`Interpreter::execute_chunk`] failed in, as an index in the statements of its
+    /// [`Chunk`], where the statements of the body of a loop follow the loop. Its source is
+    /// found with [`statement_spans`](crate::statement_spans).
+    pub fn failed_statement(&self) -> Option<usize> {
+        self.environment.failed_statement
+    }
+
+    /// Runs a chunk returned by [`Interpreter::compile_chunk`] or [`module::decode`] with the
+    /// virtual machine, whatever the engine of the interpreter is. Chunks aren't type checked
+    /// again, so calls to functions and classes which aren't registered fail when they run.
+    pub fn execute_chunk(
+        &mut self,
+        chunk: &Chunk,
+        on_value: impl FnMut(Value),
+    ) -> Result<(), RuntimeError> {
+        vm::run(chunk, &mut self.environment, on_value)
+    }
+
+    /// Compiles and executes `source`, returning the value of its last statement if it is an
+    /// expression. Each call is a new run, with all of the fuel and time of the limits.
+    pub fn eval_str(&mut self, source: &str) -> Result<Option<Value>, Error> {
+        let statements = self.compile(source).map_err(Error::Compile)?;
+        self.start_run();
+        let mut result = None;
+        self.execute_all(&statements, |value| result = Some(value))?;
+        // Only the last statement gives the result, and only if it is an expression.
+        match statements.last() {
+            Some(Statement::Expression(_)) => Ok(result),
+            _ => Ok(None),
+        }
+    }
+
+    /// Reads the file at `path` and evaluates it like [`Interpreter::eval_str`].
+    ///
+    /// With the virtual machine, the module compiled from the file by `tray build` is run
+    /// instead when it is newer than the file, which skips lexing, parsing and type checking it.
+    pub fn eval_file(&mut self, path: impl AsRef<Path>) -> Result<Option<Value>, Error> {
+        let path = path.as_ref();
+        if self.engine == Engine::Vm {
+            if let Some(chunk) = module::load_if_newer(path) {
+                self.start_run();
+                let mut result = None;
+                self.execute_chunk(&chunk, |value| result = Some(value))?;
+                // Like with `eval_str`, only an expression as the last statement gives a result.
+                return match chunk.code.last() {
+                    Some(Instruction::Yield) => Ok(result),
+                    _ => Ok(None),
+                };
+            }
+        }
+        let source = std::fs::read_to_string(path)?;
+        self.eval_str(&source)
+    }
+
+    /// Calls the function `name` with `args`, which are converted to the types of its
+    /// parameters.
+    pub fn call_function(&mut self, name: &str, args: Vec<Value>) -> Result<Value, Error> {
+        let function = self
+            .environment
+            .get_function(name)
+            .ok_or_else(|| executer::undefined_function(name))?;
+        self.environment
+            .sandbox
+            .check_capability(function.capability, name)?;
+        if args.len() != function.parameters.len() {
+            return Err(function.wrong_argument_count(args.len()).into());
+        }
+        Ok(function.call(args)?)
+    }
+
+    /// Makes the Rust closure `function` callable from scripts as `name(...)`, replacing any
+    /// function with the same name.
+    ///
+    /// The closure takes up to 4 arguments of types with a tray equivalent, like `i64` or
+    /// `String`, and calls to it are type checked like declarations. It returns such a value, or
+    /// a `Result` whose error becomes a [`RuntimeError::NativeError`].
+    ///
+    /// ```
+    /// let mut interpreter = tray::Interpreter::new();
+    /// interpreter.register_fn("add", |a: i64, b: i64| a + b);
+    /// interpreter.eval_str("i64 sum = add(1, 2);").unwrap();
+    /// assert_eq!(interpreter.get_global::<i64>("sum").unwrap(), 3);
+    /// ```
+    pub fn register_fn<Arguments>(
+        &mut self,
+        name: &str,
+        function: impl IntoNativeFunction<Arguments>,
+    ) {
+        self.environment
+            .register_function(function.into_native_function(name));
+    }
+
+    /// Registers `function` like [`Interpreter::register_fn`], for a function that scripts can
+    /// only call when the limits allow `capability`. Functions giving access to the file system,
+    /// processes or environment variables should be registered this way, so that they can be
+    /// disabled for untrusted scripts with [`Capabilities::none`](crate::Capabilities::none).
+    pub fn register_fn_requiring<Arguments>(
+        &mut self,
+        capability: Capability,
+        name: &str,
+        function: impl IntoNativeFunction<Arguments>,
+    ) {
+        let mut function = function.into_native_function(name);
+        function.capability = Some(capability);
+        self.environment.register_function(function);
+    }
+
+    /// Makes `T` a class of scripts, with the members added by [`TrayClass::register`].
+    pub fn register_type<T: TrayClass>(&mut self) {
+        self.environment.register_class(ClassBuilder::<T>::build());
+    }
+
+    /// Returns the value of the global variable `name` converted to `T`.
+    pub fn get_global<T: FromTray>(&self, name: &str) -> Result<T, Error> {
+        let value = self
+            .environment
+            .get(name)
+            .cloned()
+            .ok_or_else(|| executer::undefined_variable(name))?;
+        Ok(T::from_tray(value)?)
+    }
+
+    /// Declares the global variable `name` with the type of `value`, replacing any variable with
+    /// the same name.
+    pub fn set_global(&mut self, name: &str, value: impl IntoTray) {
+        let value = value.into_tray();
+        self.environment.declare(name, value.value_type(), value);
+    }
+}
@@ -1,28 +1,90 @@
 use std::{
     fmt::Display,
     iter::Peekable,
+    ops::Range,
     str::{CharIndices, FromStr},
 };
 
+use crate::{
+    bigint::BigInt,
+    decimal::Decimal,
+    diagnostic::{Diagnostic, Suggestion},
+    error_codes::{self, ErrorCode},
+};
+
 #[derive(Debug, Clone)]
 pub enum Token {
     Plus,
     Minus,
     Multiply,
     Divide,
+    I8(i8),
+    I16(i16),
     I32(i32),
     I64(i64),
     I128(i128),
+    U8(u8),
+    U16(u16),
+    U32(u32),
+    U64(u64),
+    U128(u128),
     F32(f32),
     F64(f64),
+    BigInt(BigInt),
+    Decimal(Decimal),
     String(String),
     Char(char),
     LParenthesis,
     RParenthesis,
+    LBracket,
+    RBracket,
+    LBrace,
+    RBrace,
+    Equal,
+    FatArrow,
+    Less,
+    Greater,
+    Semicolon,
+    Comma,
+    Dot,
+    DoubleColon,
+    As,
+    Let,
+    Var,
+    For,
+    In,
+    True,
+    False,
+    Identifier(String),
+}
+
+/// Every word that [`keyword`] turns into a token instead of an identifier.
+pub const KEYWORDS: &[&str] = &["as", "false", "for", "in", "let", "true", "var"];
+
+/// Returns the keyword token spelled by `word`, if `word` is reserved.
+pub fn keyword(word: &str) -> Option<Token> {
+    match word {
+        "as" => Some(Token::As),
+        "let" => Some(Token::Let),
+        "var" => Some(Token::Var),
+        "for" => Some(Token::For),
+        "in" => Some(Token::In),
+        "true" => Some(Token::True),
+        "false" => Some(Token::False),
+        _ => None,
+    }
 }
 
 pub type TokenList = Vec<Token>;
 
+/// The tokens of a source along with the byte range each one was read from, and the errors found
+/// while lexing it.
+pub struct Lexed {
+    pub tokens: TokenList,
+    pub spans: Vec<Range<usize>>,
+    pub errors: Vec<LexerError>,
+}
+
 impl Display for Token {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -30,15 +92,44 @@ impl Display for Token {
             Token::Minus => f.write_str("Minus"),
             Token::Multiply => f.write_str("Multiply"),
             Token::Divide => f.write_str("Divide"),
+            Token::I8(i) => write!(f, "{i}i8"),
+            Token::I16(i) => write!(f, "{i}i16"),
             Token::I32(i) => write!(f, "{i}i32"),
             Token::I64(i) => write!(f, "{i}i64"),
             Token::I128(i) => write!(f, "{i}i128"),
+            Token::U8(i) => write!(f, "{i}u8"),
+            Token::U16(i) => write!(f, "{i}u16"),
+            Token::U32(i) => write!(f, "{i}u32"),
+            Token::U64(i) => write!(f, "{i}u64"),
+            Token::U128(i) => write!(f, "{i}u128"),
             Token::F32(float) => write!(f, "{float}f32"),
             Token::F64(float) => write!(f, "{float}f64"),
+            Token::BigInt(i) => write!(f, "{i}n"),
+            Token::Decimal(decimal) => write!(f, "{decimal}d"),
             Token::String(string) => write!(f, "\"{string}\""),
             Token::Char(char) => write!(f, "\'{char}\'"),
             Token::LParenthesis => write!(f, "Left parenthesis"),
             Token::RParenthesis => write!(f, "Right parenthesis"),
+            Token::LBracket => f.write_str("Left bracket"),
+            Token::RBracket => f.write_str("Right bracket"),
+            Token::LBrace => f.write_str("Left brace"),
+            Token::RBrace => f.write_str("Right brace"),
+            Token::Equal => f.write_str("Equal"),
+            Token::FatArrow => f.write_str("Fat arrow"),
+            Token::Less => f.write_str("Less"),
+            Token::Greater => f.write_str("Greater"),
+            Token::Semicolon => f.write_str("Semicolon"),
+            Token::Comma => f.write_str("Comma"),
+            Token::Dot => f.write_str("Dot"),
+            Token::DoubleColon => f.write_str("Double colon"),
+            Token::As => f.write_str("As"),
+            Token::Let => f.write_str("Let"),
+            Token::Var => f.write_str("Var"),
+            Token::For => f.write_str("For"),
+            Token::In => f.write_str("In"),
+            Token::True => f.write_str("True"),
+            Token::False => f.write_str("False"),
+            Token::Identifier(name) => write!(f, "Identifier `{name}`"),
         }
     }
 }
@@ -47,31 +138,73 @@ impl Display for Token {
 pub enum LexerError {
     IllegalCharacter {
         position: usize,
+        code: &'static ErrorCode,
         message: String,
     },
     ParsingError {
         start_position: usize,
         end_position: usize,
+        code: &'static ErrorCode,
         message: String,
+        suggestion: Option<Suggestion>,
+    },
+    /// A string literal starting at `start_position` that is still open at the end of the file,
+    /// `end_position`.
+    UnterminatedString {
+        start_position: usize,
+        end_position: usize,
+        suggestion: Option<Suggestion>,
     },
 }
 
 impl LexerError {
-    pub fn arrow_error(&self, line: String) {
-        let (begin_pos, end_pos, message) = match self {
-            LexerError::IllegalCharacter { position, message } => {
-                (*position, *position + 1, message)
-            }
+    pub fn to_diagnostic(&self) -> Diagnostic {
+        match self {
+            LexerError::IllegalCharacter {
+                position,
+                code,
+                message,
+            } => Diagnostic::error(code, message.clone()).with_span(*position..*position + 1),
             LexerError::ParsingError {
                 start_position,
                 end_position,
+                code,
                 message,
-            } => (*start_position, *end_position, message),
-        };
-        eprintln!(
-            "{message}\n{line}\n{}",
-            " ".repeat(begin_pos) + &"^".repeat(end_pos - begin_pos)
-        );
+                suggestion,
+            } => {
+                let diagnostic = Diagnostic::error(code, message.clone())
+                    .with_span(*start_position..*end_position);
+                match suggestion {
+                    Some(suggestion) => diagnostic.with_suggestion(suggestion.clone()),
+                    None => diagnostic,
+                }
+            }
+            LexerError::UnterminatedString {
+                start_position,
+                end_position,
+                suggestion,
+            } => {
+                let diagnostic = Diagnostic::error(
+                    &error_codes::UNTERMINATED_STRING,
+                    String::from("Syntax Error: Found end of file while trying to parse string literal. Make sure to close the quotes or remove the trailing `\"`"),
+                )
+                .with_span(*start_position..*start_position + 1)
+                .with_label("string started here")
+                .with_secondary_label(*end_position..*end_position, "file ended here");
+                match suggestion {
+                    Some(suggestion) => diagnostic.with_suggestion(suggestion.clone()),
+                    None => diagnostic,
+                }
+            }
+        }
+    }
+
+    /// Drops the suggested fix, for errors where it doesn't apply in the context.
+    fn without_suggestion(mut self) -> Self {
+        if let LexerError::ParsingError { suggestion, .. } = &mut self {
+            *suggestion = None;
+        }
+        self
     }
 
     // pub fn offset_by(&self, size: usize) -> Self {
@@ -90,115 +223,205 @@ impl LexerError {
     // }
 }
 
-pub fn parse_number(iterator: &mut Peekable<CharIndices>) -> Result<Token, LexerError> {
+fn parse_literal<T: FromStr>(
+    string: &str,
+    type_name: &str,
+    begin_index: usize,
+    end_index: usize,
+) -> Result<T, LexerError> {
+    T::from_str(string).map_err(|_| LexerError::ParsingError {
+        start_position: begin_index,
+        end_position: end_index + 1,
+        code: &error_codes::INVALID_NUMBER,
+        message: format!("Parsing Error: Couldn't parse number to a {type_name}"),
+        suggestion: None,
+    })
+}
+
+/// Parses a signed integer literal. After a unary minus it can be one past the maximum of its
+/// type, like `128` in `-128i8`, and is then read as the negated value.
+fn parse_signed_literal<T: FromStr>(
+    string: &str,
+    type_name: &str,
+    negative: bool,
+    begin_index: usize,
+    end_index: usize,
+) -> Result<T, LexerError> {
+    match T::from_str(string) {
+        Ok(value) => Ok(value),
+        Err(_) if negative => {
+            parse_literal(&format!("-{string}"), type_name, begin_index, end_index)
+        }
+        Err(_) => parse_literal(string, type_name, begin_index, end_index),
+    }
+}
+
+/// Returns the type to cast to in place of an integer number suffix.
+fn cast_suffix_type(suffix: &str) -> Option<&str> {
+    match suffix {
+        "i8" | "i16" | "i32" | "i64" | "i128" | "u8" | "u16" | "u32" | "u64" | "u128" => {
+            Some(suffix)
+        }
+        "n" => Some("bigint"),
+        _ => None,
+    }
+}
+
+fn parse_suffixed_number(
+    string: &str,
+    suffix: &str,
+    has_dot: bool,
+    negative: bool,
+    begin_index: usize,
+    end_index: usize,
+) -> Result<Token, LexerError> {
+    let (b, e) = (begin_index, end_index);
+    if has_dot && !(suffix.starts_with('f') || suffix == "d") {
+        return Err(LexerError::ParsingError {
+            start_position: begin_index,
+            end_position: end_index + 1,
+            code: &error_codes::FLOAT_WITH_INTEGER_SUFFIX,
+            message: format!("Syntax Error: A floating point number cannot have the integer suffix `{suffix}`. Use an explicit cast instead."),
+            suggestion: cast_suffix_type(suffix).map(|type_name| {
+                Suggestion::replace(
+                    end_index + 1 - suffix.len()..end_index + 1,
+                    &format!(" as {type_name}"),
+                    &format!("cast the number instead: `as {type_name}`"),
+                )
+            }),
+        });
+    }
+    match suffix {
+        "i8" => Ok(Token::I8(parse_signed_literal(string, "Int8", negative, b, e)?)),
+        "i16" => Ok(Token::I16(parse_signed_literal(string, "Int16", negative, b, e)?)),
+        "i32" => Ok(Token::I32(parse_signed_literal(string, "Int32", negative, b, e)?)),
+        "i64" => Ok(Token::I64(parse_signed_literal(string, "Int64", negative, b, e)?)),
+        "i128" => Ok(Token::I128(parse_signed_literal(string, "Int128", negative, b, e)?)),
+        "u8" => Ok(Token::U8(parse_literal(string, "UInt8", b, e)?)),
+        "u16" => Ok(Token::U16(parse_literal(string, "UInt16", b, e)?)),
+        "u32" => Ok(Token::U32(parse_literal(string, "UInt32", b, e)?)),
+        "u64" => Ok(Token::U64(parse_literal(string, "UInt64", b, e)?)),
+        "u128" => Ok(Token::U128(parse_literal(string, "UInt128", b, e)?)),
+        "f32" => Ok(Token::F32(parse_literal(string, "Float32", b, e)?)),
+        "f64" => Ok(Token::F64(parse_literal(string, "Float64", b, e)?)),
+        "n" => Ok(Token::BigInt(parse_literal(string, "BigInt", b, e)?)),
+        "d" => Ok(Token::Decimal(parse_literal(string, "Decimal", b, e)?)),
+        _ => Err(LexerError::ParsingError {
+            start_position: begin_index,
+            end_position: end_index + 1,
+            code: &error_codes::UNKNOWN_NUMBER_SUFFIX,
+            message: format!("Syntax Error: Unknown number suffix `{suffix}`. Valid suffixes are `i8`, `i16`, `i32`, `i64`, `i128`, `u8`, `u16`, `u32`, `u64`, `u128`, `f32`, `f64`, `n` (BigInt) and `d` (Decimal)."),
+            suggestion: None,
+        }),
+    }
+}
+
+/// Lexes a number literal. `negative` tells whether it follows a unary minus, which lets a
+/// signed literal hold the minimum of its type.
+pub fn parse_number(
+    iterator: &mut Peekable<CharIndices>,
+    negative: bool,
+) -> Result<Token, LexerError> {
     let mut string = String::new();
     let mut has_dot = false;
     let begin_index = iterator
         .peek()
         .expect("Expected iterator to still be valid.")
         .0;
-    let mut end_index = begin_index.clone();
-    loop {
-        if let Some((index, char)) = iterator.peek() {
-            match *char {
-                c if c == '_' && string.ends_with('.') => {
-                    return Err(LexerError::ParsingError {
-						start_position: begin_index,
-                        end_position: index + 1,
-						message: String::from("Syntax Error: Cannot add a `_` in a number right after a floating point `.`"),
-                    })
-                }
-                c if c == '.' && has_dot => {
-                    return Err(LexerError::ParsingError {
-                        start_position: begin_index,
-                        end_position: index + 1,
-                        message: String::from("Syntax Error: A floating point number cannot have multiple `.`"),
-                    })
-                }
-                '.' => {
-                    has_dot = true;
-                    string.push('.');
-                }
-                '_' => {}
-                c @ '0'..='9' => string.push(c),
-                _ if char.is_whitespace() => {
-                    break;
-                }
-                _ => break,
+    let mut end_index = begin_index;
+    while let Some((index, char)) = iterator.peek() {
+        match *char {
+            c if c == '_' && string.ends_with('.') => {
+                return Err(LexerError::ParsingError {
+                    start_position: begin_index,
+                    end_position: index + 1,
+                    code: &error_codes::UNDERSCORE_AFTER_DOT,
+                    message: String::from("Syntax Error: Cannot add a `_` in a number right after a floating point `.`"),
+                    suggestion: None,
+                })
             }
-            end_index = *index;
-            iterator.next();
-        } else {
+            c if c == '.' && has_dot => {
+                return Err(LexerError::ParsingError {
+                    start_position: begin_index,
+                    end_position: index + 1,
+                    code: &error_codes::MULTIPLE_DOTS,
+                    message: String::from("Syntax Error: A floating point number cannot have multiple `.`"),
+                    suggestion: None,
+                })
+            }
+            '.' => {
+                has_dot = true;
+                string.push('.');
+            }
+            '_' => {}
+            c @ '0'..='9' => string.push(c),
+            _ => break,
+        }
+        end_index = *index;
+        iterator.next();
+    }
+
+    let mut suffix = String::new();
+    while let Some((index, char)) = iterator.peek() {
+        if !char.is_ascii_alphanumeric() {
             break;
         }
+        suffix.push(*char);
+        end_index = *index;
+        iterator.next();
+    }
+
+    if !suffix.is_empty() {
+        return parse_suffixed_number(&string, &suffix, has_dot, negative, begin_index, end_index);
     }
 
+    let (b, e) = (begin_index, end_index);
     match string.len() {
         0 => Err(LexerError::ParsingError {
             start_position: begin_index,
             end_position: begin_index + 1,
+            code: &error_codes::INVALID_NUMBER,
             message: String::from("Unreachable."),
+            suggestion: None,
         }),
         1..=9 => {
             if has_dot {
-                return Ok(Token::F32(f32::from_str(&string).map_err(|_| {
-                    LexerError::ParsingError {
-                        start_position: begin_index,
-                        end_position: end_index + 1,
-                        message: String::from("Parsing Error: Couldn't parse number to a Float32"),
-                    }
-                })?));
+                Ok(Token::F32(parse_literal(&string, "Float32", b, e)?))
             } else {
-                Ok(Token::I32(i32::from_str(&string).map_err(|_| {
-                    LexerError::ParsingError {
-                        start_position: begin_index,
-                        end_position: end_index + 1,
-                        message: String::from("Parsing Error: Couldn't parse number to a Int32"),
-                    }
-                })?))
+                Ok(Token::I32(parse_literal(&string, "Int32", b, e)?))
             }
         }
         10..=18 => {
             if has_dot {
-                return Ok(Token::F64(f64::from_str(&string).map_err(|_| {
-                    LexerError::ParsingError {
-                        start_position: begin_index,
-                        end_position: end_index + 1,
-                        message: String::from("Parsing Error: Couldn't parse number to a Float64"),
-                    }
-                })?));
+                Ok(Token::F64(parse_literal(&string, "Float64", b, e)?))
             } else {
-                Ok(Token::I64(i64::from_str(&string).map_err(|_| {
-                    LexerError::ParsingError {
-                        start_position: begin_index,
-                        end_position: end_index + 1,
-                        message: String::from("Parsing Error: Couldn't parse number to a Int64"),
-                    }
-                })?))
+                Ok(Token::I64(parse_literal(&string, "Int64", b, e)?))
             }
         }
         _ => {
             if has_dot {
-                return Ok(Token::F64(f64::from_str(&string).map_err(|_| {
-                    LexerError::ParsingError {
-                        start_position: begin_index,
-                        end_position: end_index + 1,
-                        message: String::from("Parsing Error: Couldn't parse number to a Float64"),
-                    }
-                })?));
+                Ok(Token::F64(parse_literal(&string, "Float64", b, e)?))
+            } else if let Ok(value) = i128::from_str(&string) {
+                Ok(Token::I128(value))
             } else {
-                Ok(Token::I128(i128::from_str(&string).map_err(|_| {
-                    LexerError::ParsingError {
-                        start_position: begin_index,
-                        end_position: end_index + 1,
-                        message: String::from("Parsing Error: Couldn't parse number to a Int128"),
-                    }
-                })?))
+                Ok(Token::BigInt(parse_literal(&string, "BigInt", b, e)?))
             }
         }
     }
 }
 
+fn parse_identifier(iterator: &mut Peekable<CharIndices>) -> Token {
+    let mut word = String::new();
+    while let Some((_, char)) = iterator.peek() {
+        if !(char.is_alphanumeric() || *char == '_') {
+            break;
+        }
+        word.push(*char);
+        iterator.next();
+    }
+    keyword(&word).unwrap_or(Token::Identifier(word))
+}
+
 fn parse_escape(iterator: &mut Peekable<CharIndices>) -> Result<char, LexerError> {
     let begin_index = iterator.next().expect("Expected string to stay valid").0;
 
@@ -214,7 +437,9 @@ fn parse_escape(iterator: &mut Peekable<CharIndices>) -> Result<char, LexerError
                             return Err(LexerError::ParsingError {
                                 start_position: begin_index,
                                 end_position: code_2.0 + 1,
+                                code: &error_codes::INVALID_HEX_ESCAPE,
                                 message: String::from("Syntax Error: Expected two digits hexadecimal number lower between 0x00 and 0x7F."),
+                                suggestion: None,
                             });
                         } else {
                             return Ok(code as char);
@@ -223,20 +448,26 @@ fn parse_escape(iterator: &mut Peekable<CharIndices>) -> Result<char, LexerError
                         return Err(LexerError::ParsingError {
                             start_position: begin_index,
                             end_position: code_2.0 + 1,
-							message: String::from("Syntax Error: Couldn't parse escaped hexadecimal number. Make sure the digits are valid hexadecimal characters (0-9, A-F).")
+							code: &error_codes::INVALID_HEX_ESCAPE,
+							message: String::from("Syntax Error: Couldn't parse escaped hexadecimal number. Make sure the digits are valid hexadecimal characters (0-9, A-F)."),
+							suggestion: None,
                         });
                     }
                 } else if let Some(code_1) = code_1 {
                     return Err(LexerError::ParsingError {
                         start_position: begin_index,
                         end_position: code_1.0 + 1,
+                        code: &error_codes::INVALID_HEX_ESCAPE,
                         message: String::from("Syntax Error: Expected two hexadecimal digits to be escaped, could only find one. Make sure to use two hexadecimal digits like `\\x7F`"),
+                        suggestion: None,
                     });
                 } else {
                     return Err(LexerError::ParsingError {
                         start_position: begin_index,
                         end_position: begin_index + 1,
+                        code: &error_codes::INVALID_HEX_ESCAPE,
                         message: String::from("Syntax Error: Tried to escape an hexadecimal number 7bit number with \\x but couldn't find the two required hexadecimal digits."),
+                        suggestion: None,
                     });
                 }
             }
@@ -256,7 +487,7 @@ fn parse_escape(iterator: &mut Peekable<CharIndices>) -> Result<char, LexerError
                             '}' => false,
                             _ => false,
                         })
-                        .map(|(_, v)| v)
+                        .map(|(_, value)| value)
                         .collect::<String>();
                     for _ in 0..count {
                         iterator.next().expect("Cannot fail");
@@ -266,13 +497,17 @@ fn parse_escape(iterator: &mut Peekable<CharIndices>) -> Result<char, LexerError
                         return Err(LexerError::ParsingError {
                             start_position: begin_index,
                             end_position: lcurly.0 + 2,
+                            code: &error_codes::INVALID_UNICODE_ESCAPE,
                             message: String::from("Syntax Error: Tried to escape a 24bit unicode character but no hexadecimal digits were found. Make sure to specify a hexadecimal number in range [0, 10FFFF]"),
+                            suggestion: None,
                         });
                     } else if count == 7 {
                         return Err(LexerError::ParsingError {
                             start_position: begin_index,
                             end_position: lcurly.0 + 7,
-							message: String::from("Syntax Error: Tried to escape a 24bit unicode character but found 7 or more characters. A 24bit unicode character can at most have 6 hexadecimal digits and has to be in range [0, 10FFFF].")
+							code: &error_codes::INVALID_UNICODE_ESCAPE,
+							message: String::from("Syntax Error: Tried to escape a 24bit unicode character but found 7 or more characters. A 24bit unicode character can at most have 6 hexadecimal digits and has to be in range [0, 10FFFF]."),
+							suggestion: None,
                         });
                     }
 
@@ -285,21 +520,27 @@ fn parse_escape(iterator: &mut Peekable<CharIndices>) -> Result<char, LexerError
                                 return Err(LexerError::ParsingError {
                                     start_position: begin_index,
                                     end_position: begin_index + count + 4,
+                                    code: &error_codes::INVALID_UNICODE_ESCAPE,
                                     message: String::from("Parsing error: Couldn't convert escaped unicode character back into a single character, make sure the number represented is between in range [0,10FFFF]"),
+                                    suggestion: None,
                                 });
                             }
                         } else {
                             return Err(LexerError::ParsingError {
                                 start_position: begin_index,
                                 end_position: begin_index + count + 4,
+                                code: &error_codes::INVALID_UNICODE_ESCAPE,
                                 message: String::from("Parsing error: Couldn't convert escaped 24bit unicode character into a number. Make sure to use valid hexadecimal digits from 0-9 and A-F"),
+                                suggestion: None,
                             });
                         }
                     } else {
                         return Err(LexerError::ParsingError {
                             start_position: begin_index,
                             end_position: begin_index + count + 4,
-                            message: String::from("Syntax error: Escaped unicode characters must follow the format: `\\u{07FFFF}` with from 1 to 6 digits. The range of numbers is [0, 10FFFF].")
+                            code: &error_codes::INVALID_UNICODE_ESCAPE,
+                            message: String::from("Syntax error: Escaped unicode characters must follow the format: `\\u{07FFFF}` with from 1 to 6 digits. The range of numbers is [0, 10FFFF]."),
+                            suggestion: None,
 							
                         });
                     }
@@ -307,7 +548,9 @@ fn parse_escape(iterator: &mut Peekable<CharIndices>) -> Result<char, LexerError
                     return Err(LexerError::ParsingError {
                         start_position: begin_index,
                         end_position: begin_index + 2,
-						message: String::from("Syntax error: Escaped unicode characters must follow the format: `\\u{07FFFF}` with from 1 to 6 digits. The range of numbers is [0, 10FFFF].")
+						code: &error_codes::INVALID_UNICODE_ESCAPE,
+						message: String::from("Syntax error: Escaped unicode characters must follow the format: `\\u{07FFFF}` with from 1 to 6 digits. The range of numbers is [0, 10FFFF]."),
+						suggestion: None,
                     });
                 }
             }
@@ -344,7 +587,13 @@ fn parse_escape(iterator: &mut Peekable<CharIndices>) -> Result<char, LexerError
                 return Err(LexerError::ParsingError {
                     start_position: begin_index,
                     end_position: *index + 1,
+                    code: &error_codes::INVALID_ESCAPE,
                     message: format!("Syntax error: Character {c} preceeded by a `\\` cannot be escaped, make sure to escape the backslash like `\\\\{c}` if you meant to add a backslash to the string."),
+                    suggestion: Some(Suggestion::insert(
+                        begin_index,
+                        "\\",
+                        &format!("escape the backslash: `\\\\{c}`"),
+                    )),
                 });
             }
         }
@@ -352,7 +601,9 @@ fn parse_escape(iterator: &mut Peekable<CharIndices>) -> Result<char, LexerError
         return Err(LexerError::ParsingError {
             start_position: begin_index,
             end_position: begin_index + 1,
-            message: String::from("Syntax error: "),
+            code: &error_codes::INVALID_ESCAPE,
+            message: String::from("Syntax Error: Expected a character to escape after `\\`, found the end of the file."),
+            suggestion: None,
         });
     }
 }
@@ -367,11 +618,14 @@ pub fn parse_char(iterator: &mut Peekable<CharIndices>) -> Result<Token, LexerEr
                 return Err(LexerError::ParsingError {
                     start_position: begin_index,
                     end_position: index + 1,
+                    code: &error_codes::EMPTY_CHAR,
                     message: String::from("Character literal cannot be empty."),
+                    suggestion: None,
                 })
             }
             '\\' => {
-                content = parse_escape(iterator).map_err(|err| err)?;
+                // Escaping the backslash would make the literal two characters long.
+                content = parse_escape(iterator).map_err(LexerError::without_suggestion)?;
             }
             c => {
                 content = c;
@@ -385,23 +639,39 @@ pub fn parse_char(iterator: &mut Peekable<CharIndices>) -> Result<Token, LexerEr
             return Err(LexerError::ParsingError {
                 start_position: begin_index,
                 end_position: begin_index + 2,
+                code: &error_codes::UNTERMINATED_CHAR,
                 message: String::from("Syntax Error: Expected `'` to end the character literal."),
+                suggestion: None,
             });
         }
     } else {
         return Err(LexerError::ParsingError {
             start_position: begin_index,
             end_position: begin_index + 1,
-			message: String::from("Syntax Error: Found end of file while trying to parse a character literal. Try removing the trailing `'`.")
+			code: &error_codes::UNTERMINATED_CHAR,
+			message: String::from("Syntax Error: Found end of file while trying to parse a character literal. Try removing the trailing `'`."),
+			suggestion: Some(Suggestion::replace(
+				begin_index..begin_index + 1,
+				"",
+				"remove the trailing `'`",
+			)),
         });
     }
 }
 
-pub fn parse_string(iterator: &mut Peekable<CharIndices>) -> Result<Token, LexerError> {
+/// Lexes a string literal. `source_length` is the length in bytes of the whole source, where an
+/// unterminated string ends.
+pub fn parse_string(
+    iterator: &mut Peekable<CharIndices>,
+    source_length: usize,
+) -> Result<Token, LexerError> {
     let mut content = String::new();
 
     let begin_index = iterator.next().expect("Expected string to stay valid").0;
-    let mut end_index = begin_index;
+    // Closing the string is only suggested if nothing but whitespace follows the end of its
+    // first line.
+    let mut line_end = None;
+    let mut closable = true;
     loop {
         if let Some((index, char)) = iterator.peek() {
             match *char {
@@ -410,60 +680,265 @@ pub fn parse_string(iterator: &mut Peekable<CharIndices>) -> Result<Token, Lexer
                     return Ok(Token::String(content));
                 }
                 '\\' => {
-                    content.push(parse_escape(iterator).map_err(|err| err)?);
+                    closable &= line_end.is_none();
+                    content.push(parse_escape(iterator)?);
                     continue;
                 }
                 c => {
                     content.push(c);
-                    end_index = *index;
+                    if c == '\n' && line_end.is_none() {
+                        line_end = Some(*index);
+                    } else if line_end.is_some() && !c.is_whitespace() {
+                        closable = false;
+                    }
                 }
             }
             iterator.next();
         } else {
-            return Err(LexerError::ParsingError {
+            return Err(LexerError::UnterminatedString {
                 start_position: begin_index,
-                end_position: end_index + 1,
-                message: String::from("Syntax Error: Found end of file while trying to parse string literal. Make sure to close the quotes or remove the trailing `\"`"),
+                end_position: source_length,
+                suggestion: closable.then(|| {
+                    Suggestion::insert(
+                        line_end.unwrap_or(source_length),
+                        "\"",
+                        "close the string with `\"`",
+                    )
+                }),
             });
         }
     }
 }
 
-pub fn parse_tokens(content: String) -> Result<TokenList, LexerError> {
-    let mut return_val = vec![];
-    let mut iterator = content.char_indices().peekable();
-    loop {
-        if let Some((index, char)) = iterator.peek() {
+fn punctuation(char: char) -> Option<Token> {
+    match char {
+        '+' => Some(Token::Plus),
+        '-' => Some(Token::Minus),
+        '*' => Some(Token::Multiply),
+        '/' => Some(Token::Divide),
+        '(' => Some(Token::LParenthesis),
+        ')' => Some(Token::RParenthesis),
+        '[' => Some(Token::LBracket),
+        ']' => Some(Token::RBracket),
+        '{' => Some(Token::LBrace),
+        '}' => Some(Token::RBrace),
+        '<' => Some(Token::Less),
+        '>' => Some(Token::Greater),
+        ';' => Some(Token::Semicolon),
+        ',' => Some(Token::Comma),
+        '.' => Some(Token::Dot),
+        _ => None,
+    }
+}
+
+/// Skips the rest of a token that failed to lex and started with `first`. The rest of a string
+/// or char literal is skipped up to its closing quote, so the quote isn't taken as the start of
+/// another literal, and char literals stop at the end of the line. Anything else is skipped up
+/// to the next whitespace, quote, `;` or `}`, so that the parser still finds the end of the
+/// statement or block it is in.
+fn skip_to_sync_point(iterator: &mut Peekable<CharIndices>, first: char) {
+    if let '"' | '\'' = first {
+        let mut escaped = false;
+        for (_, char) in iterator.by_ref() {
             match char {
-                '+' => return_val.push(Token::Plus),
-                '-' => return_val.push(Token::Minus),
-                '*' => return_val.push(Token::Multiply),
-                '/' => return_val.push(Token::Divide),
-                '(' => return_val.push(Token::LParenthesis),
-                ')' => return_val.push(Token::RParenthesis),
-                '"' => {
-                    return_val.push(parse_string(&mut iterator)?);
-                    continue;
+                '\\' if !escaped => escaped = true,
+                '\n' if first == '\'' => return,
+                char if char == first && !escaped => return,
+                _ => escaped = false,
+            }
+        }
+        return;
+    }
+    while iterator
+        .next_if(|(_, char)| !(char.is_whitespace() || matches!(char, '"' | '\'' | ';' | '}')))
+        .is_some()
+    {}
+}
+
+/// Returns whether the last of `tokens` is a `-` negating what follows it, rather than
+/// subtracting it from an operand.
+fn follows_unary_minus(tokens: &[Token]) -> bool {
+    match tokens {
+        [.., before, Token::Minus] => !matches!(
+            before,
+            Token::I8(_)
+                | Token::I16(_)
+                | Token::I32(_)
+                | Token::I64(_)
+                | Token::I128(_)
+                | Token::U8(_)
+                | Token::U16(_)
+                | Token::U32(_)
+                | Token::U64(_)
+                | Token::U128(_)
+                | Token::F32(_)
+                | Token::F64(_)
+                | Token::BigInt(_)
+                | Token::Decimal(_)
+                | Token::String(_)
+                | Token::Char(_)
+                | Token::True
+                | Token::False
+                | Token::Identifier(_)
+                | Token::RParenthesis
+                | Token::RBracket
+        ),
+        [Token::Minus] => true,
+        _ => false,
+    }
+}
+
+/// Lexes `content` completely. After an error the lexer skips to the next whitespace or quote
+/// and carries on, so that every error of the source is found at once.
+pub fn parse_tokens(content: String) -> Lexed {
+    let mut lexed = Lexed {
+        tokens: vec![],
+        spans: vec![],
+        errors: vec![],
+    };
+    let mut iterator = content.char_indices().peekable();
+    // A `#!` line at the very start is a shebang for the shell, like `#!/usr/bin/env tray`.
+    // The newline is kept so positions and line numbers stay the same.
+    if content.starts_with("#!") {
+        while iterator.next_if(|(_, char)| *char != '\n').is_some() {}
+    }
+    while let Some(&(index, char)) = iterator.peek() {
+        let token = match char {
+            '"' => parse_string(&mut iterator, content.len()),
+            '\'' => parse_char(&mut iterator),
+            '0'..='9' => parse_number(&mut iterator, follows_unary_minus(&lexed.tokens)),
+            c if c.is_alphabetic() || c == '_' => Ok(parse_identifier(&mut iterator)),
+            _ if char.is_whitespace() => {
+                iterator.next();
+                continue;
+            }
+            ':' => {
+                iterator.next();
+                match iterator.next_if(|(_, char)| *char == ':') {
+                    Some(_) => Ok(Token::DoubleColon),
+                    None => Err(LexerError::IllegalCharacter {
+                        position: index,
+                        code: &error_codes::UNRECOGNIZED_CHARACTER,
+                        message: String::from("Unrecognized character :, did you mean `::`?"),
+                    }),
                 }
-                '\'' => {
-                    return_val.push(parse_char(&mut iterator)?);
+            }
+            '=' => {
+                iterator.next();
+                match iterator.next_if(|(_, char)| *char == '>') {
+                    Some(_) => Ok(Token::FatArrow),
+                    None => Ok(Token::Equal),
                 }
-                '0'..='9' => {
-                    return_val.push(parse_number(&mut iterator)?);
-                    continue;
+            }
+            c => {
+                iterator.next();
+                punctuation(c).ok_or_else(|| LexerError::IllegalCharacter {
+                    position: index,
+                    code: &error_codes::UNRECOGNIZED_CHARACTER,
+                    message: format!("Unrecognized character {c}"),
+                })
+            }
+        };
+
+        match token {
+            Ok(token) => {
+                let mut start = index;
+                // Only a literal read after a unary minus holds the minimum of its type, like
+                // `-128i8`, and it takes the place of the minus.
+                if matches!(
+                    token,
+                    Token::I8(i8::MIN)
+                        | Token::I16(i16::MIN)
+                        | Token::I32(i32::MIN)
+                        | Token::I64(i64::MIN)
+                        | Token::I128(i128::MIN)
+                ) {
+                    lexed.tokens.pop();
+                    start = lexed.spans.pop().expect("The minus has a span").start;
                 }
-                _ if char.is_whitespace() => {}
-                c => return Err(LexerError::IllegalCharacter { position: *index, message: format!("Unrecognized character {c}") }),
+                let end = iterator.peek().map_or(content.len(), |(end, _)| *end);
+                lexed.tokens.push(token);
+                lexed.spans.push(start..end);
+            }
+            Err(error) => {
+                lexed.errors.push(error);
+                skip_to_sync_point(&mut iterator, char);
             }
-            iterator.next();
-        } else {
-            break;
         }
     }
 
-    Ok(return_val)
+    lexed
 }
 
-pub fn parse_one(string: String) -> Result<Vec<Token>, LexerError> {
-    parse_tokens(string)
+/// Returns whether `content` can't be a complete input yet because it ends inside a string
+/// literal, has unclosed `(`, `{` or `[`, or ends with a binary operator.
+///
+/// This doesn't validate anything else: input that is complete but malformed is left to the
+/// lexer and parser to report.
+pub fn is_incomplete(content: &str) -> bool {
+    let mut depth = 0i32;
+    let mut quote = None;
+    let mut last = None;
+    let mut iterator = content.chars();
+    while let Some(char) = iterator.next() {
+        if let Some(quote_char) = quote {
+            if char == '\\' {
+                iterator.next();
+            } else if char == quote_char {
+                quote = None;
+            }
+            continue;
+        }
+        match char {
+            '"' | '\'' => quote = Some(char),
+            '(' | '{' | '[' => depth += 1,
+            ')' | '}' | ']' => depth -= 1,
+            _ => {}
+        }
+        if !char.is_whitespace() {
+            last = Some(char);
+        }
+    }
+
+    quote == Some('"') || depth > 0 || matches!(last, Some('+' | '-' | '*' | '/'))
+}
+
+/// Lexes `string`, returning its tokens only if it has no errors.
+pub fn parse_one(string: String) -> Result<TokenList, Vec<LexerError>> {
+    let lexed = parse_tokens(string);
+    if lexed.errors.is_empty() {
+        Ok(lexed.tokens)
+    } else {
+        Err(lexed.errors)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lex(source: &str) -> Lexed {
+        parse_tokens(source.to_string())
+    }
+
+    #[test]
+    fn negated_minimum_literals_are_one_token() {
+        let lexed = lex("i8 a = -128i8; -2147483648i32;");
+        assert!(lexed.errors.is_empty());
+        assert!(matches!(lexed.tokens[3], Token::I8(i8::MIN)));
+        assert_eq!(lexed.spans[3], 7..13);
+        assert!(matches!(lexed.tokens[5], Token::I32(i32::MIN)));
+        assert_eq!(lexed.tokens.len(), 7);
+    }
+
+    #[test]
+    fn literals_past_the_maximum_are_still_out_of_range() {
+        // A subtraction doesn't negate its right operand, and only the minimum fits negated.
+        for source in ["3 -128i8;", "-129i8;", "128i8;", "-256u8;"] {
+            let lexed = lex(source);
+            assert_eq!(lexed.errors.len(), 1, "{source}");
+            let code = lexed.errors[0].to_diagnostic().code.code;
+            assert_eq!(code, "T0002", "{source}");
+        }
+    }
 }
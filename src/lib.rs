@@ -0,0 +1,238 @@
+#![allow(clippy::needless_return)]
+
+pub mod array;
+pub mod bigint;
+pub mod bytecode;
+pub mod checker;
+mod conversion;
+pub mod decimal;
+pub mod diagnostic;
+pub mod error_codes;
+pub mod executer;
+pub mod formatter;
+mod interpreter;
+pub mod lexer;
+pub mod module;
+pub mod native;
+pub mod optimizer;
+pub mod parser;
+pub mod sandbox;
+pub mod vm;
+
+pub use conversion::{FromTray, IntoTray};
+pub use executer::{RuntimeError, Value};
+pub use interpreter::{Engine, Error, Interpreter};
+pub use native::{ClassBuilder, TrayClass};
+pub use sandbox::{Capabilities, Capability, InterruptHandle, Limit, Limits};
+
+use std::ops::Range;
+
+use diagnostic::Diagnostic;
+use lexer::{Lexed, LexerError, Token};
+use parser::Statement;
+
+/// Lexes `source`, returning every lexer error if there is any.
+pub fn lex(source: &str) -> Result<Lexed, Vec<Diagnostic>> {
+    let lexed = lexer::parse_tokens(source.to_string());
+    if !lexed.errors.is_empty() {
+        return Err(lexed.errors.iter().map(LexerError::to_diagnostic).collect());
+    }
+    Ok(lexed)
+}
+
+/// Lexes and parses `source`, returning the errors of both steps if there is any.
+pub fn parse(source: &str) -> Result<Vec<Statement>, Vec<Diagnostic>> {
+    parse_with_lines(source).map(|(statements, _)| statements)
+}
+
+/// Lexes and parses `source` like [`parse`], along with the 1-based line each statement starts
+/// on.
+pub fn parse_with_lines(source: &str) -> Result<(Vec<Statement>, Vec<u32>), Vec<Diagnostic>> {
+    let (lexed, statements) = parse_at(source, parser::DEFAULT_MAX_NESTING)?;
+    let lines = statement_lines(source, &lexed, &statements);
+    let statements = statements
+        .into_iter()
+        .map(|(_, statement)| statement)
+        .collect();
+    Ok((statements, lines))
+}
+
+/// The tokens of a source and its statements, along with the index of their first token.
+type Parsed = (Lexed, Vec<(usize, Statement)>);
+
+/// Lexes and parses `source` like [`parse`], along with its tokens and the index of the first
+/// token of each statement. Expressions, blocks and types can nest at most `max_nesting` levels
+/// deep.
+pub(crate) fn parse_at(source: &str, max_nesting: usize) -> Result<Parsed, Vec<Diagnostic>> {
+    let (parsed, diagnostics) = parse_recovering(source, max_nesting);
+    if !diagnostics.is_empty() {
+        return Err(diagnostics);
+    }
+    Ok(parsed)
+}
+
+/// Lexes and parses `source` like [`parse_at`], but keeps going after errors so that they can
+/// be reported together. The characters a lexer error is about are left out of the tokens, and
+/// the statements a parse error is in are left out of the statements.
+pub(crate) fn parse_recovering(source: &str, max_nesting: usize) -> (Parsed, Vec<Diagnostic>) {
+    let lexed = lexer::parse_tokens(source.to_string());
+    let (statements, errors) = parser::parse_statements_at(&lexed.tokens, max_nesting);
+    let mut diagnostics: Vec<Diagnostic> =
+        lexed.errors.iter().map(LexerError::to_diagnostic).collect();
+    let lexer_errors: Vec<usize> = diagnostics
+        .iter()
+        .filter_map(|diagnostic| Some(diagnostic.span.as_ref()?.start))
+        .collect();
+    // The characters left out by a lexer error usually make the statement they are in fail to
+    // parse too, so the parse errors of those statements would only repeat it.
+    diagnostics.extend(
+        errors
+            .iter()
+            .filter(|error| {
+                let position = error.position.unwrap_or(lexed.tokens.len());
+                let statement_start = lexed.tokens[..position]
+                    .iter()
+                    .rposition(|token| matches!(token, Token::Semicolon))
+                    .map_or(0, |semicolon| lexed.spans[semicolon].end);
+                let end = lexed
+                    .spans
+                    .get(position)
+                    .map_or(source.len(), |span| span.start);
+                !lexer_errors
+                    .iter()
+                    .any(|start| (statement_start..=end).contains(start))
+            })
+            .map(|error| error.to_diagnostic(&lexed.spans)),
+    );
+    ((lexed, statements), diagnostics)
+}
+
+/// Lexes and parses `source` like [`parse`], returning the byte range of each statement instead.
+/// The statements of the body of a loop follow the loop, like in a [`Chunk`](bytecode::Chunk),
+/// and the range of a loop only covers its header.
+pub fn statement_spans(source: &str) -> Result<Vec<Range<usize>>, Vec<Diagnostic>> {
+    let (lexed, statements) = parse_at(source, parser::DEFAULT_MAX_NESTING)?;
+    let mut spans = vec![];
+    push_statement_spans(&lexed, &statements, lexed.tokens.len(), &mut spans);
+    Ok(spans)
+}
+
+/// Adds the byte range of each of `statements` to `spans`, followed by the ones of the
+/// statements of their body for loops. The tokens of the statements end before the token at
+/// `end`.
+fn push_statement_spans(
+    lexed: &Lexed,
+    statements: &[(usize, Statement)],
+    end: usize,
+    spans: &mut Vec<Range<usize>>,
+) {
+    // A statement ends where the next one starts, without the semicolons between them, or
+    // the opening brace of the body of a loop.
+    let span = |start: usize, mut end: usize| {
+        while end > start + 1 && matches!(lexed.tokens[end - 1], Token::Semicolon | Token::LBrace) {
+            end -= 1;
+        }
+        lexed.spans[start].start..lexed.spans[end - 1].end
+    };
+    for (index, (start, statement)) in statements.iter().enumerate() {
+        let next = statements.get(index + 1).map_or(end, |(next, _)| *next);
+        let Statement::For { body, .. } = statement else {
+            spans.push(span(*start, next));
+            continue;
+        };
+        // The body ends before the closing brace of the loop.
+        let mut closing = next - 1;
+        while matches!(lexed.tokens[closing], Token::Semicolon) {
+            closing -= 1;
+        }
+        let header_end = body.first().map_or(closing, |(first, _)| *first);
+        spans.push(span(*start, header_end));
+        push_statement_spans(lexed, body, closing, spans);
+    }
+}
+
+/// Returns the 1-based line each of `statements`, parsed from `source`, starts on. The lines of
+/// the statements of the body of a loop follow the line of the loop, like in a
+/// [`Chunk`](bytecode::Chunk).
+pub(crate) fn statement_lines(
+    source: &str,
+    lexed: &Lexed,
+    statements: &[(usize, Statement)],
+) -> Vec<u32> {
+    let mut starts = vec![];
+    statement_starts(statements, &mut starts);
+    // The statements are in order, so the newlines are counted from the previous one.
+    let mut line = 1;
+    let mut counted = 0;
+    starts
+        .iter()
+        .map(|start| {
+            let offset = lexed.spans[*start].start;
+            line += source[counted..offset].matches('\n').count() as u32;
+            counted = offset;
+            line
+        })
+        .collect()
+}
+
+/// Adds the index of the first token of each of `statements` to `starts`, followed by the ones
+/// of the statements of their body for loops.
+fn statement_starts(statements: &[(usize, Statement)], starts: &mut Vec<usize>) {
+    for (start, statement) in statements {
+        starts.push(*start);
+        if let Statement::For { body, .. } = statement {
+            statement_starts(body, starts);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parses `source` with error recovery, returning the statements it kept, formatted, and
+    /// the code of each error along with the part of the source it points at.
+    fn recover(source: &str) -> (Vec<String>, Vec<(&'static str, &str)>) {
+        let ((_, statements), mut diagnostics) =
+            parse_recovering(source, parser::DEFAULT_MAX_NESTING);
+        diagnostics.sort_by_key(|diagnostic| diagnostic.span.as_ref().map(|span| span.start));
+        let statements = statements
+            .iter()
+            .map(|(_, statement)| formatter::format_statement(statement))
+            .collect();
+        let errors = diagnostics
+            .iter()
+            .map(|diagnostic| {
+                let span = diagnostic.span.clone().expect("Syntax errors have a span");
+                (diagnostic.code.code, &source[span])
+            })
+            .collect();
+        (statements, errors)
+    }
+
+    #[test]
+    fn reports_every_error_of_a_source() {
+        let (statements, errors) = recover("i32 a = ;\nu8 b = 1 $;\ni32 c = 2;\nc + ;\nc;");
+        assert_eq!(errors, [("T0013", ";"), ("T0001", "$"), ("T0013", ";")]);
+        // The statement with a lexer error is kept without the characters it is about.
+        assert_eq!(statements, ["u8 b = 1;", "i32 c = 2;", "c;"]);
+    }
+
+    #[test]
+    fn leaves_out_the_parse_errors_caused_by_lexer_errors() {
+        let (statements, errors) = recover("'ab' * 1; 2 * 300u8; \"open");
+        assert_eq!(
+            errors.iter().map(|(code, _)| *code).collect::<Vec<_>>(),
+            ["T0011", "T0002", "T0012"]
+        );
+        assert!(statements.is_empty());
+    }
+
+    #[test]
+    fn resynchronizes_after_blocks() {
+        // A loop is left out as a whole at its first error.
+        let (statements, errors) = recover("for x in [1] { x + ; 1 +* 2; }\n4 +* 5;\n3;");
+        assert_eq!(errors, [("T0013", ";"), ("T0013", "*")]);
+        assert_eq!(statements, ["3;"]);
+    }
+}
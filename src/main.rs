@@ -1,36 +1,494 @@
-mod executer;
+#![allow(clippy::needless_return)]
+
+mod cli;
+mod diff;
 mod extensions;
-mod lexer;
-mod parser;
 mod reader;
-use std::env;
+mod repl;
+use std::{
+    env,
+    io::{IsTerminal, Read},
+    path::Path,
+    process::ExitCode,
+    sync::OnceLock,
+};
 
+use cli::Command;
 use extensions::IteratorExt;
+use tray::{
+    bytecode::{self, Chunk},
+    diagnostic::{self, Diagnostic, ErrorFormat},
+    error_codes, formatter, lexer, module,
+    parser::Statement,
+    Engine, Interpreter, InterruptHandle, RuntimeError, Value,
+};
+
+const EXIT_RUNTIME_ERROR: u8 = 1;
+const EXIT_COMPILE_ERROR: u8 = 2;
+/// How many times `check --fix` fixes a file at most, since a fix can uncover other errors.
+const MAX_FIX_PASSES: usize = 10;
+
+fn main() -> ExitCode {
+    let arguments = env::args().skip(1).collect_into_vec();
+    let options = match cli::parse_arguments(arguments) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("{message}\n\n{}", cli::USAGE);
+            return ExitCode::from(EXIT_COMPILE_ERROR);
+        }
+    };
+    let promote_overflow =
+        options.promote_overflow || env::var_os("TRAY_PROMOTE_OVERFLOW").is_some();
+
+    let driver = Driver {
+        error_format: options.error_format,
+        max_errors: options.max_errors,
+        engine: options.engine,
+        differential: options.differential,
+        dump_ast: options.dump_ast,
+        promote_overflow,
+        script_arguments: match &options.command {
+            Command::Run { arguments, .. } => arguments.clone(),
+            _ => vec![],
+        },
+    };
+    let result = match options.command {
+        Command::Run { file, .. } => driver.run_file(&file),
+        Command::Eval { source } => driver.run(&SourceFile {
+            name: String::from("<-e>"),
+            text: source,
+        }),
+        Command::Repl => return repl(driver.engine, promote_overflow),
+        Command::Check { file, fix: false } => driver
+            .read_file(&file)
+            .and_then(|source| driver.compile(&source))
+            .map(|_| ()),
+        Command::Check { file, fix: true } => driver
+            .read_file(&file)
+            .and_then(|source| driver.fix(&file, source)),
+        Command::Fmt { files } => driver.format_files(&files),
+        Command::Tokens { file } => driver
+            .read_file(&file)
+            .and_then(|source| driver.lex(&source))
+            .map(|lexed| lexed.tokens.iter().for_each(|token| println!("{token}"))),
+        Command::Ast { file } => driver
+            .read_file(&file)
+            .and_then(|source| driver.parse(&source))
+            .map(|statements| println!("{statements:#?}")),
+        Command::Build { file } => driver
+            .read_file(&file)
+            .and_then(|source| driver.build(&file, &source)),
+        Command::Disasm { file } => driver
+            .load_chunk(&file)
+            .map(|chunk| print!("{}", bytecode::disassemble(&chunk))),
+        Command::Explain { code } => match error_codes::find(&code) {
+            Some(error_code) => {
+                print!("{}", error_code.long_explanation());
+                Ok(())
+            }
+            None => {
+                eprintln!("`{code}` is not an error code.");
+                Err(EXIT_COMPILE_ERROR)
+            }
+        },
+        Command::Help => {
+            println!("{}", cli::USAGE);
+            Ok(())
+        }
+        Command::Version => {
+            println!("tray {}", env!("CARGO_PKG_VERSION"));
+            Ok(())
+        }
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(code) => ExitCode::from(code),
+    }
+}
+
+struct SourceFile {
+    name: String,
+    text: String,
+}
+
+/// Runs the commands that work on files, reporting errors in the chosen format. Failing steps
+/// return the exit code to use.
+struct Driver {
+    error_format: ErrorFormat,
+    max_errors: usize,
+    engine: Engine,
+    differential: bool,
+    dump_ast: bool,
+    promote_overflow: bool,
+    /// The arguments given to the program after its file, which it reads from `args`.
+    script_arguments: Vec<String>,
+}
+
+impl Driver {
+    /// Returns a new interpreter using the engine and arithmetic of the options, with the
+    /// arguments of the program in the `Vec<String>` variable `args`.
+    fn interpreter(&self, engine: Engine) -> Interpreter {
+        let mut interpreter = Interpreter::new();
+        interpreter.set_engine(engine);
+        interpreter.set_promote_overflow(self.promote_overflow);
+        interpreter.set_global("args", self.script_arguments.clone());
+        interpreter
+    }
+
+    fn report(&self, diagnostic: &Diagnostic, source: &SourceFile) {
+        diagnostic.emit(self.error_format, &source.name, &source.text);
+    }
 
-fn main() {
-    let args = env::args().collect_into_vec();
-    if let Some(file_name) = args.get(1) {
-        let content = std::fs::read_to_string(file_name)
-            .expect(format!("Couldn't read file `{file_name}`. Make sure the file is in the right directory and make sure the file is readable.").as_str());
+    /// Reads a source file, or stdin if `file_name` is `-`.
+    fn read_file(&self, file_name: &str) -> Result<SourceFile, u8> {
+        let text = if file_name == "-" {
+            let mut text = String::new();
+            std::io::stdin().read_to_string(&mut text).map(|_| text)
+        } else {
+            std::fs::read_to_string(file_name)
+        };
+        let name = match file_name {
+            "-" => "<stdin>",
+            file_name => file_name,
+        };
+        text.map(|text| SourceFile {
+            name: name.to_string(),
+            text,
+        })
+        .map_err(|err| {
+            let source = SourceFile {
+                name: name.to_string(),
+                text: String::new(),
+            };
+            self.report(
+                &Diagnostic::error(
+                    &error_codes::IO_ERROR,
+                    format!("Couldn't read file `{name}`: {err}"),
+                ),
+                &source,
+            );
+            EXIT_COMPILE_ERROR
+        })
+    }
+
+    fn write_file(&self, file: &str, contents: &[u8], source: &SourceFile) -> Result<(), u8> {
+        std::fs::write(file, contents).map_err(|err| {
+            self.report(
+                &Diagnostic::error(
+                    &error_codes::IO_ERROR,
+                    format!("Couldn't write file `{file}`: {err}"),
+                ),
+                source,
+            );
+            EXIT_RUNTIME_ERROR
+        })
+    }
+
+    /// Reports the first `max_errors` diagnostics and how many were left out, and returns the
+    /// compile error exit code.
+    fn report_all(&self, diagnostics: Vec<Diagnostic>, source: &SourceFile) -> u8 {
+        for diagnostic in diagnostics.iter().take(self.max_errors) {
+            self.report(diagnostic, source);
+        }
+        let hidden = diagnostics.len().saturating_sub(self.max_errors);
+        if hidden > 0 && self.error_format == ErrorFormat::Human {
+            eprintln!("{hidden} more errors not shown, see `--max-errors`.");
+        }
+        EXIT_COMPILE_ERROR
+    }
 
-        let _iterator = content.lines();
+    fn lex(&self, source: &SourceFile) -> Result<lexer::Lexed, u8> {
+        tray::lex(&source.text).map_err(|diagnostics| self.report_all(diagnostics, source))
+    }
 
-        return;
+    fn parse(&self, source: &SourceFile) -> Result<Vec<Statement>, u8> {
+        tray::parse(&source.text).map_err(|diagnostics| self.report_all(diagnostics, source))
     }
 
-    let mut lines = reader::LineReader {};
-    loop {
-        let line = lines.next();
-        if let Some(line) = line {
-            let tokens = lexer::parse_one(line.clone());
-            if let Ok(tokens) = tokens {
-                if let Some(expression) = parser::parse(&tokens) {
-                    dbg!(&expression);
-                    dbg!(executer::execute(&expression));
+    /// Compiles `source`, printing its parse tree before and after the optimizations with
+    /// `--dump-ast`.
+    fn compile(&self, source: &SourceFile) -> Result<Vec<Statement>, u8> {
+        if self.dump_ast {
+            // Parse errors are reported by the compilation below.
+            if let Ok(statements) = tray::parse(&source.text) {
+                eprintln!("AST before optimization:\n{statements:#?}");
+            }
+        }
+        let statements = self
+            .interpreter(self.engine)
+            .compile(&source.text)
+            .map_err(|diagnostics| self.report_all(diagnostics, source))?;
+        if self.dump_ast {
+            eprintln!("AST after optimization:\n{statements:#?}");
+        }
+        Ok(statements)
+    }
+
+    fn compile_chunk(&self, source: &SourceFile) -> Result<Chunk, u8> {
+        if self.dump_ast {
+            self.compile(source)?;
+        }
+        self.interpreter(self.engine)
+            .compile_chunk(&source.text)
+            .map_err(|diagnostics| self.report_all(diagnostics, source))
+    }
+
+    /// Reads a module written by `tray build`.
+    fn read_module(&self, file: &str) -> Result<Chunk, u8> {
+        let source = SourceFile {
+            name: file.to_string(),
+            text: String::new(),
+        };
+        let report = |code, message| {
+            self.report(&Diagnostic::error(code, message), &source);
+            EXIT_COMPILE_ERROR
+        };
+        let bytes = std::fs::read(file).map_err(|err| {
+            report(
+                &error_codes::IO_ERROR,
+                format!("Couldn't read file `{file}`: {err}"),
+            )
+        })?;
+        module::decode(&bytes).map_err(|err| {
+            report(
+                &error_codes::INVALID_MODULE,
+                format!("Couldn't load `{file}`: {err}"),
+            )
+        })
+    }
+
+    /// Compiles `file` to bytecode, or reads it if it is a compiled module.
+    fn load_chunk(&self, file: &str) -> Result<Chunk, u8> {
+        if is_module(file) {
+            return self.read_module(file);
+        }
+        self.read_file(file)
+            .and_then(|source| self.compile_chunk(&source))
+    }
+
+    /// Compiles `source`, read from `file`, to a module next to it.
+    fn build(&self, file: &str, source: &SourceFile) -> Result<(), u8> {
+        let chunk = self.compile_chunk(source)?;
+        let path = module::compiled_path(Path::new(file));
+        self.write_file(&path.to_string_lossy(), &module::encode(&chunk), source)
+    }
+
+    /// Applies the suggested fixes to `source` until none are left, prints them as a diff and
+    /// writes the result to `file`. The errors left are then reported like with `check`.
+    fn fix(&self, file: &str, source: SourceFile) -> Result<(), u8> {
+        let mut text = source.text.clone();
+        for _ in 0..MAX_FIX_PASSES {
+            let Err(diagnostics) = self.interpreter(self.engine).compile(&text) else {
+                break;
+            };
+            let suggestions = diagnostics
+                .into_iter()
+                .flat_map(|diagnostic| diagnostic.suggestions)
+                .collect_into_vec();
+            if suggestions.is_empty() {
+                break;
+            }
+            text = diagnostic::apply_suggestions(&text, &suggestions);
+        }
+        if text != source.text {
+            print!("{}", diff::unified_diff(file, &source.text, &text));
+            self.write_file(file, text.as_bytes(), &source)?;
+        }
+        self.compile(&SourceFile {
+            name: source.name,
+            text,
+        })
+        .map(|_| ())
+    }
+
+    /// Executes `file`, which is a source file or a module compiled by `tray build`. The module
+    /// compiled from a source file is executed instead when it is newer, unless the tree-walker
+    /// is used.
+    fn run_file(&self, file: &str) -> Result<(), u8> {
+        let uses_vm = self.engine == Engine::Vm && !self.differential;
+        let chunk = if is_module(file) {
+            if !uses_vm {
+                eprintln!("`.trayc` modules can only be executed with the `vm` engine.");
+                return Err(EXIT_COMPILE_ERROR);
+            }
+            Some(self.read_module(file)?)
+        } else if uses_vm && file != "-" {
+            module::load_if_newer(Path::new(file))
+        } else {
+            None
+        };
+        let Some(chunk) = chunk else {
+            return self.read_file(file).and_then(|source| self.run(&source));
+        };
+        let mut interpreter = self.interpreter(Engine::Vm);
+        interpreter
+            .execute_chunk(&chunk, |value| println!("{value}"))
+            .map_err(|err| {
+                // The source is read again to point at the statement that failed. Modules
+                // don't have it, so only the line of the statement is given for them.
+                let text = match is_module(file) {
+                    true => String::new(),
+                    false => std::fs::read_to_string(file).unwrap_or_default(),
+                };
+                let source = SourceFile {
+                    name: file.to_string(),
+                    text,
+                };
+                let failed = interpreter.failed_statement();
+                let mut diagnostic = runtime_diagnostic(&err, failed, &source.text);
+                let line = failed.and_then(|failed| chunk.lines.get(failed));
+                if let (None, Some(line)) = (&diagnostic.span, line) {
+                    diagnostic = diagnostic.with_note(format!("in line {line} of the source"));
                 }
-            } else if let Err(err) = tokens {
-                err.arrow_error(line);
+                self.report(&diagnostic, &source);
+                EXIT_RUNTIME_ERROR
+            })
+    }
+
+    /// Compiles and executes `source`, printing the value of every expression statement.
+    fn run(&self, source: &SourceFile) -> Result<(), u8> {
+        let statements = self.compile(source)?;
+        if self.differential {
+            return self.run_differential(&statements, source);
+        }
+        let mut interpreter = self.interpreter(self.engine);
+        interpreter
+            .execute_all(&statements, |value| println!("{value}"))
+            .map_err(|err| {
+                let diagnostic =
+                    runtime_diagnostic(&err, interpreter.failed_statement(), &source.text);
+                self.report(&diagnostic, source);
+                EXIT_RUNTIME_ERROR
+            })
+    }
+
+    /// Executes `statements` with both engines, printing their results if they are the same
+    /// and a diff of them otherwise.
+    fn run_differential(&self, statements: &[Statement], source: &SourceFile) -> Result<(), u8> {
+        let execute = |engine| {
+            let mut values = vec![];
+            let mut interpreter = self.interpreter(engine);
+            let result = interpreter
+                .execute_all(statements, |value| values.push(value))
+                .map_err(|err| {
+                    runtime_diagnostic(&err, interpreter.failed_statement(), &source.text)
+                });
+            (values, result)
+        };
+        // Errors are compared by their diagnostic, which includes the statement they happened
+        // in and the stack trace of native errors.
+        let output = |(values, result): &(Vec<Value>, Result<(), Diagnostic>)| {
+            let mut output = values
+                .iter()
+                .map(|value| format!("{value}\n"))
+                .collect::<String>();
+            if let Err(diagnostic) = result {
+                output += &diagnostic.render(&source.name, &source.text, false);
             }
+            output
+        };
+        let tree = execute(Engine::Tree);
+        let vm = execute(Engine::Vm);
+        let (tree_output, vm_output) = (output(&tree), output(&vm));
+        if tree_output != vm_output {
+            // Only the first difference is shown: outputs that differ on every line after it
+            // would be too long to compare, and it is where the engines started to disagree.
+            let (mut tree_lines, mut vm_lines) = (tree_output.split('\n'), vm_output.split('\n'));
+            let mut line = 1;
+            let (tree_line, vm_line) = loop {
+                match (tree_lines.next(), vm_lines.next()) {
+                    (tree_line, vm_line) if tree_line != vm_line => break (tree_line, vm_line),
+                    _ => line += 1,
+                }
+            };
+            let show = |output: Option<&str>| {
+                output.map_or(String::from("nothing"), |output| format!("`{output}`"))
+            };
+            eprintln!(
+                "The engines gave different results, starting at line {line} of their output:"
+            );
+            eprintln!("  tree: {}", show(tree_line));
+            eprintln!("  vm:   {}", show(vm_line));
+            return Err(EXIT_RUNTIME_ERROR);
+        }
+        let (values, result) = vm;
+        values.iter().for_each(|value| println!("{value}"));
+        result.map_err(|diagnostic| {
+            self.report(&diagnostic, source);
+            EXIT_RUNTIME_ERROR
+        })
+    }
+
+    /// Formats every file in place, or stdin to stdout when no file is given.
+    fn format_files(&self, files: &[String]) -> Result<(), u8> {
+        if files.is_empty() || files == ["-"] {
+            print!("{}", self.format_source(&self.read_file("-")?)?);
+            return Ok(());
         }
+        for file in files {
+            let source = self.read_file(file)?;
+            let formatted = self.format_source(&source)?;
+            self.write_file(file, formatted.as_bytes(), &source)?;
+        }
+        Ok(())
+    }
+
+    /// Formats `source`, keeping its shebang line if it has one.
+    fn format_source(&self, source: &SourceFile) -> Result<String, u8> {
+        let statements = self.parse(source)?;
+        let shebang = match source.text.strip_prefix("#!") {
+            Some(rest) => format!("#!{}\n", rest.lines().next().unwrap_or_default()),
+            None => String::new(),
+        };
+        Ok(shebang + &formatter::format_statements(&statements))
+    }
+}
+
+/// Returns the diagnostic of `err`, which stopped the run of `source` in the statement `failed`
+/// numbered like in [`Chunk::statements`], pointing at that statement.
+fn runtime_diagnostic(err: &RuntimeError, failed: Option<usize>, source: &str) -> Diagnostic {
+    let diagnostic = err.to_diagnostic();
+    let span = failed.and_then(|failed| {
+        let spans = tray::statement_spans(source).ok()?;
+        spans.get(failed).cloned()
+    });
+    match span {
+        Some(span) => diagnostic.with_span(span),
+        None => diagnostic,
+    }
+}
+
+/// Whether `file` is a compiled module rather than a source file.
+fn is_module(file: &str) -> bool {
+    Path::new(file).extension() == Some("trayc".as_ref())
+}
+
+/// The handle interrupting the REPL session, for the signal handler.
+static REPL_INTERRUPT: OnceLock<InterruptHandle> = OnceLock::new();
+
+fn repl(engine: Engine, promote_overflow: bool) -> ExitCode {
+    let mut session = repl::ReplSession::new(engine, promote_overflow);
+    // Without a terminal, the REPL runs piped input as a batch and reports failures through its
+    // exit code. With one, Ctrl-C interrupts the running input instead of the whole session.
+    let interactive = std::io::stdin().is_terminal();
+    if interactive {
+        REPL_INTERRUPT.get_or_init(|| session.interrupt_handle());
+        reader::install_interrupt_handler(handle_interrupt);
+    }
+
+    let mut lines = reader::LineReader::new();
+    let mut failed = false;
+    while let Some(input) = lines.read_input(&|line| session.complete(line)) {
+        failed |= !session.eval(input);
+    }
+    if failed && !interactive {
+        return ExitCode::from(EXIT_RUNTIME_ERROR);
+    }
+    ExitCode::SUCCESS
+}
+
+extern "C" fn handle_interrupt(_signal: i32) {
+    if let Some(interrupt) = REPL_INTERRUPT.get() {
+        interrupt.interrupt();
     }
 }
@@ -0,0 +1,840 @@
+use std::{
+    fmt::Display,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    bytecode::{Chunk, Instruction},
+    executer::Value,
+    parser::{BinaryOperator, Number, NumberType, Type, DEFAULT_MAX_NESTING},
+};
+
+/// The first bytes of every compiled module. The line break catches files mangled by newline
+/// conversions.
+pub const MAGIC: [u8; 8] = *b"\0TRAYC\r\n";
+
+/// The version of the format written by [`encode`]. Modules of other versions are rejected, to
+/// be compiled again from their source.
+pub const FORMAT_VERSION: u16 = 5;
+
+/// The magic bytes, the format version and the checksum of the rest of the module.
+const HEADER_SIZE: usize = MAGIC.len() + 2 + 8;
+
+/// Why a compiled module couldn't be read.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ModuleError {
+    /// The file doesn't start with [`MAGIC`].
+    NotAModule,
+    /// The module was written by another version of tray.
+    UnsupportedVersion(u16),
+    /// The content of the module doesn't match its checksum.
+    ChecksumMismatch,
+    /// The module has a valid checksum but its content is invalid.
+    Malformed(String),
+}
+
+impl Display for ModuleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ModuleError::NotAModule => f.write_str("The file is not a compiled tray module."),
+            ModuleError::UnsupportedVersion(version) => write!(
+                f,
+                "The module has format version {version}, but this version of tray reads version {FORMAT_VERSION}. Build it again."
+            ),
+            ModuleError::ChecksumMismatch => {
+                f.write_str("The module is corrupted: its checksum doesn't match its content.")
+            }
+            ModuleError::Malformed(reason) => write!(f, "The module is malformed: {reason}."),
+        }
+    }
+}
+
+impl std::error::Error for ModuleError {}
+
+fn malformed(reason: &str) -> ModuleError {
+    ModuleError::Malformed(reason.to_string())
+}
+
+/// The 64-bit FNV-1a hash of `bytes`.
+fn checksum(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Returns where `tray build` writes the module compiled from the source file `source`.
+pub fn compiled_path(source: &Path) -> PathBuf {
+    source.with_extension("trayc")
+}
+
+/// Reads the module compiled from the source file `source`, if it is newer than the source.
+///
+/// Anything keeping the module from being used, like it being older than the source, unreadable
+/// or written by another version of tray, gives `None` so that the source is compiled instead.
+pub fn load_if_newer(source: &Path) -> Option<Chunk> {
+    let compiled = compiled_path(source);
+    let modified = |path: &Path| std::fs::metadata(path).and_then(|metadata| metadata.modified());
+    if modified(&compiled).ok()? <= modified(source).ok()? {
+        return None;
+    }
+    decode(&std::fs::read(compiled).ok()?).ok()
+}
+
+/// Serializes `chunk` to the `.trayc` format: [`MAGIC`], [`FORMAT_VERSION`] and the checksum of
+/// the tables and instructions which follow them. Numbers are written in little endian.
+///
+/// # Panics
+///
+/// Panics if a constant of `chunk` is an object or a `Vec`, which
+/// [`compile`](crate::bytecode::compile) never makes.
+pub fn encode(chunk: &Chunk) -> Vec<u8> {
+    let mut encoder = Encoder::default();
+    encoder.list(&chunk.statements, |encoder, statement| {
+        encoder.string(statement)
+    });
+    encoder.list(&chunk.lines, |encoder, line| encoder.u32(*line));
+    encoder.list(&chunk.constants, Encoder::value);
+    encoder.list(&chunk.slots, |encoder, slot| encoder.string(slot));
+    encoder.list(&chunk.names, |encoder, name| encoder.string(name));
+    encoder.list(&chunk.types, Encoder::declared_type);
+    encoder.list(&chunk.sites, |encoder, site| encoder.string(site));
+    encoder.list(&chunk.scopes, |encoder, scope| {
+        encoder.list(scope, |encoder, slot| encoder.u32(*slot))
+    });
+    encoder.list(&chunk.code, Encoder::instruction);
+    encoder.list(&chunk.statement_of, |encoder, statement| {
+        encoder.u32(*statement)
+    });
+    encoder.list(&chunk.depths, |encoder, depth| encoder.u32(*depth));
+
+    let mut bytes = MAGIC.to_vec();
+    bytes.extend(FORMAT_VERSION.to_le_bytes());
+    bytes.extend(checksum(&encoder.bytes).to_le_bytes());
+    bytes.extend(encoder.bytes);
+    bytes
+}
+
+/// Deserializes a chunk written by [`encode`], checking that it can be run safely.
+pub fn decode(bytes: &[u8]) -> Result<Chunk, ModuleError> {
+    if !bytes.starts_with(&MAGIC) {
+        return Err(ModuleError::NotAModule);
+    }
+    if bytes.len() < HEADER_SIZE {
+        return Err(malformed("its header is truncated"));
+    }
+    let version = u16::from_le_bytes([bytes[8], bytes[9]]);
+    if version != FORMAT_VERSION {
+        return Err(ModuleError::UnsupportedVersion(version));
+    }
+    let expected = u64::from_le_bytes(bytes[10..HEADER_SIZE].try_into().unwrap());
+    let payload = &bytes[HEADER_SIZE..];
+    if checksum(payload) != expected {
+        return Err(ModuleError::ChecksumMismatch);
+    }
+
+    let mut decoder = Decoder {
+        bytes: payload,
+        position: 0,
+    };
+    // The fields are read in the order they are written by `encode`.
+    let chunk = Chunk {
+        statements: decoder.list(Decoder::string)?,
+        lines: decoder.list(Decoder::u32)?,
+        constants: decoder.list(Decoder::value)?,
+        slots: decoder.list(Decoder::string)?,
+        names: decoder.list(Decoder::string)?,
+        types: decoder.list(Decoder::declared_type)?,
+        sites: decoder.list(Decoder::string)?,
+        scopes: decoder.list(|decoder| decoder.list(Decoder::u32))?,
+        code: decoder.list(Decoder::instruction)?,
+        statement_of: decoder.list(Decoder::u32)?,
+        depths: decoder.list(Decoder::u32)?,
+    };
+    if decoder.position != payload.len() {
+        return Err(malformed("it has bytes after its instructions"));
+    }
+    validate(&chunk)?;
+    Ok(chunk)
+}
+
+/// Checks that the instructions of `chunk` only refer to entries of its tables, never pop from
+/// an empty stack and only use the loops they are in, which the virtual machine relies on.
+fn validate(chunk: &Chunk) -> Result<(), ModuleError> {
+    if chunk.statement_of.len() != chunk.code.len() {
+        return Err(malformed(
+            "its instructions and their statements don't match",
+        ));
+    }
+    if chunk.depths.len() != chunk.code.len() {
+        return Err(malformed("its instructions and their depths don't match"));
+    }
+    if !chunk.lines.is_empty() && chunk.lines.len() != chunk.statements.len() {
+        return Err(malformed("its statements and their lines don't match"));
+    }
+    let check = |index: &u32, length: usize, entry: &str| {
+        if *index as usize >= length {
+            return Err(ModuleError::Malformed(format!(
+                "an instruction refers to a missing {entry}"
+            )));
+        }
+        Ok(())
+    };
+    for slot in chunk.scopes.iter().flatten() {
+        check(slot, chunk.slots.len(), "variable")?;
+    }
+    for (instruction, statement) in chunk.code.iter().zip(&chunk.statement_of) {
+        check(statement, chunk.statements.len(), "statement")?;
+        match instruction {
+            Instruction::Constant(index) => check(index, chunk.constants.len(), "constant")?,
+            Instruction::Load(slot) | Instruction::Store(slot) => {
+                check(slot, chunk.slots.len(), "variable")?
+            }
+            Instruction::Declare {
+                slot,
+                declared_type,
+                ..
+            } => {
+                check(slot, chunk.slots.len(), "variable")?;
+                if let Some(declared_type) = declared_type {
+                    check(declared_type, chunk.types.len(), "type")?;
+                }
+            }
+            Instruction::Call { function, site, .. } => {
+                check(function, chunk.names.len(), "name")?;
+                check(site, chunk.sites.len(), "call")?;
+            }
+            Instruction::CallMethod { method, site, .. } => {
+                check(method, chunk.names.len(), "name")?;
+                check(site, chunk.sites.len(), "call")?;
+            }
+            Instruction::GetField { field, site } => {
+                check(field, chunk.names.len(), "name")?;
+                check(site, chunk.sites.len(), "call")?;
+            }
+            Instruction::GetConstant { class, member } => {
+                check(class, chunk.names.len(), "name")?;
+                check(member, chunk.names.len(), "name")?;
+            }
+            Instruction::CallStatic {
+                class,
+                function,
+                site,
+                ..
+            } => {
+                check(class, chunk.names.len(), "name")?;
+                check(function, chunk.names.len(), "name")?;
+                check(site, chunk.sites.len(), "call")?;
+            }
+            Instruction::MakeVec { element_type, .. } | Instruction::Collect { element_type } => {
+                if let Some(element_type) = element_type {
+                    check(element_type, chunk.types.len(), "type")?;
+                }
+            }
+            Instruction::Iterate { scope } => check(scope, chunk.scopes.len(), "scope")?,
+            Instruction::Next { slot, end } => {
+                check(slot, chunk.slots.len(), "variable")?;
+                check(end, chunk.code.len(), "instruction")?;
+            }
+            Instruction::Jump(target) => check(target, chunk.code.len(), "instruction")?,
+            Instruction::Plus
+            | Instruction::Negate
+            | Instruction::Binary(_)
+            | Instruction::Cast(_)
+            | Instruction::Yield
+            | Instruction::Index
+            | Instruction::StoreIndex
+            | Instruction::Append
+            | Instruction::AppendIf
+            | Instruction::EndLoop => {}
+        }
+    }
+    validate_flow(chunk)
+}
+
+/// Follows every path through the instructions of `chunk`, checking that they never pop from an
+/// empty stack or use a loop outside of one. As jumps make an instruction reachable from several
+/// others, the size of the stack and the number of loops must be the same on all of them.
+fn validate_flow(chunk: &Chunk) -> Result<(), ModuleError> {
+    // The size of the stack and the number of loops before each instruction reached so far.
+    let mut states: Vec<Option<(u32, u32)>> = vec![None; chunk.code.len()];
+    let mut pending = vec![(0, (0, 0))];
+    while let Some((index, state)) = pending.pop() {
+        let Some(instruction) = chunk.code.get(index) else {
+            continue;
+        };
+        match states[index] {
+            Some(reached) if reached == state => continue,
+            Some(_) => return Err(malformed("an instruction is reached with different stacks")),
+            None => states[index] = Some(state),
+        }
+        let (depth, loops) = state;
+        let (pops, pushes) = instruction.stack_effect();
+        let depth = depth
+            .checked_sub(pops)
+            .ok_or_else(|| malformed("an instruction pops more values than there are"))?
+            + pushes;
+        let outside_loop = || malformed("an instruction uses a loop outside of one");
+        let loops = match instruction {
+            Instruction::Iterate { .. } => loops + 1,
+            Instruction::EndLoop | Instruction::Collect { .. } => {
+                loops.checked_sub(1).ok_or_else(outside_loop)?
+            }
+            Instruction::Next { .. } | Instruction::Append | Instruction::AppendIf
+                if loops == 0 =>
+            {
+                return Err(outside_loop());
+            }
+            _ => loops,
+        };
+        match instruction {
+            Instruction::Jump(target) => pending.push((*target as usize, (depth, loops))),
+            Instruction::Next { end, .. } => {
+                pending.push((*end as usize, (depth, loops)));
+                pending.push((index + 1, (depth, loops)));
+            }
+            _ => pending.push((index + 1, (depth, loops))),
+        }
+    }
+    Ok(())
+}
+
+const VALUE_NUMBER: u8 = 0;
+const VALUE_CHAR: u8 = 1;
+const VALUE_STRING: u8 = 2;
+const VALUE_BOOL: u8 = 3;
+
+const TYPE_NUMBER: u8 = 0;
+const TYPE_CHAR: u8 = 1;
+const TYPE_STRING: u8 = 2;
+const TYPE_CLASS: u8 = 3;
+const TYPE_VEC: u8 = 4;
+const TYPE_BOOL: u8 = 5;
+
+const OPERATORS: [BinaryOperator; 4] = [
+    BinaryOperator::Plus,
+    BinaryOperator::Minus,
+    BinaryOperator::Divide,
+    BinaryOperator::Multiply,
+];
+
+#[derive(Default)]
+struct Encoder {
+    bytes: Vec<u8>,
+}
+
+impl Encoder {
+    fn u8(&mut self, value: u8) {
+        self.bytes.push(value);
+    }
+
+    fn bool(&mut self, value: bool) {
+        self.u8(value as u8);
+    }
+
+    fn u32(&mut self, value: u32) {
+        self.bytes.extend(value.to_le_bytes());
+    }
+
+    /// Writes a flag telling whether there is a `value`, followed by it if there is one.
+    fn optional_u32(&mut self, value: Option<u32>) {
+        self.bool(value.is_some());
+        if let Some(value) = value {
+            self.u32(value);
+        }
+    }
+
+    fn string(&mut self, string: &str) {
+        self.u32(string.len() as u32);
+        self.bytes.extend(string.as_bytes());
+    }
+
+    /// Writes the length of `items`, then each of them with `write`.
+    fn list<T>(&mut self, items: &[T], mut write: impl FnMut(&mut Self, &T)) {
+        self.u32(items.len() as u32);
+        for item in items {
+            write(self, item);
+        }
+    }
+
+    fn number_type(&mut self, number_type: NumberType) {
+        self.string(number_type.name());
+    }
+
+    /// Writes the type of `number` and its value: the bits of floats, integers widened to 128
+    /// bits, and the digits of `bigint` and `decimal` numbers.
+    fn number(&mut self, number: &Number) {
+        let number_type = number.number_type();
+        self.number_type(number_type);
+        match number {
+            Number::Float32(value) => self.u32(value.to_bits()),
+            Number::Float64(value) => self.bytes.extend(value.to_bits().to_le_bytes()),
+            Number::BigInt(value) => self.string(&value.to_string()),
+            Number::Decimal(value) => self.string(&value.to_string()),
+            number if number_type.is_signed() => self.bytes.extend(number.as_i128().to_le_bytes()),
+            number => self.bytes.extend(number.as_u128().to_le_bytes()),
+        }
+    }
+
+    fn value(&mut self, value: &Value) {
+        match value {
+            Value::Number(number) => {
+                self.u8(VALUE_NUMBER);
+                self.number(number);
+            }
+            Value::Char(char) => {
+                self.u8(VALUE_CHAR);
+                self.u32(*char as u32);
+            }
+            Value::String(string) => {
+                self.u8(VALUE_STRING);
+                self.string(string);
+            }
+            Value::Bool(bool) => {
+                self.u8(VALUE_BOOL);
+                self.bool(*bool);
+            }
+            Value::Object(_) | Value::Vec(_) => {
+                panic!("Objects and `Vec`s can't be constants of a compiled module.")
+            }
+        }
+    }
+
+    fn declared_type(&mut self, declared_type: &Type) {
+        match declared_type {
+            Type::Number(number_type) => {
+                self.u8(TYPE_NUMBER);
+                self.number_type(*number_type);
+            }
+            Type::Bool => self.u8(TYPE_BOOL),
+            Type::Char => self.u8(TYPE_CHAR),
+            Type::String => self.u8(TYPE_STRING),
+            Type::Class(name) => {
+                self.u8(TYPE_CLASS);
+                self.string(name);
+            }
+            Type::Vec(element) => {
+                self.u8(TYPE_VEC);
+                self.declared_type(element);
+            }
+        }
+    }
+
+    /// Writes the opcode of `instruction`, its index in the declaration of [`Instruction`],
+    /// followed by its operands.
+    fn instruction(&mut self, instruction: &Instruction) {
+        match instruction {
+            Instruction::Constant(index) => {
+                self.u8(0);
+                self.u32(*index);
+            }
+            Instruction::Load(slot) => {
+                self.u8(1);
+                self.u32(*slot);
+            }
+            Instruction::Declare {
+                slot,
+                declared_type,
+                mutable,
+            } => {
+                self.u8(2);
+                self.u32(*slot);
+                self.bool(*mutable);
+                self.optional_u32(*declared_type);
+            }
+            Instruction::Store(slot) => {
+                self.u8(3);
+                self.u32(*slot);
+            }
+            Instruction::Plus => self.u8(4),
+            Instruction::Negate => self.u8(5),
+            Instruction::Binary(operator) => {
+                self.u8(6);
+                let code = OPERATORS.iter().position(|known| known == operator);
+                self.u8(code.expect("Every operator should be listed.") as u8);
+            }
+            Instruction::Cast(target) => {
+                self.u8(7);
+                self.number_type(*target);
+            }
+            Instruction::Call {
+                function,
+                arguments,
+                site,
+            } => {
+                self.u8(8);
+                self.u32(*function);
+                self.u32(*arguments);
+                self.u32(*site);
+            }
+            Instruction::CallMethod {
+                method,
+                arguments,
+                site,
+            } => {
+                self.u8(9);
+                self.u32(*method);
+                self.u32(*arguments);
+                self.u32(*site);
+            }
+            Instruction::GetField { field, site } => {
+                self.u8(10);
+                self.u32(*field);
+                self.u32(*site);
+            }
+            Instruction::GetConstant { class, member } => {
+                self.u8(11);
+                self.u32(*class);
+                self.u32(*member);
+            }
+            Instruction::CallStatic {
+                class,
+                function,
+                arguments,
+                site,
+            } => {
+                self.u8(12);
+                self.u32(*class);
+                self.u32(*function);
+                self.u32(*arguments);
+                self.u32(*site);
+            }
+            Instruction::Yield => self.u8(13),
+            Instruction::MakeVec {
+                count,
+                element_type,
+            } => {
+                self.u8(14);
+                self.u32(*count);
+                self.optional_u32(*element_type);
+            }
+            Instruction::Index => self.u8(15),
+            Instruction::StoreIndex => self.u8(16),
+            Instruction::Iterate { scope } => {
+                self.u8(17);
+                self.u32(*scope);
+            }
+            Instruction::Next { slot, end } => {
+                self.u8(18);
+                self.u32(*slot);
+                self.u32(*end);
+            }
+            Instruction::Jump(target) => {
+                self.u8(19);
+                self.u32(*target);
+            }
+            Instruction::Append => self.u8(20),
+            Instruction::AppendIf => self.u8(21),
+            Instruction::EndLoop => self.u8(22),
+            Instruction::Collect { element_type } => {
+                self.u8(23);
+                self.optional_u32(*element_type);
+            }
+        }
+    }
+}
+
+struct Decoder<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Decoder<'a> {
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], ModuleError> {
+        Ok(self.slice(N)?.try_into().unwrap())
+    }
+
+    fn slice(&mut self, length: usize) -> Result<&'a [u8], ModuleError> {
+        let bytes = self
+            .bytes
+            .get(self.position..self.position.saturating_add(length))
+            .ok_or_else(|| malformed("it ends too early"))?;
+        self.position += length;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, ModuleError> {
+        Ok(self.take::<1>()?[0])
+    }
+
+    fn bool(&mut self) -> Result<bool, ModuleError> {
+        match self.u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(malformed("a flag is neither 0 nor 1")),
+        }
+    }
+
+    fn u32(&mut self) -> Result<u32, ModuleError> {
+        Ok(u32::from_le_bytes(self.take()?))
+    }
+
+    fn optional_u32(&mut self) -> Result<Option<u32>, ModuleError> {
+        match self.bool()? {
+            true => Ok(Some(self.u32()?)),
+            false => Ok(None),
+        }
+    }
+
+    fn string(&mut self) -> Result<String, ModuleError> {
+        let length = self.u32()? as usize;
+        String::from_utf8(self.slice(length)?.to_vec())
+            .map_err(|_| malformed("a string isn't valid UTF-8"))
+    }
+
+    /// Reads a length, then that many items with `read`.
+    fn list<T>(
+        &mut self,
+        mut read: impl FnMut(&mut Self) -> Result<T, ModuleError>,
+    ) -> Result<Vec<T>, ModuleError> {
+        let length = self.u32()?;
+        (0..length).map(|_| read(self)).collect()
+    }
+
+    fn number_type(&mut self) -> Result<NumberType, ModuleError> {
+        NumberType::from_name(&self.string()?).ok_or_else(|| malformed("a number type is unknown"))
+    }
+
+    fn number(&mut self) -> Result<Number, ModuleError> {
+        let number_type = self.number_type()?;
+        let number = match number_type {
+            NumberType::Float32 => Some(Number::Float32(f32::from_bits(self.u32()?))),
+            NumberType::Float64 => Some(Number::Float64(f64::from_bits(u64::from_le_bytes(
+                self.take()?,
+            )))),
+            NumberType::BigInt => self.string()?.parse().ok().map(Number::BigInt),
+            NumberType::Decimal => self.string()?.parse().ok().map(Number::Decimal),
+            _ if number_type.is_signed() => {
+                Number::from_i128(i128::from_le_bytes(self.take()?), number_type)
+            }
+            _ => Number::from_u128(u128::from_le_bytes(self.take()?), number_type),
+        };
+        number.ok_or_else(|| malformed("a number is invalid"))
+    }
+
+    fn value(&mut self) -> Result<Value, ModuleError> {
+        match self.u8()? {
+            VALUE_NUMBER => Ok(Value::Number(self.number()?)),
+            VALUE_CHAR => char::from_u32(self.u32()?)
+                .map(Value::Char)
+                .ok_or_else(|| malformed("a char is invalid")),
+            VALUE_STRING => Ok(Value::String(self.string()?)),
+            VALUE_BOOL => Ok(Value::Bool(self.bool()?)),
+            _ => Err(malformed("a constant has an unknown kind")),
+        }
+    }
+
+    fn declared_type(&mut self) -> Result<Type, ModuleError> {
+        self.nested_type(0)
+    }
+
+    /// Reads a type whose type arguments are nested at most [`DEFAULT_MAX_NESTING`] levels deep,
+    /// like the types the parser accepts by default.
+    fn nested_type(&mut self, depth: usize) -> Result<Type, ModuleError> {
+        if depth >= DEFAULT_MAX_NESTING {
+            return Err(malformed("a type is nested too deeply"));
+        }
+        match self.u8()? {
+            TYPE_NUMBER => Ok(Type::Number(self.number_type()?)),
+            TYPE_BOOL => Ok(Type::Bool),
+            TYPE_CHAR => Ok(Type::Char),
+            TYPE_STRING => Ok(Type::String),
+            TYPE_CLASS => Ok(Type::Class(self.string()?)),
+            TYPE_VEC => Ok(Type::Vec(Box::new(self.nested_type(depth + 1)?))),
+            _ => Err(malformed("a type has an unknown kind")),
+        }
+    }
+
+    fn instruction(&mut self) -> Result<Instruction, ModuleError> {
+        Ok(match self.u8()? {
+            0 => Instruction::Constant(self.u32()?),
+            1 => Instruction::Load(self.u32()?),
+            2 => {
+                let slot = self.u32()?;
+                let mutable = self.bool()?;
+                let declared_type = self.optional_u32()?;
+                Instruction::Declare {
+                    slot,
+                    declared_type,
+                    mutable,
+                }
+            }
+            3 => Instruction::Store(self.u32()?),
+            4 => Instruction::Plus,
+            5 => Instruction::Negate,
+            6 => {
+                let operator = OPERATORS.get(self.u8()? as usize);
+                Instruction::Binary(
+                    operator
+                        .ok_or_else(|| malformed("an operator is unknown"))?
+                        .clone(),
+                )
+            }
+            7 => Instruction::Cast(self.number_type()?),
+            8 => Instruction::Call {
+                function: self.u32()?,
+                arguments: self.u32()?,
+                site: self.u32()?,
+            },
+            9 => Instruction::CallMethod {
+                method: self.u32()?,
+                arguments: self.u32()?,
+                site: self.u32()?,
+            },
+            10 => Instruction::GetField {
+                field: self.u32()?,
+                site: self.u32()?,
+            },
+            11 => Instruction::GetConstant {
+                class: self.u32()?,
+                member: self.u32()?,
+            },
+            12 => Instruction::CallStatic {
+                class: self.u32()?,
+                function: self.u32()?,
+                arguments: self.u32()?,
+                site: self.u32()?,
+            },
+            13 => Instruction::Yield,
+            14 => Instruction::MakeVec {
+                count: self.u32()?,
+                element_type: self.optional_u32()?,
+            },
+            15 => Instruction::Index,
+            16 => Instruction::StoreIndex,
+            17 => Instruction::Iterate { scope: self.u32()? },
+            18 => Instruction::Next {
+                slot: self.u32()?,
+                end: self.u32()?,
+            },
+            19 => Instruction::Jump(self.u32()?),
+            20 => Instruction::Append,
+            21 => Instruction::AppendIf,
+            22 => Instruction::EndLoop,
+            23 => Instruction::Collect {
+                element_type: self.optional_u32()?,
+            },
+            _ => return Err(malformed("an instruction has an unknown opcode")),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{bytecode, Interpreter};
+
+    const SOURCE: &str = "\
+var total = 0u64;
+for x in [1, 2, 3] {
+    total = total + x as u64;
+}
+let name = \"tray\";
+let letter = 'y';
+19.99d * 3d;
+12345678901234567890123456789n + 1n;
+total * 2u64;
+true;
+name";
+
+    fn compile(source: &str) -> Chunk {
+        Interpreter::new().compile_chunk(source).unwrap()
+    }
+
+    fn run(chunk: &Chunk) -> Vec<String> {
+        let mut values = vec![];
+        Interpreter::new()
+            .execute_chunk(chunk, |value| values.push(value.to_string()))
+            .unwrap();
+        values
+    }
+
+    /// Builds a module with a valid header around `payload`.
+    fn with_payload(payload: &[u8]) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend(FORMAT_VERSION.to_le_bytes());
+        bytes.extend(checksum(payload).to_le_bytes());
+        bytes.extend(payload);
+        bytes
+    }
+
+    #[test]
+    fn decoding_gives_back_the_encoded_chunk() {
+        let chunk = compile(SOURCE);
+        let bytes = encode(&chunk);
+        let decoded = decode(&bytes).unwrap();
+        assert_eq!(
+            bytecode::disassemble(&decoded),
+            bytecode::disassemble(&chunk)
+        );
+        assert_eq!(decoded.lines, chunk.lines);
+        assert_eq!(decoded.statements, chunk.statements);
+        assert_eq!(encode(&decoded), bytes);
+        assert_eq!(run(&decoded), run(&chunk));
+    }
+
+    #[test]
+    fn rejects_other_files_and_versions() {
+        assert_eq!(decode(b"var x = 1;").unwrap_err(), ModuleError::NotAModule);
+        assert!(matches!(decode(&MAGIC), Err(ModuleError::Malformed(_))));
+        let mut bytes = encode(&compile(SOURCE));
+        bytes[8..10].copy_from_slice(&(FORMAT_VERSION + 1).to_le_bytes());
+        assert!(matches!(
+            decode(&bytes),
+            Err(ModuleError::UnsupportedVersion(version)) if version == FORMAT_VERSION + 1
+        ));
+    }
+
+    #[test]
+    fn rejects_corrupted_content() {
+        let bytes = encode(&compile(SOURCE));
+        for index in [HEADER_SIZE, bytes.len() / 2, bytes.len() - 1] {
+            let mut corrupted = bytes.clone();
+            corrupted[index] ^= 0x40;
+            assert!(matches!(
+                decode(&corrupted),
+                Err(ModuleError::ChecksumMismatch)
+            ));
+        }
+        assert!(matches!(
+            decode(&bytes[..bytes.len() - 1]),
+            Err(ModuleError::ChecksumMismatch)
+        ));
+    }
+
+    #[test]
+    fn rejects_malformed_content_with_a_valid_checksum() {
+        let payload = &encode(&compile(SOURCE))[HEADER_SIZE..];
+        let truncated = with_payload(&payload[..payload.len() - 3]);
+        assert!(matches!(decode(&truncated), Err(ModuleError::Malformed(_))));
+        let mut extended = payload.to_vec();
+        extended.push(0);
+        assert!(matches!(
+            decode(&with_payload(&extended)),
+            Err(ModuleError::Malformed(_))
+        ));
+        // A list claiming more entries than there are bytes.
+        assert!(matches!(
+            decode(&with_payload(&[0xff; 4])),
+            Err(ModuleError::Malformed(_))
+        ));
+    }
+
+    #[test]
+    fn rejects_instructions_the_virtual_machine_cannot_run() {
+        let mut chunk = compile("1 + 2");
+        chunk.code[0] = Instruction::Constant(chunk.constants.len() as u32);
+        assert_eq!(
+            decode(&encode(&chunk)).unwrap_err(),
+            malformed("an instruction refers to a missing constant")
+        );
+        let mut chunk = compile("1 + 2");
+        // Nothing is on the stack to negate.
+        chunk.code.insert(0, Instruction::Negate);
+        chunk.statement_of.insert(0, 0);
+        chunk.depths.insert(0, 0);
+        assert!(matches!(
+            decode(&encode(&chunk)),
+            Err(ModuleError::Malformed(_))
+        ));
+    }
+}
@@ -0,0 +1,430 @@
+use std::{any::Any, cell::RefCell, collections::HashMap, fmt::Debug, fmt::Display, rc::Rc};
+
+use crate::{
+    bigint::BigInt,
+    conversion::{FromTray, IntoTray},
+    decimal::Decimal,
+    error_codes,
+    executer::{RuntimeError, Value},
+    parser::{NumberType, Type},
+    sandbox::Capability,
+};
+
+/// A Rust type with a tray equivalent, used to type check calls to native functions before
+/// running them.
+pub trait TrayType {
+    fn tray_type() -> Type;
+}
+
+macro_rules! tray_type {
+    ($type:ty, $tray_type:expr) => {
+        impl TrayType for $type {
+            fn tray_type() -> Type {
+                $tray_type
+            }
+        }
+    };
+}
+
+tray_type!(f32, Type::Number(NumberType::Float32));
+tray_type!(f64, Type::Number(NumberType::Float64));
+tray_type!(i8, Type::Number(NumberType::Int8));
+tray_type!(i16, Type::Number(NumberType::Int16));
+tray_type!(i32, Type::Number(NumberType::Int32));
+tray_type!(i64, Type::Number(NumberType::Int64));
+tray_type!(i128, Type::Number(NumberType::Int128));
+tray_type!(u8, Type::Number(NumberType::UInt8));
+tray_type!(u16, Type::Number(NumberType::UInt16));
+tray_type!(u32, Type::Number(NumberType::UInt32));
+tray_type!(u64, Type::Number(NumberType::UInt64));
+tray_type!(u128, Type::Number(NumberType::UInt128));
+tray_type!(BigInt, Type::Number(NumberType::BigInt));
+tray_type!(Decimal, Type::Number(NumberType::Decimal));
+tray_type!(bool, Type::Bool);
+tray_type!(char, Type::Char);
+tray_type!(String, Type::String);
+
+impl<T: TrayType> TrayType for Vec<T> {
+    fn tray_type() -> Type {
+        Type::Vec(Box::new(T::tray_type()))
+    }
+}
+
+/// A Rust type whose values scripts can hold, registered with
+/// [`Interpreter::register_type`](crate::Interpreter::register_type). Its conversions are
+/// implemented with [`tray_class!`](crate::tray_class), for natives to take and return it.
+///
+/// Values of the type are shared between the variables holding them, so a method changing the
+/// value is seen through all of them.
+pub trait TrayClass: Any + Debug + Clone {
+    /// The name of the class in scripts, which must start with an uppercase letter.
+    const NAME: &'static str;
+
+    /// Adds the fields, methods and static members of the class.
+    fn register(class: &mut ClassBuilder<Self>);
+}
+
+/// Implements [`TrayType`], [`IntoTray`] and [`FromTray`] for the [`TrayClass`] `$type`, which
+/// is then passed to and returned by natives as an object of the class.
+///
+/// They aren't implemented for every class at once, as they would overlap with the ones of
+/// `Vec`, which a class could be.
+///
+/// ```
+/// use tray::{ClassBuilder, TrayClass};
+///
+/// #[derive(Debug, Clone)]
+/// struct Point {
+///     x: i64,
+/// }
+///
+/// impl TrayClass for Point {
+///     const NAME: &'static str = "Point";
+///
+///     fn register(class: &mut ClassBuilder<Self>) {
+///         class.field("x", |point: &Point| point.x);
+///     }
+/// }
+///
+/// tray::tray_class!(Point);
+///
+/// let mut interpreter = tray::Interpreter::new();
+/// interpreter.register_type::<Point>();
+/// interpreter.register_fn("points", || vec![Point { x: 1 }, Point { x: 2 }]);
+/// interpreter.eval_str("i64 x = points()[1].x;").unwrap();
+/// assert_eq!(interpreter.get_global::<i64>("x").unwrap(), 2);
+/// ```
+#[macro_export]
+macro_rules! tray_class {
+    ($type:ty) => {
+        impl $crate::native::TrayType for $type {
+            fn tray_type() -> $crate::parser::Type {
+                $crate::parser::Type::Class(<$type as $crate::TrayClass>::NAME.to_string())
+            }
+        }
+
+        impl $crate::IntoTray for $type {
+            fn into_tray(self) -> $crate::Value {
+                $crate::Value::Object($crate::native::Object::new(self))
+            }
+        }
+
+        impl $crate::FromTray for $type {
+            fn from_tray(value: $crate::Value) -> Result<Self, $crate::RuntimeError> {
+                $crate::native::class_from_tray(value)
+            }
+        }
+    };
+}
+
+/// Returns the value of the class `T` held by `value`, for the [`FromTray`] implementation of
+/// [`tray_class!`](crate::tray_class).
+pub fn class_from_tray<T: TrayClass>(value: Value) -> Result<T, RuntimeError> {
+    match &value {
+        Value::Object(object) => object.get::<T>(),
+        _ => None,
+    }
+    .ok_or_else(|| RuntimeError::TypeError {
+        code: &error_codes::MISMATCHED_TYPES,
+        message: format!(
+            "Expected a `{}`, found {value} of type `{}`.",
+            T::NAME,
+            value.value_type()
+        ),
+    })
+}
+
+/// Object safe part of [`TrayClass`], for storing values of any class in an [`Object`].
+trait ObjectData: Any + Debug {}
+
+impl<T: Any + Debug> ObjectData for T {}
+
+/// A value of a [`TrayClass`], shared by the tray values holding it.
+#[derive(Debug, Clone)]
+pub struct Object {
+    class: &'static str,
+    data: Rc<RefCell<dyn ObjectData>>,
+}
+
+impl Object {
+    pub fn new<T: TrayClass>(value: T) -> Self {
+        Object {
+            class: T::NAME,
+            data: Rc::new(RefCell::new(value)),
+        }
+    }
+
+    pub fn class(&self) -> &'static str {
+        self.class
+    }
+
+    /// Whether `other` is this very object rather than a copy of it.
+    pub fn same_as(&self, other: &Object) -> bool {
+        Rc::ptr_eq(&self.data, &other.data)
+    }
+
+    /// Returns how many bytes the value takes on the heap, not counting what it points to.
+    pub fn heap_size(&self) -> usize {
+        std::mem::size_of_val(&*self.data.borrow())
+    }
+
+    /// Returns a copy of the value if it is a `T`.
+    pub fn get<T: TrayClass>(&self) -> Option<T> {
+        let data = self.data.borrow();
+        let data: &dyn Any = &*data;
+        data.downcast_ref::<T>().cloned()
+    }
+
+    /// Calls `f` with the value if it is a `T`.
+    fn with_mut<T: TrayClass, R>(&self, f: impl FnOnce(&mut T) -> R) -> Option<R> {
+        let mut data = self.data.borrow_mut();
+        let data: &mut dyn Any = &mut *data;
+        data.downcast_mut::<T>().map(f)
+    }
+}
+
+impl Display for Object {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self.data.borrow())
+    }
+}
+
+/// What a native function can return: a value, or a `Result` whose error becomes a tray
+/// [`RuntimeError::NativeError`].
+pub trait NativeResult {
+    fn return_type() -> Type;
+    fn into_result(self) -> Result<Value, RuntimeError>;
+}
+
+impl<T: IntoTray + TrayType> NativeResult for T {
+    fn return_type() -> Type {
+        T::tray_type()
+    }
+
+    fn into_result(self) -> Result<Value, RuntimeError> {
+        Ok(self.into_tray())
+    }
+}
+
+impl<T: IntoTray + TrayType, E: Display> NativeResult for Result<T, E> {
+    fn return_type() -> Type {
+        T::tray_type()
+    }
+
+    fn into_result(self) -> Result<Value, RuntimeError> {
+        self.map(IntoTray::into_tray)
+            .map_err(|err| RuntimeError::NativeError {
+                code: &error_codes::NATIVE_ERROR,
+                message: err.to_string(),
+                trace: vec![],
+            })
+    }
+}
+
+type Function = Box<dyn Fn(Vec<Value>) -> Result<Value, RuntimeError>>;
+
+/// A Rust function callable from scripts. Methods and fields take the object as an extra first
+/// argument, which isn't part of `parameters`.
+pub struct NativeFunction {
+    pub name: String,
+    pub parameters: Vec<Type>,
+    pub return_type: Type,
+    /// What the function needs to be allowed to do to be called by scripts.
+    pub capability: Option<Capability>,
+    function: Function,
+}
+
+impl Debug for NativeFunction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("NativeFunction")
+            .field("name", &self.name)
+            .field("parameters", &self.parameters)
+            .field("return_type", &self.return_type)
+            .field("capability", &self.capability)
+            .finish_non_exhaustive()
+    }
+}
+
+impl NativeFunction {
+    /// Calls the function, the arguments having been checked to be as many as its parameters.
+    pub fn call(&self, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+        (self.function)(arguments)
+    }
+
+    pub fn wrong_argument_count(&self, given: usize) -> RuntimeError {
+        let plural = if self.parameters.len() == 1 { "" } else { "s" };
+        let verb = if given == 1 { "was" } else { "were" };
+        RuntimeError::TypeError {
+            code: &error_codes::WRONG_ARGUMENT_COUNT,
+            message: format!(
+                "`{}` takes {} argument{plural} but {given} {verb} given.",
+                self.name,
+                self.parameters.len()
+            ),
+        }
+    }
+}
+
+/// A Rust closure that can be made into a [`NativeFunction`], implemented for closures taking up
+/// to 4 arguments that implement [`FromTray`] and [`TrayType`].
+pub trait IntoNativeFunction<Arguments> {
+    fn into_native_function(self, name: &str) -> NativeFunction;
+}
+
+/// A Rust closure that can be made into a method of the class `T`, taking the object as
+/// `&mut T` followed by up to 4 arguments.
+pub trait IntoNativeMethod<T, Arguments> {
+    fn into_native_method(self, name: &str) -> NativeFunction;
+}
+
+fn receiver_error<T: TrayClass>(name: &str, receiver: &Value) -> RuntimeError {
+    RuntimeError::TypeError {
+        code: &error_codes::MISMATCHED_TYPES,
+        message: format!(
+            "`{name}` was called on a value of type `{}` instead of `{}`.",
+            receiver.value_type(),
+            T::NAME
+        ),
+    }
+}
+
+/// Implements [`IntoNativeFunction`] and [`IntoNativeMethod`] for closures taking the argument
+/// types `$argument`, which also name the variables holding the converted arguments.
+macro_rules! native_function {
+    ($($argument:ident),*) => {
+        #[allow(non_snake_case, unused_mut, unused_variables)]
+        impl<F, R, $($argument),*> IntoNativeFunction<($($argument,)*)> for F
+        where
+            F: Fn($($argument),*) -> R + 'static,
+            R: NativeResult,
+            $($argument: FromTray + TrayType),*
+        {
+            fn into_native_function(self, name: &str) -> NativeFunction {
+                NativeFunction {
+                    name: name.to_string(),
+                    parameters: vec![$($argument::tray_type()),*],
+                    return_type: R::return_type(),
+                    capability: None,
+                    function: Box::new(move |arguments| {
+                        let mut arguments = arguments.into_iter();
+                        $(let $argument = $argument::from_tray(arguments.next().unwrap())?;)*
+                        self($($argument),*).into_result()
+                    }),
+                }
+            }
+        }
+
+        #[allow(non_snake_case, unused_mut, unused_variables)]
+        impl<T, F, R, $($argument),*> IntoNativeMethod<T, ($($argument,)*)> for F
+        where
+            T: TrayClass,
+            F: Fn(&mut T, $($argument),*) -> R + 'static,
+            R: NativeResult,
+            $($argument: FromTray + TrayType),*
+        {
+            fn into_native_method(self, name: &str) -> NativeFunction {
+                let method_name = name.to_string();
+                NativeFunction {
+                    name: name.to_string(),
+                    parameters: vec![$($argument::tray_type()),*],
+                    return_type: R::return_type(),
+                    capability: None,
+                    function: Box::new(move |arguments| {
+                        let mut arguments = arguments.into_iter();
+                        let receiver = arguments.next().unwrap();
+                        // The arguments are converted first, as they may be copies of the
+                        // receiver which is borrowed mutably during the call.
+                        $(let $argument = $argument::from_tray(arguments.next().unwrap())?;)*
+                        let Value::Object(object) = &receiver else {
+                            return Err(receiver_error::<T>(&method_name, &receiver));
+                        };
+                        object
+                            .with_mut(|this: &mut T| self(this, $($argument),*))
+                            .ok_or_else(|| receiver_error::<T>(&method_name, &receiver))?
+                            .into_result()
+                    }),
+                }
+            }
+        }
+    };
+}
+
+native_function!();
+native_function!(A);
+native_function!(A, B);
+native_function!(A, B, C);
+native_function!(A, B, C, D);
+
+/// The fields, methods and static members of a class registered by the program embedding tray.
+#[derive(Debug)]
+pub struct Class {
+    pub name: &'static str,
+    pub fields: HashMap<String, NativeFunction>,
+    pub methods: HashMap<String, NativeFunction>,
+    pub functions: HashMap<String, NativeFunction>,
+    pub constants: HashMap<String, Value>,
+}
+
+/// Collects the members of the class `T` in [`TrayClass::register`].
+pub struct ClassBuilder<T> {
+    class: Class,
+    marker: std::marker::PhantomData<T>,
+}
+
+impl<T: TrayClass> ClassBuilder<T> {
+    pub(crate) fn build() -> Class {
+        let mut builder = ClassBuilder {
+            class: Class {
+                name: T::NAME,
+                fields: HashMap::new(),
+                methods: HashMap::new(),
+                functions: HashMap::new(),
+                constants: HashMap::new(),
+            },
+            marker: std::marker::PhantomData,
+        };
+        T::register(&mut builder);
+        builder.class
+    }
+
+    /// Adds a read-only field, `object.<name>`, whose value is returned by `getter`.
+    pub fn field<R>(&mut self, name: &str, getter: impl Fn(&T) -> R + 'static) -> &mut Self
+    where
+        R: IntoTray + TrayType,
+    {
+        let method = move |this: &mut T| getter(this);
+        let method = method.into_native_method(&format!("{}.{name}", T::NAME));
+        self.class.fields.insert(name.to_string(), method);
+        self
+    }
+
+    /// Adds a method, `object.<name>(<arguments>)`.
+    pub fn method<Arguments>(
+        &mut self,
+        name: &str,
+        method: impl IntoNativeMethod<T, Arguments>,
+    ) -> &mut Self {
+        let method = method.into_native_method(&format!("{}.{name}", T::NAME));
+        self.class.methods.insert(name.to_string(), method);
+        self
+    }
+
+    /// Adds a static function, `Class::<name>(<arguments>)`.
+    pub fn function<Arguments>(
+        &mut self,
+        name: &str,
+        function: impl IntoNativeFunction<Arguments>,
+    ) -> &mut Self {
+        let function = function.into_native_function(&format!("{}::{name}", T::NAME));
+        self.class.functions.insert(name.to_string(), function);
+        self
+    }
+
+    /// Adds a constant, `Class::<name>`.
+    pub fn constant(&mut self, name: &str, value: impl IntoTray) -> &mut Self {
+        self.class
+            .constants
+            .insert(name.to_string(), value.into_tray());
+        self
+    }
+}
@@ -0,0 +1,439 @@
+use crate::{
+    checker,
+    executer::{self, Environment, RuntimeError},
+    parser::{BinaryOperator, Expression, NumberType, Statement, Type, UnaryOperator},
+};
+
+/// Optimizes `statement`, whose variables have the types they have in `environment`:
+///
+/// - parentheses are removed, as the tree already groups the operands;
+/// - operations on constants are computed with the checked arithmetic of the executer, so an
+///   overflow or a division by zero is reported before anything runs;
+/// - operations giving back their operand unchanged, like `x * 1`, are removed when they don't
+///   change its type either.
+///
+/// Type errors are left in the statement for the type checker or the executer to report, so it
+/// doesn't have to be checked first.
+pub fn optimize_statement(
+    statement: Statement,
+    environment: &Environment,
+) -> Result<Statement, RuntimeError> {
+    Ok(match statement {
+        Statement::Declaration {
+            declared_type,
+            mutable,
+            name,
+            value,
+        } => Statement::Declaration {
+            declared_type,
+            mutable,
+            name,
+            value: optimize(value, environment)?,
+        },
+        Statement::Assignment { name, value } => Statement::Assignment {
+            name,
+            value: optimize(value, environment)?,
+        },
+        Statement::IndexAssignment {
+            object,
+            index,
+            value,
+        } => Statement::IndexAssignment {
+            object: optimize(object, environment)?,
+            index: optimize(index, environment)?,
+            value: optimize(value, environment)?,
+        },
+        Statement::For {
+            variable,
+            iterable,
+            body,
+        } => {
+            // The statements of the body see the variable of the loop and the variables declared
+            // before them in the body.
+            let iterable = optimize(iterable, environment)?;
+            let mut scope = environment.clone();
+            if let Ok(Type::Vec(element)) = checker::static_type(&iterable, environment) {
+                scope.declare_unset(&variable, *element, false);
+            }
+            let mut optimized = vec![];
+            for (start, statement) in body {
+                let statement = optimize_statement(statement, &scope)?;
+                if let Statement::Declaration {
+                    declared_type,
+                    mutable,
+                    name,
+                    value,
+                } = &statement
+                {
+                    let declared_type = declared_type
+                        .clone()
+                        .or_else(|| checker::static_type(value, &scope).ok());
+                    if let Some(declared_type) = declared_type {
+                        scope.declare_unset(name, declared_type, *mutable);
+                    }
+                }
+                optimized.push((start, statement));
+            }
+            Statement::For {
+                variable,
+                iterable,
+                body: optimized,
+            }
+        }
+        Statement::Expression(expression) => {
+            Statement::Expression(optimize(expression, environment)?)
+        }
+    })
+}
+
+/// Optimizes `expression` after its operands, like [`optimize_statement`].
+pub fn optimize(
+    expression: Expression,
+    environment: &Environment,
+) -> Result<Expression, RuntimeError> {
+    let operand = |expr: Box<Expression>| optimize(*expr, environment).map(Box::new);
+    let arguments = |arguments: Vec<Expression>| {
+        arguments
+            .into_iter()
+            .map(|argument| optimize(argument, environment))
+            .collect::<Result<Vec<_>, _>>()
+    };
+    let expression = match expression {
+        Expression::ParenthesisExpression(expr) => return optimize(*expr, environment),
+        Expression::UnaryOperation { operator, expr } => Expression::UnaryOperation {
+            operator,
+            expr: operand(expr)?,
+        },
+        Expression::BinaryOperations { first, operations } => {
+            let operations = operations
+                .into_iter()
+                .map(|(operator, operand)| Ok((operator, optimize(operand, environment)?)))
+                .collect::<Result<Vec<_>, _>>()?;
+            // A chain starting with another one, whose parentheses were removed, is merged with
+            // it, as its operations are applied first either way.
+            match optimize(*first, environment)? {
+                Expression::BinaryOperations {
+                    first,
+                    operations: mut first_operations,
+                } => {
+                    first_operations.extend(operations);
+                    Expression::BinaryOperations {
+                        first,
+                        operations: first_operations,
+                    }
+                }
+                first => Expression::BinaryOperations {
+                    first: Box::new(first),
+                    operations,
+                },
+            }
+        }
+        Expression::Cast { expr, target } => Expression::Cast {
+            expr: operand(expr)?,
+            target,
+        },
+        Expression::Call {
+            function,
+            arguments: call_arguments,
+        } => Expression::Call {
+            function,
+            arguments: arguments(call_arguments)?,
+        },
+        Expression::Field { object, field } => Expression::Field {
+            object: operand(object)?,
+            field,
+        },
+        Expression::MethodCall {
+            object,
+            method,
+            arguments: call_arguments,
+        } => {
+            let object = operand(object)?;
+            let arguments = match <[Expression; 1]>::try_from(call_arguments) {
+                Ok(
+                    [Expression::Lambda {
+                        parameter,
+                        body,
+                        result_type,
+                    }],
+                ) => vec![Expression::Lambda {
+                    body: optimize_lambda(&object, &parameter, body, environment)?,
+                    parameter,
+                    result_type,
+                }],
+                Ok(argument) => arguments(Vec::from(argument))?,
+                Err(call_arguments) => arguments(call_arguments)?,
+            };
+            Expression::MethodCall {
+                object,
+                method,
+                arguments,
+            }
+        }
+        Expression::StaticCall {
+            class,
+            function,
+            arguments: call_arguments,
+        } => Expression::StaticCall {
+            class,
+            function,
+            arguments: arguments(call_arguments)?,
+        },
+        Expression::Vec {
+            elements,
+            element_type,
+        } => Expression::Vec {
+            elements: arguments(elements)?,
+            element_type,
+        },
+        Expression::Index { object, index } => Expression::Index {
+            object: operand(object)?,
+            index: operand(index)?,
+        },
+        expression => expression,
+    };
+    let expression = fold(expression, environment.promote_overflow)?;
+    Ok(simplify(expression, environment))
+}
+
+/// Optimizes the body of a lambda taking `parameter`, passed to a method of `object`. It is left
+/// as it is when `object` isn't a `Vec`, as the type of the parameter isn't known then.
+fn optimize_lambda(
+    object: &Expression,
+    parameter: &str,
+    body: Box<Expression>,
+    environment: &Environment,
+) -> Result<Box<Expression>, RuntimeError> {
+    let Ok(Type::Vec(element)) = checker::static_type(object, environment) else {
+        return Ok(body);
+    };
+    let mut scope = environment.clone();
+    scope.declare_unset(parameter, *element, false);
+    optimize(*body, &scope).map(Box::new)
+}
+
+/// Computes an operation whose operands are constants. Operations failing with a type error
+/// are given back as they are, for it to be reported with the other type errors. Overflows are
+/// promoted to `bigint` like at runtime when `promote_overflow` is enabled.
+fn fold(expression: Expression, promote_overflow: bool) -> Result<Expression, RuntimeError> {
+    let result = match &expression {
+        Expression::UnaryOperation { operator, expr } => match (operator, &**expr) {
+            (UnaryOperator::Plus, Expression::Number(number)) => Ok(number.clone()),
+            (UnaryOperator::Minus, Expression::Number(number)) => {
+                executer::negate(number, promote_overflow)
+            }
+            _ => return Ok(expression),
+        },
+        Expression::BinaryOperations { .. } => {
+            return fold_operations(expression, promote_overflow)
+        }
+        Expression::Cast { expr, target } => match &**expr {
+            Expression::Number(number) => Ok(number.cast(*target)),
+            _ => return Ok(expression),
+        },
+        _ => return Ok(expression),
+    };
+    match result {
+        Ok(number) => Ok(Expression::Number(number)),
+        Err(RuntimeError::TypeError { .. }) => Ok(expression),
+        Err(err) => Err(err),
+    }
+}
+
+/// Computes the operations at the start of a chain for as long as their operands are constants,
+/// like [`fold`].
+fn fold_operations(
+    expression: Expression,
+    promote_overflow: bool,
+) -> Result<Expression, RuntimeError> {
+    let Expression::BinaryOperations {
+        mut first,
+        operations,
+    } = expression
+    else {
+        return Ok(expression);
+    };
+    let mut operations = operations.into_iter().peekable();
+    while let (Expression::Number(left), Some((operator, Expression::Number(right)))) =
+        (&*first, operations.peek())
+    {
+        match executer::binary_operation(operator, left, right, promote_overflow) {
+            Ok(number) => *first = Expression::Number(number),
+            Err(RuntimeError::TypeError { .. }) => break,
+            Err(err) => return Err(err),
+        }
+        operations.next();
+    }
+    Ok(chain(*first, operations.collect()))
+}
+
+/// Makes the chain of `operations` applied to `first`, which is `first` itself without any.
+fn chain(first: Expression, operations: Vec<(BinaryOperator, Expression)>) -> Expression {
+    if operations.is_empty() {
+        return first;
+    }
+    Expression::BinaryOperations {
+        first: Box::new(first),
+        operations,
+    }
+}
+
+/// Whether `expression` is a zero, which isn't `-0.0` as `x - -0.0` is `0.0` for `x = -0.0`.
+fn is_zero(expression: &Expression) -> bool {
+    matches!(expression, Expression::Number(number)
+        if number.is_zero() && number.as_f64().is_sign_positive())
+}
+
+fn is_one(expression: &Expression) -> bool {
+    matches!(expression, Expression::Number(number) if number.as_f64() == 1.0)
+}
+
+/// Replaces an operation giving back its operand unchanged by the operand, if they have the
+/// same type.
+///
+/// Only the identities which are exact for floats are used for them: `x + 0.0` is `0.0` for
+/// `x = -0.0`. `-(-x)` is only simplified for floats, as it overflows for the minimum value of
+/// signed integers, and `decimal` results are left alone as their scale depends on both operands.
+fn simplify(expression: Expression, environment: &Environment) -> Expression {
+    if let Expression::BinaryOperations { first, operations } = expression {
+        return simplify_operations(*first, operations, environment);
+    }
+    let Ok(Type::Number(result_type)) = checker::static_type(&expression, environment) else {
+        return expression;
+    };
+    if result_type == NumberType::Decimal {
+        return expression;
+    }
+    let float = result_type.is_floating_point();
+    let operand = match &expression {
+        Expression::UnaryOperation {
+            operator: UnaryOperator::Plus,
+            expr,
+        } => Some(&**expr),
+        Expression::UnaryOperation {
+            operator: UnaryOperator::Minus,
+            expr,
+        } => match &**expr {
+            Expression::UnaryOperation {
+                operator: UnaryOperator::Minus,
+                expr,
+            } if float => Some(&**expr),
+            _ => None,
+        },
+        _ => None,
+    };
+    match operand {
+        Some(operand)
+            if checker::static_type(operand, environment).ok()
+                == Some(Type::Number(result_type)) =>
+        {
+            operand.clone()
+        }
+        _ => expression,
+    }
+}
+
+/// Simplifies the operations of a chain like [`simplify`], from left to right. An operation
+/// giving back the result so far is removed, and so is `first` when it is the left operand of an
+/// operation giving back its right operand.
+fn simplify_operations(
+    mut first: Expression,
+    operations: Vec<(BinaryOperator, Expression)>,
+    environment: &Environment,
+) -> Expression {
+    let number_type = |expression: &Expression| match checker::static_type(expression, environment)
+    {
+        Ok(Type::Number(number_type)) => Some(number_type),
+        _ => None,
+    };
+    let mut left_type = number_type(&first);
+    let mut kept = vec![];
+    for (operator, operand) in operations {
+        let operand_type = number_type(&operand);
+        let result_type = left_type
+            .zip(operand_type)
+            .and_then(|(left, right)| executer::promote(left, right, &operator).ok());
+        let Some(result_type) = result_type.filter(|&result| result != NumberType::Decimal) else {
+            left_type = result_type;
+            kept.push((operator, operand));
+            continue;
+        };
+        let float = result_type.is_floating_point();
+        let gives_left = match operator {
+            BinaryOperator::Multiply | BinaryOperator::Divide => is_one(&operand),
+            BinaryOperator::Minus => is_zero(&operand),
+            BinaryOperator::Plus => !float && is_zero(&operand),
+        };
+        let gives_operand = kept.is_empty()
+            && match operator {
+                BinaryOperator::Multiply => is_one(&first),
+                BinaryOperator::Plus => !float && is_zero(&first),
+                _ => false,
+            };
+        if gives_left && left_type == Some(result_type) {
+            // The operation is removed, which doesn't change the result so far.
+            continue;
+        }
+        if gives_operand && operand_type == Some(result_type) {
+            first = operand;
+        } else {
+            kept.push((operator, operand));
+        }
+        left_type = Some(result_type);
+    }
+    chain(first, kept)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{formatter, Interpreter};
+
+    /// Compiles `source`, returning its optimized statements formatted one per line.
+    fn optimized(source: &str) -> Vec<String> {
+        let statements = Interpreter::new().compile(source).unwrap();
+        statements.iter().map(formatter::format_statement).collect()
+    }
+
+    /// Compiles `source`, which must fail, returning the code of its error and the part of the
+    /// source it points at.
+    fn error(source: &str) -> (&'static str, &str) {
+        let diagnostics = Interpreter::new().compile(source).unwrap_err();
+        assert_eq!(diagnostics.len(), 1, "{source}");
+        let span = diagnostics[0].span.clone().unwrap();
+        (diagnostics[0].code.code, &source[span])
+    }
+
+    #[test]
+    fn keeps_float_identities_that_change_negative_zero() {
+        let source = "f64 x = 1f64; x + 0.0f64; 0.0f64 + x; x - -0.0f64; x - 0.0f64; x * 1f64;";
+        assert_eq!(
+            optimized(source)[1..],
+            ["x + 0.0f64;", "0.0f64 + x;", "x - -0.0f64;", "x;", "x;"]
+        );
+    }
+
+    #[test]
+    fn removes_integer_identities() {
+        let source = "i32 a = 1; a + 0; 0 + a; a - 0; a * 1; 1 * a; a / 1;";
+        assert_eq!(optimized(source)[1..], ["a;"; 6]);
+        // The result of `b + 0i16` has another type than `b`.
+        assert_eq!(optimized("i8 b = 1i8; b + 0i16;")[1], "b + 0i16;");
+    }
+
+    #[test]
+    fn merges_parenthesized_chains_starting_another_one() {
+        let source = "i32 a = 1; (a - 1) - 2; (a * 2) + 3; (a + 2) * 3; a - (1 - 2); (2 + 3) * 4;";
+        assert_eq!(
+            optimized(source)[1..],
+            ["a - 1 - 2;", "a * 2 + 3;", "(a + 2) * 3;", "a - -1;", "20;"]
+        );
+    }
+
+    #[test]
+    fn reports_failing_constant_operations_before_running() {
+        assert_eq!(error("200u8 + 100u8;"), ("T0025", "200u8 + 100u8"));
+        assert_eq!(error("i32 a = 1; a + 2147483647i32 * 2i32;").0, "T0025");
+        assert_eq!(error("-(-128i8);"), ("T0025", "-(-128i8)"));
+        assert_eq!(error("i32 a = 1; a * (1 / 0);"), ("T0024", "1 / 0"));
+    }
+}
@@ -1,9 +1,18 @@
 use std::{
+    cell::Cell,
+    fmt::Display,
     iter::{Enumerate, Peekable},
+    ops::Range,
     slice::Iter,
 };
 
-use crate::lexer::{Token, TokenList};
+use crate::{
+    bigint::BigInt,
+    decimal::Decimal,
+    diagnostic::{Diagnostic, Suggestion},
+    error_codes::{self, ErrorCode},
+    lexer::{Token, TokenList},
+};
 
 #[derive(Debug, Clone)]
 pub enum UnaryOperator {
@@ -11,7 +20,7 @@ pub enum UnaryOperator {
     Minus,
 }
 
-#[derive(Debug, PartialEq, PartialOrd, Eq, Clone)]
+#[derive(Debug, PartialEq, Eq, Clone)]
 pub enum BinaryOperator {
     Plus,
     Minus,
@@ -22,17 +31,171 @@ pub enum BinaryOperator {
 impl BinaryOperator {
     pub fn priority(&self) -> u8 {
         match self {
-            BinaryOperator::Plus => 1,
-            BinaryOperator::Minus => 0,
-            BinaryOperator::Divide => 2,
-            BinaryOperator::Multiply => 3,
+            BinaryOperator::Plus | BinaryOperator::Minus => 1,
+            BinaryOperator::Divide | BinaryOperator::Multiply => 2,
+        }
+    }
+
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            BinaryOperator::Plus => "+",
+            BinaryOperator::Minus => "-",
+            BinaryOperator::Divide => "/",
+            BinaryOperator::Multiply => "*",
+        }
+    }
+
+    fn from_token(token: &Token) -> Option<Self> {
+        match token {
+            Token::Plus => Some(BinaryOperator::Plus),
+            Token::Minus => Some(BinaryOperator::Minus),
+            Token::Multiply => Some(BinaryOperator::Multiply),
+            Token::Divide => Some(BinaryOperator::Divide),
+            _ => None,
+        }
+    }
+}
+
+/// The type of a [`Number`], as written after `as` in a cast expression.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NumberType {
+    Float32,
+    Float64,
+    Int8,
+    Int16,
+    Int32,
+    Int64,
+    Int128,
+    UInt8,
+    UInt16,
+    UInt32,
+    UInt64,
+    UInt128,
+    BigInt,
+    Decimal,
+}
+
+impl NumberType {
+    /// Resolves a type name such as `u8` or `f64`. `int` and `byte` are accepted as aliases for
+    /// `i32` and `u8`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "f32" => Some(NumberType::Float32),
+            "f64" => Some(NumberType::Float64),
+            "i8" => Some(NumberType::Int8),
+            "i16" => Some(NumberType::Int16),
+            "i32" | "int" => Some(NumberType::Int32),
+            "i64" => Some(NumberType::Int64),
+            "i128" => Some(NumberType::Int128),
+            "u8" | "byte" => Some(NumberType::UInt8),
+            "u16" => Some(NumberType::UInt16),
+            "u32" => Some(NumberType::UInt32),
+            "u64" => Some(NumberType::UInt64),
+            "u128" => Some(NumberType::UInt128),
+            "bigint" => Some(NumberType::BigInt),
+            "decimal" => Some(NumberType::Decimal),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            NumberType::Float32 => "f32",
+            NumberType::Float64 => "f64",
+            NumberType::Int8 => "i8",
+            NumberType::Int16 => "i16",
+            NumberType::Int32 => "i32",
+            NumberType::Int64 => "i64",
+            NumberType::Int128 => "i128",
+            NumberType::UInt8 => "u8",
+            NumberType::UInt16 => "u16",
+            NumberType::UInt32 => "u32",
+            NumberType::UInt64 => "u64",
+            NumberType::UInt128 => "u128",
+            NumberType::BigInt => "bigint",
+            NumberType::Decimal => "decimal",
+        }
+    }
+
+    /// The width of the type. `bigint` and `decimal` are unbounded and report `u32::MAX`.
+    pub fn bits(&self) -> u32 {
+        match self {
+            NumberType::Int8 | NumberType::UInt8 => 8,
+            NumberType::Int16 | NumberType::UInt16 => 16,
+            NumberType::Float32 | NumberType::Int32 | NumberType::UInt32 => 32,
+            NumberType::Float64 | NumberType::Int64 | NumberType::UInt64 => 64,
+            NumberType::Int128 | NumberType::UInt128 => 128,
+            NumberType::BigInt | NumberType::Decimal => u32::MAX,
         }
     }
+
+    pub fn is_floating_point(&self) -> bool {
+        matches!(self, NumberType::Float32 | NumberType::Float64)
+    }
+
+    pub fn is_signed(&self) -> bool {
+        matches!(
+            self,
+            NumberType::Int8
+                | NumberType::Int16
+                | NumberType::Int32
+                | NumberType::Int64
+                | NumberType::Int128
+                | NumberType::BigInt
+                | NumberType::Decimal
+        )
+    }
 }
 
-impl Ord for BinaryOperator {
-    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        self.priority().cmp(&other.priority())
+impl Display for NumberType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// A type that can be written in a declaration.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Type {
+    Number(NumberType),
+    Bool,
+    Char,
+    String,
+    /// A Rust type registered as a class by the program embedding tray.
+    Class(String),
+    /// `Vec<T>`
+    Vec(Box<Type>),
+}
+
+impl Type {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "bool" => Some(Type::Bool),
+            "char" => Some(Type::Char),
+            "String" => Some(Type::String),
+            _ => NumberType::from_name(name).map(Type::Number),
+        }
+    }
+}
+
+/// Every name accepted by [`Type::from_name`].
+pub const TYPE_NAMES: &[&str] = &[
+    "f32", "f64", "i8", "i16", "i32", "int", "i64", "i128", "u8", "byte", "u16", "u32", "u64",
+    "u128", "bigint", "decimal", "bool", "char", "String",
+];
+
+/// The generic types, with the names of their type parameters.
+pub const GENERIC_TYPES: &[(&str, &[&str])] = &[("Vec", &["T"])];
+
+impl Display for Type {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Type::Number(number_type) => write!(f, "{number_type}"),
+            Type::Bool => f.write_str("bool"),
+            Type::Char => f.write_str("char"),
+            Type::String => f.write_str("String"),
+            Type::Class(name) => f.write_str(name),
+            Type::Vec(element) => write!(f, "Vec<{element}>"),
+        }
     }
 }
 
@@ -40,90 +203,326 @@ impl Ord for BinaryOperator {
 pub enum Number {
     Float32(f32),
     Float64(f64),
+    Int8(i8),
+    Int16(i16),
     Int32(i32),
     Int64(i64),
     Int128(i128),
+    UInt8(u8),
+    UInt16(u16),
+    UInt32(u32),
+    UInt64(u64),
+    UInt128(u128),
+    BigInt(BigInt),
+    Decimal(Decimal),
 }
 
-#[allow(dead_code)]
+/// Converts any [`Number`] variant to `$type` with the semantics of Rust's `as`.
+macro_rules! number_as {
+    ($number:expr, $type:ty) => {
+        match $number {
+            Number::Float32(v) => *v as $type,
+            Number::Float64(v) => *v as $type,
+            Number::Int8(v) => *v as $type,
+            Number::Int16(v) => *v as $type,
+            Number::Int32(v) => *v as $type,
+            Number::Int64(v) => *v as $type,
+            Number::Int128(v) => *v as $type,
+            Number::UInt8(v) => *v as $type,
+            Number::UInt16(v) => *v as $type,
+            Number::UInt32(v) => *v as $type,
+            Number::UInt64(v) => *v as $type,
+            Number::UInt128(v) => *v as $type,
+            Number::BigInt(v) => v.to_i128_wrapping() as $type,
+            Number::Decimal(v) => v.truncate().to_i128_wrapping() as $type,
+        }
+    };
+}
+
+#[allow(dead_code, clippy::unnecessary_cast)]
 impl Number {
     pub fn as_f64(&self) -> f64 {
         match self {
-            Number::Float32(v) => *v as f64,
-            Number::Float64(v) => *v as f64,
-            Number::Int32(v) => *v as f64,
-            Number::Int64(v) => *v as f64,
-            Number::Int128(v) => *v as f64,
+            Number::BigInt(v) => v.to_f64(),
+            Number::Decimal(v) => v.to_f64(),
+            _ => number_as!(self, f64),
         }
     }
 
     pub fn as_f32(&self) -> f32 {
         match self {
-            Number::Float32(v) => *v as f32,
-            Number::Float64(v) => *v as f32,
-            Number::Int32(v) => *v as f32,
-            Number::Int64(v) => *v as f32,
-            Number::Int128(v) => *v as f32,
+            Number::BigInt(_) | Number::Decimal(_) => self.as_f64() as f32,
+            _ => number_as!(self, f32),
         }
     }
 
     pub fn as_i32(&self) -> i32 {
+        number_as!(self, i32)
+    }
+
+    pub fn as_i64(&self) -> i64 {
+        number_as!(self, i64)
+    }
+
+    pub fn as_i128(&self) -> i128 {
+        number_as!(self, i128)
+    }
+
+    pub fn as_u128(&self) -> u128 {
+        number_as!(self, u128)
+    }
+
+    pub fn to_bigint(&self) -> BigInt {
         match self {
-            Number::Float32(v) => *v as i32,
-            Number::Float64(v) => *v as i32,
-            Number::Int32(v) => *v as i32,
-            Number::Int64(v) => *v as i32,
-            Number::Int128(v) => *v as i32,
+            Number::BigInt(v) => v.clone(),
+            Number::Decimal(v) => v.truncate(),
+            Number::Float32(_) | Number::Float64(_) => BigInt::from_f64(self.as_f64()),
+            _ if self.number_type().is_signed() => BigInt::from_i128(self.as_i128()),
+            _ => BigInt::from_u128(self.as_u128()),
         }
     }
 
-    pub fn as_i64(&self) -> i64 {
+    pub fn to_decimal(&self) -> Decimal {
         match self {
-            Number::Float32(v) => *v as i64,
-            Number::Float64(v) => *v as i64,
-            Number::Int32(v) => *v as i64,
-            Number::Int64(v) => *v as i64,
-            Number::Int128(v) => *v as i64,
+            Number::Decimal(v) => v.clone(),
+            Number::Float32(_) | Number::Float64(_) => Decimal::from_f64(self.as_f64()),
+            _ => Decimal::from(self.to_bigint()),
         }
     }
 
-    pub fn as_i128(&self) -> i128 {
+    /// Converts the number to `target` only if no information is lost, so `200` converts to
+    /// `u8` but `300`, `-1` and `1.5` don't.
+    pub fn convert_lossless(&self, target: NumberType) -> Option<Number> {
+        let number_type = self.number_type();
+        if number_type == target {
+            return Some(self.clone());
+        }
+        let converted = self.cast(target);
+        let round_trip = converted.cast(number_type);
+        // Fixed-width integers of the same type are equal if their bits are, which spares
+        // building decimals.
+        let lossless = if number_type.is_floating_point() || number_type.bits() > 128 {
+            round_trip.to_decimal() == self.to_decimal()
+        } else {
+            round_trip.as_i128() == self.as_i128()
+        };
+        if lossless {
+            Some(converted)
+        } else {
+            None
+        }
+    }
+
+    pub fn is_zero(&self) -> bool {
         match self {
-            Number::Float32(v) => *v as i128,
-            Number::Float64(v) => *v as i128,
-            Number::Int32(v) => *v as i128,
-            Number::Int64(v) => *v as i128,
-            Number::Int128(v) => *v as i128,
+            Number::BigInt(v) => v.is_zero(),
+            Number::Decimal(v) => v.is_zero(),
+            _ => self.as_f64() == 0.,
         }
     }
 
+    pub fn number_type(&self) -> NumberType {
+        match self {
+            Number::Float32(_) => NumberType::Float32,
+            Number::Float64(_) => NumberType::Float64,
+            Number::Int8(_) => NumberType::Int8,
+            Number::Int16(_) => NumberType::Int16,
+            Number::Int32(_) => NumberType::Int32,
+            Number::Int64(_) => NumberType::Int64,
+            Number::Int128(_) => NumberType::Int128,
+            Number::UInt8(_) => NumberType::UInt8,
+            Number::UInt16(_) => NumberType::UInt16,
+            Number::UInt32(_) => NumberType::UInt32,
+            Number::UInt64(_) => NumberType::UInt64,
+            Number::UInt128(_) => NumberType::UInt128,
+            Number::BigInt(_) => NumberType::BigInt,
+            Number::Decimal(_) => NumberType::Decimal,
+        }
+    }
+
+    /// Converts the number to `target` the way an explicit `as` cast does: integers wrap,
+    /// floats saturate when converted to integers, and `bigint` truncates toward zero.
+    pub fn cast(&self, target: NumberType) -> Number {
+        match target {
+            NumberType::Float32 => Number::Float32(self.as_f32()),
+            NumberType::Float64 => Number::Float64(self.as_f64()),
+            NumberType::Int8 => Number::Int8(number_as!(self, i8)),
+            NumberType::Int16 => Number::Int16(number_as!(self, i16)),
+            NumberType::Int32 => Number::Int32(number_as!(self, i32)),
+            NumberType::Int64 => Number::Int64(number_as!(self, i64)),
+            NumberType::Int128 => Number::Int128(number_as!(self, i128)),
+            NumberType::UInt8 => Number::UInt8(number_as!(self, u8)),
+            NumberType::UInt16 => Number::UInt16(number_as!(self, u16)),
+            NumberType::UInt32 => Number::UInt32(number_as!(self, u32)),
+            NumberType::UInt64 => Number::UInt64(number_as!(self, u64)),
+            NumberType::UInt128 => Number::UInt128(number_as!(self, u128)),
+            NumberType::BigInt => Number::BigInt(self.to_bigint()),
+            NumberType::Decimal => Number::Decimal(self.to_decimal()),
+        }
+    }
+
+    /// Narrows a signed intermediate result to `target`, or returns `None` if it doesn't fit.
+    pub fn from_i128(value: i128, target: NumberType) -> Option<Number> {
+        Some(match target {
+            NumberType::Int8 => Number::Int8(value.try_into().ok()?),
+            NumberType::Int16 => Number::Int16(value.try_into().ok()?),
+            NumberType::Int32 => Number::Int32(value.try_into().ok()?),
+            NumberType::Int64 => Number::Int64(value.try_into().ok()?),
+            NumberType::Int128 => Number::Int128(value),
+            NumberType::BigInt => Number::BigInt(BigInt::from_i128(value)),
+            NumberType::Decimal => Number::Decimal(Decimal::from(BigInt::from_i128(value))),
+            NumberType::Float32 => Number::Float32(value as f32),
+            NumberType::Float64 => Number::Float64(value as f64),
+            _ => Number::from_u128(value.try_into().ok()?, target)?,
+        })
+    }
+
+    /// Narrows an unsigned intermediate result to `target`, or returns `None` if it doesn't fit.
+    pub fn from_u128(value: u128, target: NumberType) -> Option<Number> {
+        Some(match target {
+            NumberType::UInt8 => Number::UInt8(value.try_into().ok()?),
+            NumberType::UInt16 => Number::UInt16(value.try_into().ok()?),
+            NumberType::UInt32 => Number::UInt32(value.try_into().ok()?),
+            NumberType::UInt64 => Number::UInt64(value.try_into().ok()?),
+            NumberType::UInt128 => Number::UInt128(value),
+            NumberType::BigInt => Number::BigInt(BigInt::from_u128(value)),
+            NumberType::Decimal => Number::Decimal(Decimal::from(BigInt::from_u128(value))),
+            NumberType::Float32 => Number::Float32(value as f32),
+            NumberType::Float64 => Number::Float64(value as f64),
+            _ => Number::from_i128(value.try_into().ok()?, target)?,
+        })
+    }
+
     pub fn is_floating_point(&self, other: &Self) -> bool {
-        if let (Number::Float32(_) | Number::Float64(_), _)
-        | (_, Number::Float32(_) | Number::Float64(_)) = (self, other)
-        {
-            true
-        } else {
-            false
+        self.number_type().is_floating_point() || other.number_type().is_floating_point()
+    }
+}
+
+impl Display for Number {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Number::Float32(v) => write!(f, "{v:?}"),
+            Number::Float64(v) => write!(f, "{v:?}"),
+            Number::Int8(v) => write!(f, "{v}"),
+            Number::Int16(v) => write!(f, "{v}"),
+            Number::Int32(v) => write!(f, "{v}"),
+            Number::Int64(v) => write!(f, "{v}"),
+            Number::Int128(v) => write!(f, "{v}"),
+            Number::UInt8(v) => write!(f, "{v}"),
+            Number::UInt16(v) => write!(f, "{v}"),
+            Number::UInt32(v) => write!(f, "{v}"),
+            Number::UInt64(v) => write!(f, "{v}"),
+            Number::UInt128(v) => write!(f, "{v}"),
+            Number::BigInt(v) => write!(f, "{v}"),
+            Number::Decimal(v) => write!(f, "{v}"),
         }
     }
 }
+
+#[allow(clippy::enum_variant_names)]
 #[derive(Debug, Clone)]
 pub enum Expression {
     UnaryOperation {
         operator: UnaryOperator,
         expr: Box<Expression>,
     },
-    BinaryOperation {
-        operator: BinaryOperator,
-        left: Box<Expression>,
-        right: Box<Expression>,
+    /// `<first> <operator> <operand> <operator> <operand> ...`, each operator being applied from
+    /// left to right to the result so far and its operand. Operators of a higher priority are
+    /// grouped in their operand, so `1 - 2 * 3 + 4` is `1`, `- 2 * 3` and `+ 4`.
+    ///
+    /// Chains are kept flat rather than nested in their left operand, so that a long one doesn't
+    /// nest deeply.
+    BinaryOperations {
+        first: Box<Expression>,
+        operations: Vec<(BinaryOperator, Expression)>,
+    },
+    Cast {
+        expr: Box<Expression>,
+        target: NumberType,
     },
     ParenthesisExpression(Box<Expression>),
+    /// `<function>(<arguments>)`
+    Call {
+        function: String,
+        arguments: Vec<Expression>,
+    },
+    /// `<object>.<field>`
+    Field {
+        object: Box<Expression>,
+        field: String,
+    },
+    /// `<object>.<method>(<arguments>)`
+    MethodCall {
+        object: Box<Expression>,
+        method: String,
+        arguments: Vec<Expression>,
+    },
+    /// `<class>::<member>`
+    StaticMember {
+        class: String,
+        member: String,
+    },
+    /// `<class>::<function>(<arguments>)`
+    StaticCall {
+        class: String,
+        function: String,
+        arguments: Vec<Expression>,
+    },
+    /// `[<elements>]`. The type checker fills in `element_type`, which otherwise is the type of
+    /// the first element.
+    Vec {
+        elements: Vec<Expression>,
+        element_type: Option<Type>,
+    },
+    /// `<object>[<index>]`
+    Index {
+        object: Box<Expression>,
+        index: Box<Expression>,
+    },
+    /// `<parameter> => <body>`, only allowed as the argument of the `map` and `filter` methods
+    /// of `Vec`. The type checker fills in `result_type`, the type of `body`.
+    Lambda {
+        parameter: String,
+        body: Box<Expression>,
+        result_type: Option<Type>,
+    },
+    Variable(String),
     Number(Number),
+    Bool(bool),
     Char(char),
     String(String),
 }
 
+#[derive(Debug, Clone)]
+pub enum Statement {
+    /// `<type> <name> = <value>`, or `let <name> = <value>` and `var <name> = <value>` when
+    /// `declared_type` is `None`, which take the type of their value. Only variables declared
+    /// with `let` are immutable.
+    Declaration {
+        declared_type: Option<Type>,
+        mutable: bool,
+        name: String,
+        value: Expression,
+    },
+    /// `<name> = <value>`
+    Assignment { name: String, value: Expression },
+    /// `<object>[<index>] = <value>`
+    IndexAssignment {
+        object: Expression,
+        index: Expression,
+        value: Expression,
+    },
+    /// `for <variable> in <iterable> { <body> }`, along with the index of the first token of
+    /// each statement of the body. The variable and the variables declared in the body only
+    /// exist until the end of the loop.
+    For {
+        variable: String,
+        iterable: Expression,
+        body: Vec<(usize, Statement)>,
+    },
+    Expression(Expression),
+}
+
 impl Expression {
     pub fn unwrap_content(&self) -> Expression {
         if let Self::ParenthesisExpression(expr) = self {
@@ -132,202 +531,913 @@ impl Expression {
             self.clone()
         }
     }
+
+    /// Returns how many expressions are nested in each other on the longest path from this one
+    /// to an operand, counting both.
+    pub fn depth(&self) -> usize {
+        let operands = match self {
+            Expression::UnaryOperation { expr, .. }
+            | Expression::Cast { expr, .. }
+            | Expression::ParenthesisExpression(expr) => vec![expr.as_ref()],
+            Expression::BinaryOperations { first, operations } => std::iter::once(first.as_ref())
+                .chain(operations.iter().map(|(_, operand)| operand))
+                .collect(),
+            Expression::Call { arguments, .. } | Expression::StaticCall { arguments, .. } => {
+                arguments.iter().collect()
+            }
+            Expression::Field { object, .. } | Expression::Lambda { body: object, .. } => {
+                vec![object.as_ref()]
+            }
+            Expression::MethodCall {
+                object, arguments, ..
+            } => std::iter::once(object.as_ref()).chain(arguments).collect(),
+            Expression::Vec { elements, .. } => elements.iter().collect(),
+            Expression::Index { object, index } => vec![object.as_ref(), index.as_ref()],
+            Expression::StaticMember { .. }
+            | Expression::Variable(_)
+            | Expression::Number(_)
+            | Expression::Bool(_)
+            | Expression::Char(_)
+            | Expression::String(_) => vec![],
+        };
+        let deepest = operands.into_iter().map(Expression::depth).max();
+        1 + deepest.unwrap_or(0)
+    }
 }
 
-fn default_parse(iterator: &mut Peekable<Enumerate<Iter<Token>>>) -> Option<Expression> {
-    if let Some((_index, token)) = iterator.next() {
-        Some(match token {
-            Token::Plus => parse_unary(iterator, UnaryOperator::Plus)?,
-            Token::Minus => parse_unary(iterator, UnaryOperator::Minus)?,
-            Token::Multiply => panic!(""),
-            Token::Divide => panic!(""),
-            Token::I32(value) => parse_number(iterator, Number::Int32(*value))?,
-            Token::I64(value) => parse_number(iterator, Number::Int64(*value))?,
-            Token::I128(value) => parse_number(iterator, Number::Int128(*value))?,
-            Token::F32(value) => parse_number(iterator, Number::Float32(*value))?,
-            Token::F64(value) => parse_number(iterator, Number::Float64(*value))?,
-            Token::String(value) => Expression::String(value.clone()),
-            Token::Char(value) => Expression::Char(*value),
-            Token::LParenthesis => parse_lparen(iterator)?,
-            Token::RParenthesis => panic!(""),
-        })
+type TokenIterator<'a> = Peekable<Enumerate<Iter<'a, Token>>>;
+
+#[derive(Debug)]
+pub struct ParseError {
+    /// The index of the token the error is about, or `None` if the input ended too early.
+    pub position: Option<usize>,
+    pub code: &'static ErrorCode,
+    pub message: String,
+    pub insertion: Option<Insertion>,
+}
+
+/// A fix for a parse error that inserts `text` right after the token before the one at index
+/// `before`, or after the last token if `before` is `None`.
+#[derive(Debug)]
+pub struct Insertion {
+    pub before: Option<usize>,
+    pub text: &'static str,
+    pub message: &'static str,
+}
+
+impl ParseError {
+    /// `spans` are the byte ranges of the tokens, as returned by the lexer.
+    pub fn to_diagnostic(&self, spans: &[Range<usize>]) -> Diagnostic {
+        let span = match self.position {
+            Some(index) => spans.get(index).cloned(),
+            None => spans.last().map(|span| span.end..span.end),
+        };
+        let mut diagnostic = Diagnostic::error(self.code, self.message.clone());
+        if let Some(span) = span {
+            diagnostic = diagnostic.with_span(span);
+        }
+        let insertion_point = self.insertion.as_ref().and_then(|insertion| {
+            let previous = match insertion.before {
+                Some(index) => spans.get(index.checked_sub(1)?),
+                None => spans.last(),
+            };
+            Some((previous?.end, insertion))
+        });
+        if let Some((position, insertion)) = insertion_point {
+            diagnostic = diagnostic
+                .with_secondary_label(
+                    position..position,
+                    &format!("expected `{}` here", insertion.text),
+                )
+                .with_suggestion(Suggestion::insert(
+                    position,
+                    insertion.text,
+                    insertion.message,
+                ));
+        }
+        diagnostic
+    }
+}
+
+type ParseResult<T> = Result<T, ParseError>;
+
+/// How deeply expressions, blocks and types can nest when no other limit is set. This bounds the
+/// recursion of the parser and of everything walking the statements it returns, so that no
+/// source can overflow their stack.
+pub const DEFAULT_MAX_NESTING: usize = 256;
+
+thread_local! {
+    /// How many operands the one being parsed is nested in.
+    static NESTING: Cell<usize> = const { Cell::new(0) };
+    /// How deeply the statements being parsed can nest.
+    static MAX_NESTING: Cell<usize> = const { Cell::new(DEFAULT_MAX_NESTING) };
+}
+
+/// Returns an error about the next token if the operand being parsed is nested in
+/// [`MAX_NESTING`] others already, `what` being what is nested.
+fn check_parser_nesting(iterator: &mut TokenIterator, what: &str) -> ParseResult<()> {
+    let max_nesting = MAX_NESTING.get();
+    if NESTING.get() >= max_nesting {
+        return Err(nested_too_deeply(iterator, what, max_nesting));
+    }
+    Ok(())
+}
+
+fn nested_too_deeply(iterator: &mut TokenIterator, what: &str, max_nesting: usize) -> ParseError {
+    error_at_next(
+        iterator,
+        &error_codes::CALL_DEPTH_EXCEEDED,
+        format!("Syntax Error: {what} are nested more than {max_nesting} levels deep."),
+    )
+}
+
+/// Returns `expression` if it nests at most [`MAX_NESTING`] levels deep. Its operands must
+/// already have been checked, which bounds the recursion here.
+fn check_nesting(iterator: &mut TokenIterator, expression: Expression) -> ParseResult<Expression> {
+    let max_nesting = MAX_NESTING.get();
+    if expression.depth() > max_nesting {
+        return Err(nested_too_deeply(iterator, "Expressions", max_nesting));
+    }
+    Ok(expression)
+}
+
+/// Returns an error about the next token, or about the end of the input if there is none.
+fn error_at_next(
+    iterator: &mut TokenIterator,
+    code: &'static ErrorCode,
+    message: String,
+) -> ParseError {
+    ParseError {
+        position: iterator.peek().map(|(index, _)| *index),
+        code,
+        message,
+        insertion: None,
+    }
+}
+
+fn default_parse(iterator: &mut TokenIterator) -> ParseResult<Expression> {
+    // A `;` is left for `parse_statements` to resynchronize on.
+    let Some((index, token)) = iterator.next_if(|(_, token)| !matches!(token, Token::Semicolon))
+    else {
+        let found = match iterator.peek() {
+            Some(_) => "`;`",
+            None => "the end of the input",
+        };
+        return Err(error_at_next(
+            iterator,
+            &error_codes::EXPECTED_EXPRESSION,
+            format!("Syntax Error: Expected an expression, found {found}."),
+        ));
+    };
+    Ok(match token {
+        Token::I8(value) => Expression::Number(Number::Int8(*value)),
+        Token::I16(value) => Expression::Number(Number::Int16(*value)),
+        Token::I32(value) => Expression::Number(Number::Int32(*value)),
+        Token::I64(value) => Expression::Number(Number::Int64(*value)),
+        Token::I128(value) => Expression::Number(Number::Int128(*value)),
+        Token::U8(value) => Expression::Number(Number::UInt8(*value)),
+        Token::U16(value) => Expression::Number(Number::UInt16(*value)),
+        Token::U32(value) => Expression::Number(Number::UInt32(*value)),
+        Token::U64(value) => Expression::Number(Number::UInt64(*value)),
+        Token::U128(value) => Expression::Number(Number::UInt128(*value)),
+        Token::F32(value) => Expression::Number(Number::Float32(*value)),
+        Token::F64(value) => Expression::Number(Number::Float64(*value)),
+        Token::BigInt(value) => Expression::Number(Number::BigInt(value.clone())),
+        Token::Decimal(value) => Expression::Number(Number::Decimal(value.clone())),
+        Token::String(value) => Expression::String(value.clone()),
+        Token::Char(value) => Expression::Char(*value),
+        Token::True => Expression::Bool(true),
+        Token::False => Expression::Bool(false),
+        Token::Identifier(name) => parse_name(iterator, name)?,
+        Token::LParenthesis => parse_lparen(iterator, index)?,
+        Token::LBracket => Expression::Vec {
+            elements: parse_elements(iterator, index)?,
+            element_type: None,
+        },
+        token => {
+            return Err(ParseError {
+                position: Some(index),
+                code: &error_codes::EXPECTED_EXPRESSION,
+                message: format!(
+                    "Syntax Error: Unexpected token {token} at the start of an expression."
+                ),
+                insertion: None,
+            })
+        }
+    })
+}
+
+/// Parses the rest of a parenthesized expression whose `(` is the token at `lparen_index`.
+fn parse_lparen(iterator: &mut TokenIterator, lparen_index: usize) -> ParseResult<Expression> {
+    let expression = parse_expression(iterator, 0)?;
+    if let Some((_, Token::RParenthesis)) = iterator.peek() {
+        iterator.next();
+        Ok(Expression::ParenthesisExpression(Box::new(expression)))
     } else {
-        None
+        Err(unclosed_parenthesis(iterator, lparen_index))
     }
 }
 
-fn parse_lparen(iterator: &mut Peekable<Enumerate<Iter<Token>>>) -> Option<Expression> {
-    let expression = default_parse(iterator)?;
-    if let Some((_, Token::RParenthesis)) = iterator.next() {
-        let expression = Expression::ParenthesisExpression(Box::new(expression));
-        let next_token = iterator.peek().cloned();
-        if let Some((_, next_token)) = next_token {
-            match next_token {
-                Token::Plus => {
-                    iterator.next().expect("iterator should still be valid");
-                    parse_binary(iterator, expression, BinaryOperator::Plus)
-                }
-                Token::Minus => {
-                    iterator.next().expect("iterator should still be valid");
-                    parse_binary(iterator, expression, BinaryOperator::Minus)
-                }
-                Token::Multiply => {
-                    iterator.next().expect("iterator should still be valid");
-                    parse_binary(iterator, expression, BinaryOperator::Multiply)
-                }
-                Token::Divide => {
-                    iterator.next().expect("iterator should still be valid");
-                    parse_binary(iterator, expression, BinaryOperator::Divide)
+fn unclosed_parenthesis(iterator: &mut TokenIterator, lparen_index: usize) -> ParseError {
+    // Closing the parenthesis only fixes the statement if nothing else follows.
+    let insertion = match iterator.peek() {
+        Some((_, token)) if !matches!(token, Token::Semicolon) => None,
+        next => Some(Insertion {
+            before: next.map(|(index, _)| *index),
+            text: ")",
+            message: "close the parenthesis with `)`",
+        }),
+    };
+    ParseError {
+        position: Some(lparen_index),
+        code: &error_codes::UNCLOSED_PARENTHESIS,
+        message: String::from("Syntax Error: Unclosed parenthesis."),
+        insertion,
+    }
+}
+
+/// Parses what follows the identifier `name`: a variable, a function call, a static member or
+/// function of the class `name`, or the body of a lambda whose parameter is `name`.
+fn parse_name(iterator: &mut TokenIterator, name: &str) -> ParseResult<Expression> {
+    match iterator.peek() {
+        Some((_, Token::FatArrow)) => {
+            iterator.next();
+            Ok(Expression::Lambda {
+                parameter: name.to_string(),
+                body: Box::new(parse_expression(iterator, 0)?),
+                result_type: None,
+            })
+        }
+        Some((lparen_index, Token::LParenthesis)) => {
+            let lparen_index = *lparen_index;
+            iterator.next();
+            Ok(Expression::Call {
+                function: name.to_string(),
+                arguments: parse_arguments(iterator, lparen_index)?,
+            })
+        }
+        Some((_, Token::DoubleColon)) => {
+            iterator.next();
+            let member = expect_name(iterator, "::")?;
+            match iterator.peek() {
+                Some((lparen_index, Token::LParenthesis)) => {
+                    let lparen_index = *lparen_index;
+                    iterator.next();
+                    Ok(Expression::StaticCall {
+                        class: name.to_string(),
+                        function: member,
+                        arguments: parse_arguments(iterator, lparen_index)?,
+                    })
                 }
-                Token::RParenthesis => Some(expression),
-                _ => panic!("Unexpected token after parenthesis expression"),
+                _ => Ok(Expression::StaticMember {
+                    class: name.to_string(),
+                    member,
+                }),
             }
-        } else {
-            Some(expression)
         }
-    } else {
-        panic!("Unclosed parenthesis");
+        _ => Ok(Expression::Variable(name.to_string())),
     }
 }
 
-fn parse_number(
-    iterator: &mut Peekable<Enumerate<Iter<Token>>>,
-    number: Number,
-) -> Option<Expression> {
-    if let Some((_, token)) = iterator.peek() {
-        match token {
-            Token::Plus => {
-                iterator.next().expect("Iterator should still be valid");
-                parse_binary(iterator, Expression::Number(number), BinaryOperator::Plus)
+/// Consumes the identifier naming a member or a variable after `separator`.
+fn expect_name(iterator: &mut TokenIterator, separator: &str) -> ParseResult<String> {
+    match iterator.peek() {
+        Some((_, Token::Identifier(name))) => {
+            let name = name.clone();
+            iterator.next();
+            Ok(name)
+        }
+        _ => Err(error_at_next(
+            iterator,
+            &error_codes::EXPECTED_NAME,
+            format!("Syntax Error: Expected a name after `{separator}`."),
+        )),
+    }
+}
+
+/// Parses the arguments of a call up to the closing parenthesis, the `(` being the token at
+/// `lparen_index`.
+fn parse_arguments(
+    iterator: &mut TokenIterator,
+    lparen_index: usize,
+) -> ParseResult<Vec<Expression>> {
+    let mut arguments = vec![];
+    if iterator
+        .next_if(|(_, token)| matches!(token, Token::RParenthesis))
+        .is_some()
+    {
+        return Ok(arguments);
+    }
+    loop {
+        arguments.push(parse_expression(iterator, 0)?);
+        match iterator.peek() {
+            Some((_, Token::RParenthesis)) => {
+                iterator.next();
+                return Ok(arguments);
             }
-            Token::Minus => {
-                iterator.next().expect("Iterator should still be valid");
-                parse_binary(iterator, Expression::Number(number), BinaryOperator::Minus)
+            Some((_, Token::Comma)) => {
+                iterator.next();
             }
-            Token::Multiply => {
-                iterator.next().expect("Iterator should still be valid");
-                parse_binary(
-                    iterator,
-                    Expression::Number(number),
-                    BinaryOperator::Multiply,
-                )
+            None | Some((_, Token::Semicolon)) => {
+                return Err(unclosed_parenthesis(iterator, lparen_index))
             }
-            Token::Divide => {
-                iterator.next().expect("Iterator should still be valid");
-                parse_binary(iterator, Expression::Number(number), BinaryOperator::Divide)
+            Some((index, token)) => {
+                return Err(ParseError {
+                    position: Some(*index),
+                    code: &error_codes::EXPECTED_COMMA,
+                    message: format!(
+                        "Syntax Error: Expected `,` or `)` after an argument, found {token}."
+                    ),
+                    insertion: None,
+                })
             }
+        }
+    }
+}
 
-            Token::RParenthesis => Some(Expression::Number(number)),
-            _ => panic!(""),
+/// Parses the elements of a `Vec` literal up to the closing bracket, the `[` being the token at
+/// `lbracket_index`.
+fn parse_elements(
+    iterator: &mut TokenIterator,
+    lbracket_index: usize,
+) -> ParseResult<Vec<Expression>> {
+    let mut elements = vec![];
+    if iterator
+        .next_if(|(_, token)| matches!(token, Token::RBracket))
+        .is_some()
+    {
+        return Ok(elements);
+    }
+    loop {
+        elements.push(parse_expression(iterator, 0)?);
+        match iterator.peek() {
+            Some((_, Token::RBracket)) => {
+                iterator.next();
+                return Ok(elements);
+            }
+            Some((_, Token::Comma)) => {
+                iterator.next();
+            }
+            None | Some((_, Token::Semicolon)) => {
+                return Err(unclosed_bracket(iterator, lbracket_index, "]"))
+            }
+            Some((index, token)) => {
+                return Err(ParseError {
+                    position: Some(*index),
+                    code: &error_codes::EXPECTED_COMMA,
+                    message: format!(
+                        "Syntax Error: Expected `,` or `]` after an element, found {token}."
+                    ),
+                    insertion: None,
+                })
+            }
         }
-    } else {
-        Some(Expression::Number(number))
     }
 }
 
-fn parse_binary(
-    iterator: &mut Peekable<Enumerate<Iter<Token>>>,
-    expression: Expression,
-    operator: BinaryOperator,
-) -> Option<Expression> {
-    if let Some(second_expression) = default_parse(iterator) {
-        if let Expression::String(_) | Expression::Char(_) = second_expression.unwrap_content() {
-            panic!("tried to add number to string or char")
-        }
-        if let Expression::BinaryOperation {
-            operator: second_operator,
-            left,
-            right,
-        } = second_expression.clone()
-        {
-            match operator.cmp(&second_operator) {
-                std::cmp::Ordering::Less | std::cmp::Ordering::Equal => {
-                    Some(Expression::BinaryOperation {
-                        operator,
-                        left: Box::new(expression),
-                        right: Box::new(second_expression),
-                    })
+/// Returns the error for a `[` or `{`, the token at `open_index`, which isn't closed by `close`.
+fn unclosed_bracket(
+    iterator: &mut TokenIterator,
+    open_index: usize,
+    close: &'static str,
+) -> ParseError {
+    let (kind, message) = match close {
+        "]" => ("bracket", "close the bracket with `]`"),
+        _ => ("brace", "close the brace with `}`"),
+    };
+    // Like parentheses, closing it only fixes the statement if nothing else follows.
+    let insertion = match iterator.peek() {
+        Some((_, token)) if !matches!(token, Token::Semicolon) => None,
+        next => Some(Insertion {
+            before: next.map(|(index, _)| *index),
+            text: close,
+            message,
+        }),
+    };
+    ParseError {
+        position: Some(open_index),
+        code: &error_codes::UNCLOSED_BRACKET,
+        message: format!("Syntax Error: Unclosed {kind}."),
+        insertion,
+    }
+}
+
+/// Parses an operand followed by any number of `.<field>`, `.<method>(<arguments>)` and
+/// `[<index>]`.
+fn parse_member_access(iterator: &mut TokenIterator) -> ParseResult<Expression> {
+    // This checks the parentheses and calls returned by `default_parse` as well.
+    let expression = default_parse(iterator)?;
+    let mut expression = check_nesting(iterator, expression)?;
+    while let Some((index, token)) =
+        iterator.next_if(|(_, token)| matches!(token, Token::Dot | Token::LBracket))
+    {
+        if let Token::LBracket = token {
+            let index_expression = parse_expression(iterator, 0)?;
+            if iterator
+                .next_if(|(_, token)| matches!(token, Token::RBracket))
+                .is_none()
+            {
+                return Err(unclosed_bracket(iterator, index, "]"));
+            }
+            expression = Expression::Index {
+                object: Box::new(expression),
+                index: Box::new(index_expression),
+            };
+            expression = check_nesting(iterator, expression)?;
+            continue;
+        }
+        let name = expect_name(iterator, ".")?;
+        expression = match iterator.peek() {
+            Some((lparen_index, Token::LParenthesis)) => {
+                let lparen_index = *lparen_index;
+                iterator.next();
+                Expression::MethodCall {
+                    object: Box::new(expression),
+                    method: name,
+                    arguments: parse_arguments(iterator, lparen_index)?,
                 }
-                std::cmp::Ordering::Greater => Some(Expression::BinaryOperation {
-                    operator: second_operator,
-                    left: Box::new(Expression::BinaryOperation {
-                        operator,
-                        left: Box::new(expression),
-                        right: left,
-                    }),
-                    right,
-                }),
             }
-        } else {
-            Some(Expression::BinaryOperation {
-                operator,
-                left: Box::new(expression),
-                right: Box::new(second_expression),
-            })
+            _ => Expression::Field {
+                object: Box::new(expression),
+                field: name,
+            },
+        };
+        expression = check_nesting(iterator, expression)?;
+    }
+    Ok(expression)
+}
+
+/// Parses binary operations whose operator priority is at least `min_priority`, so that
+/// `1 - 2 + 3` groups to the left and `*`/`/` bind tighter than `+`/`-`.
+///
+/// The operations are parsed in a loop into one [`Expression::BinaryOperations`], so the
+/// operands of a chain don't count as nested in each other.
+fn parse_expression(iterator: &mut TokenIterator, min_priority: u8) -> ParseResult<Expression> {
+    let first = parse_cast(iterator)?;
+    let mut operations = vec![];
+    while let Some(operator) = iterator
+        .peek()
+        .and_then(|(_, token)| BinaryOperator::from_token(token))
+    {
+        if operator.priority() < min_priority {
+            break;
         }
-    } else {
-        panic!("")
+        iterator.next().expect("Iterator should still be valid");
+        let operand = parse_binary(iterator, &operator)?;
+        operations.push((operator, operand));
+    }
+    if operations.is_empty() {
+        return Ok(first);
+    }
+    let expression = Expression::BinaryOperations {
+        first: Box::new(first),
+        operations,
+    };
+    check_nesting(iterator, expression)
+}
+
+/// Parses the right operand of `operator`.
+fn parse_binary(
+    iterator: &mut TokenIterator,
+    operator: &BinaryOperator,
+) -> ParseResult<Expression> {
+    if iterator
+        .peek()
+        .is_none_or(|(_, token)| matches!(token, Token::Semicolon))
+    {
+        return Err(error_at_next(
+            iterator,
+            &error_codes::EXPECTED_EXPRESSION,
+            format!(
+                "Syntax Error: Expected an expression after `{}`.",
+                operator.symbol()
+            ),
+        ));
+    }
+    parse_expression(iterator, operator.priority() + 1)
+}
+
+fn parse_cast(iterator: &mut TokenIterator) -> ParseResult<Expression> {
+    let mut expression = parse_unary(iterator)?;
+    while let Some((_, Token::As)) = iterator.peek() {
+        iterator.next().expect("Iterator should still be valid");
+        let target = match iterator.peek() {
+            Some((index, Token::Identifier(name))) => {
+                NumberType::from_name(name).ok_or_else(|| ParseError {
+                    position: Some(*index),
+                    code: &error_codes::UNKNOWN_TYPE,
+                    message: format!("Syntax Error: Unknown type `{name}` in cast expression."),
+                    insertion: None,
+                })?
+            }
+            _ => {
+                return Err(error_at_next(
+                    iterator,
+                    &error_codes::EXPECTED_TYPE,
+                    String::from("Syntax Error: Expected a type name after `as`."),
+                ))
+            }
+        };
+        iterator.next();
+        expression = Expression::Cast {
+            expr: Box::new(expression),
+            target,
+        };
+        expression = check_nesting(iterator, expression)?;
     }
+    Ok(expression)
+}
+
+fn parse_unary(iterator: &mut TokenIterator) -> ParseResult<Expression> {
+    // Every operand is parsed here, including the ones in parentheses and arguments, so counting
+    // them bounds the recursion of the parser.
+    check_parser_nesting(iterator, "Expressions")?;
+    let nesting = NESTING.get();
+    NESTING.set(nesting + 1);
+    let result = parse_operand(iterator);
+    NESTING.set(nesting);
+    result
+}
+
+fn parse_operand(iterator: &mut TokenIterator) -> ParseResult<Expression> {
+    let operator = match iterator.peek() {
+        Some((_, Token::Plus)) => UnaryOperator::Plus,
+        Some((_, Token::Minus)) => UnaryOperator::Minus,
+        _ => return parse_member_access(iterator),
+    };
+    iterator.next().expect("Iterator should still be valid.");
+
+    let expr = parse_unary(iterator)?;
+    let expression = Expression::UnaryOperation {
+        operator,
+        expr: Box::new(expr),
+    };
+    check_nesting(iterator, expression)
 }
 
-fn parse_unary(
-    iterator: &mut Peekable<Enumerate<Iter<Token>>>,
-    operator: UnaryOperator,
-) -> Option<Expression> {
-    let expr;
+/// Parses `<type> <name> = <value>`.
+fn parse_declaration(iterator: &mut TokenIterator) -> ParseResult<Statement> {
+    let declared_type = parse_type(iterator)?;
+    let name = expect_name(iterator, &declared_type.to_string())?;
+    if !matches!(iterator.peek(), Some((_, Token::Equal))) {
+        return Err(error_at_next(
+            iterator,
+            &error_codes::MISSING_INITIAL_VALUE,
+            format!("Syntax Error: Expected `=` and an initial value after the declaration of `{name}`."),
+        ));
+    }
+    iterator.next();
+    let value = parse_expression(iterator, 0)?;
+    Ok(Statement::Declaration {
+        declared_type: Some(declared_type),
+        mutable: true,
+        name,
+        value,
+    })
+}
 
-    if let Some((_index, token)) = iterator.peek() {
-        expr = match token {
-            Token::F32(f) => {
-                iterator.next().expect("Iterator should still be valid.");
-                Expression::Number(Number::Float32(*f))
+/// Parses a type name, followed by its type arguments between `<` and `>` if it is generic.
+fn parse_type(iterator: &mut TokenIterator) -> ParseResult<Type> {
+    // Type arguments are bounded like operands, as everything using a type walks them.
+    check_parser_nesting(iterator, "Types")?;
+    let nesting = NESTING.get();
+    NESTING.set(nesting + 1);
+    let result = parse_type_name(iterator);
+    NESTING.set(nesting);
+    result
+}
+
+fn parse_type_name(iterator: &mut TokenIterator) -> ParseResult<Type> {
+    let (index, name) = match iterator.peek() {
+        Some((index, Token::Identifier(name))) => (*index, name.clone()),
+        _ => {
+            return Err(error_at_next(
+                iterator,
+                &error_codes::EXPECTED_TYPE,
+                String::from("Syntax Error: Expected a type."),
+            ))
+        }
+    };
+    iterator.next();
+    let arguments = match iterator.next_if(|(_, token)| matches!(token, Token::Less)) {
+        Some(_) => Some(parse_type_arguments(iterator)?),
+        None => None,
+    };
+    let error = |code, message| ParseError {
+        position: Some(index),
+        code,
+        message,
+        insertion: None,
+    };
+    if let Some((_, parameters)) = GENERIC_TYPES.iter().find(|(generic, _)| *generic == name) {
+        let generic = format!("{name}<{}>", parameters.join(", "));
+        let plural = if parameters.len() == 1 { "" } else { "s" };
+        let arguments = match arguments {
+            Some(arguments) if arguments.len() == parameters.len() => arguments,
+            Some(arguments) => {
+                let verb = if arguments.len() == 1 { "was" } else { "were" };
+                return Err(error(
+                    &error_codes::WRONG_TYPE_ARGUMENTS,
+                    format!(
+                        "Syntax Error: `{generic}` takes {} type argument{plural} but {} {verb} given.",
+                        parameters.len(),
+                        arguments.len()
+                    ),
+                ));
             }
-            Token::F64(f) => {
-                iterator.next().expect("Iterator should still be valid.");
-                Expression::Number(Number::Float64(*f))
+            None => {
+                return Err(error(
+                    &error_codes::WRONG_TYPE_ARGUMENTS,
+                    format!(
+                        "Syntax Error: `{name}` needs {} type argument{plural}, as in `{generic}`.",
+                        parameters.len()
+                    ),
+                ))
             }
-            Token::I32(f) => {
-                iterator.next().expect("Iterator should still be valid.");
-                Expression::Number(Number::Int32(*f))
+        };
+        // `Vec` is the only generic type.
+        let element = arguments
+            .into_iter()
+            .next()
+            .expect("The arguments were counted");
+        return Ok(Type::Vec(Box::new(element)));
+    }
+    // Capitalized names can be classes registered by the program embedding tray, which only the
+    // type checker knows about.
+    let declared_type = match Type::from_name(&name) {
+        Some(declared_type) => declared_type,
+        None if name.starts_with(char::is_uppercase) => Type::Class(name.clone()),
+        None => {
+            // The name of the variable follows the type of a declaration.
+            let declaration = match iterator.peek() {
+                Some((_, Token::Identifier(variable))) => {
+                    format!(" in declaration of `{variable}`")
+                }
+                _ => String::new(),
+            };
+            return Err(error(
+                &error_codes::UNKNOWN_TYPE,
+                format!("Syntax Error: Unknown type `{name}`{declaration}."),
+            ));
+        }
+    };
+    if arguments.is_some() {
+        return Err(error(
+            &error_codes::WRONG_TYPE_ARGUMENTS,
+            format!("Syntax Error: `{name}` isn't generic and doesn't take type arguments."),
+        ));
+    }
+    Ok(declared_type)
+}
+
+/// Parses the type arguments after a `<`, up to the closing `>`.
+fn parse_type_arguments(iterator: &mut TokenIterator) -> ParseResult<Vec<Type>> {
+    let mut arguments = vec![];
+    loop {
+        arguments.push(parse_type(iterator)?);
+        match iterator.peek() {
+            Some((_, Token::Greater)) => {
+                iterator.next();
+                return Ok(arguments);
             }
-            Token::I64(f) => {
-                iterator.next().expect("Iterator should still be valid.");
-                Expression::Number(Number::Int64(*f))
+            Some((_, Token::Comma)) => {
+                iterator.next();
             }
-            Token::I128(f) => {
-                iterator.next().expect("Iterator should still be valid.");
-                Expression::Number(Number::Int128(*f))
+            Some((index, token)) => {
+                return Err(ParseError {
+                    position: Some(*index),
+                    code: &error_codes::EXPECTED_COMMA,
+                    message: format!(
+                        "Syntax Error: Expected `,` or `>` after a type argument, found {token}."
+                    ),
+                    insertion: None,
+                })
             }
-            _ => default_parse(iterator)?,
-        };
+            None => {
+                return Err(error_at_next(
+                    iterator,
+                    &error_codes::EXPECTED_COMMA,
+                    String::from("Syntax Error: Expected `,` or `>` after a type argument, found the end of the input."),
+                ))
+            }
+        }
+    }
+}
 
-        if let Expression::String(_) | Expression::Char(_) = expr {
-            panic!("")
+fn parse_statement(iterator: &mut TokenIterator) -> ParseResult<Statement> {
+    let mut lookahead = iterator.clone();
+    match (lookahead.next(), lookahead.next()) {
+        (Some((_, Token::Identifier(_))), Some((_, Token::Identifier(_) | Token::Less))) => {
+            parse_declaration(iterator)
+        }
+        (Some((_, keyword @ (Token::Let | Token::Var))), _) => {
+            let mutable = matches!(keyword, Token::Var);
+            iterator.next();
+            let name = expect_name(iterator, if mutable { "var" } else { "let" })?;
+            if !matches!(iterator.peek(), Some((_, Token::Equal))) {
+                return Err(error_at_next(
+                    iterator,
+                    &error_codes::MISSING_INITIAL_VALUE,
+                    format!("Syntax Error: Expected `=` and an initial value after the declaration of `{name}`, whose type is inferred from it."),
+                ));
+            }
+            iterator.next();
+            let value = parse_expression(iterator, 0)?;
+            Ok(Statement::Declaration {
+                declared_type: None,
+                mutable,
+                name,
+                value,
+            })
+        }
+        (Some((_, Token::Identifier(name))), Some((_, Token::Equal))) => {
+            *iterator = lookahead;
+            let value = parse_expression(iterator, 0)?;
+            Ok(Statement::Assignment {
+                name: name.clone(),
+                value,
+            })
+        }
+        (Some((_, Token::For)), _) => {
+            iterator.next();
+            parse_for(iterator)
         }
+        _ => {
+            let expression = parse_expression(iterator, 0)?;
+            match expression {
+                Expression::Index { object, index }
+                    if iterator
+                        .next_if(|(_, token)| matches!(token, Token::Equal))
+                        .is_some() =>
+                {
+                    Ok(Statement::IndexAssignment {
+                        object: *object,
+                        index: *index,
+                        value: parse_expression(iterator, 0)?,
+                    })
+                }
+                expression => Ok(Statement::Expression(expression)),
+            }
+        }
+    }
+}
 
-        let unary_expr = Expression::UnaryOperation {
-            operator,
-            expr: Box::new(expr),
+/// Parses `<variable> in <iterable> { <body> }` after the `for` keyword.
+fn parse_for(iterator: &mut TokenIterator) -> ParseResult<Statement> {
+    let variable = expect_name(iterator, "for")?;
+    let expect = |iterator: &mut TokenIterator, expected: &str, after: &str| {
+        let found = match iterator.peek() {
+            Some((_, token)) => token.to_string(),
+            None => String::from("the end of the input"),
         };
+        error_at_next(
+            iterator,
+            &error_codes::MALFORMED_LOOP,
+            format!(
+                "Syntax Error: Expected `{expected}` after {after} of a `for` loop, found {found}."
+            ),
+        )
+    };
+    if iterator
+        .next_if(|(_, token)| matches!(token, Token::In))
+        .is_none()
+    {
+        return Err(expect(iterator, "in", "the variable"));
+    }
+    let iterable = parse_expression(iterator, 0)?;
+    let Some((lbrace_index, _)) = iterator.next_if(|(_, token)| matches!(token, Token::LBrace))
+    else {
+        return Err(expect(iterator, "{", "the `Vec`"));
+    };
+    Ok(Statement::For {
+        variable,
+        iterable,
+        body: parse_block(iterator, lbrace_index)?,
+    })
+}
 
-        if let Some((_, token)) = iterator.next() {
-            match token {
-                Token::Plus => parse_binary(iterator, unary_expr, BinaryOperator::Plus),
-                Token::Minus => parse_binary(iterator, unary_expr, BinaryOperator::Minus),
-                Token::Multiply => parse_binary(iterator, unary_expr, BinaryOperator::Multiply),
-                Token::Divide => parse_binary(iterator, unary_expr, BinaryOperator::Divide),
-                Token::RParenthesis => Some(unary_expr),
-                _ => panic!(),
+/// Parses the statements of a block up to the closing brace, the `{` being the token at
+/// `lbrace_index`. Like at the top level, statements are separated by `;`.
+fn parse_block(
+    iterator: &mut TokenIterator,
+    lbrace_index: usize,
+) -> ParseResult<Vec<(usize, Statement)>> {
+    // Blocks are nested in each other like operands.
+    check_parser_nesting(iterator, "Blocks")?;
+    let nesting = NESTING.get();
+    NESTING.set(nesting + 1);
+    let result = parse_block_statements(iterator, lbrace_index);
+    NESTING.set(nesting);
+    result
+}
+
+fn parse_block_statements(
+    iterator: &mut TokenIterator,
+    lbrace_index: usize,
+) -> ParseResult<Vec<(usize, Statement)>> {
+    let mut statements = vec![];
+    loop {
+        while iterator
+            .next_if(|(_, token)| matches!(token, Token::Semicolon))
+            .is_some()
+        {}
+        let start = match iterator.peek() {
+            Some((_, Token::RBrace)) => {
+                iterator.next();
+                return Ok(statements);
+            }
+            Some((start, _)) => *start,
+            None => return Err(unclosed_bracket(iterator, lbrace_index, "}")),
+        };
+        let statement = parse_statement(iterator)?;
+        let ends_with_block = matches!(statement, Statement::For { .. });
+        statements.push((start, statement));
+        match iterator.peek() {
+            Some((_, Token::Semicolon | Token::RBrace)) => {}
+            _ if ends_with_block => {}
+            None => return Err(unclosed_bracket(iterator, lbrace_index, "}")),
+            Some((index, token)) => {
+                return Err(ParseError {
+                    position: Some(*index),
+                    code: &error_codes::UNEXPECTED_TOKEN,
+                    message: format!(
+                        "Syntax Error: Unexpected token {token} after the end of the statement."
+                    ),
+                    insertion: None,
+                })
             }
-        } else {
-            Some(unary_expr)
         }
-    } else {
-        None
     }
 }
 
-pub fn parse(tokens: &TokenList) -> Option<Expression> {
+/// Parses statements separated by `;`. The last statement doesn't need a trailing `;`, and
+/// neither do statements ending with a block.
+///
+/// After an error, parsing resumes after the next `;` outside of the blocks of the statement, or
+/// after the end of its first block, so every statement with an error is reported once.
+pub fn parse_statements(tokens: &TokenList) -> (Vec<Statement>, Vec<ParseError>) {
+    let (statements, errors) = parse_statements_at(tokens, DEFAULT_MAX_NESTING);
+    let statements = statements
+        .into_iter()
+        .map(|(_, statement)| statement)
+        .collect();
+    (statements, errors)
+}
+
+/// Parses statements like [`parse_statements`], along with the index of the first token of each
+/// statement. Expressions, blocks and types can nest at most `max_nesting` levels deep.
+pub fn parse_statements_at(
+    tokens: &TokenList,
+    max_nesting: usize,
+) -> (Vec<(usize, Statement)>, Vec<ParseError>) {
+    MAX_NESTING.set(max_nesting);
     let mut iterator = tokens.iter().enumerate().peekable();
-    default_parse(&mut iterator)
+    let mut statements = vec![];
+    let mut errors = vec![];
+    loop {
+        while let Some((_, Token::Semicolon)) = iterator.peek() {
+            iterator.next();
+        }
+        let Some((start, _)) = iterator.peek() else {
+            break;
+        };
+        let start = *start;
+        let result = parse_statement(&mut iterator).and_then(|statement| match iterator.peek() {
+            Some((_, Token::Semicolon)) | None => Ok(statement),
+            Some(_) if matches!(statement, Statement::For { .. }) => Ok(statement),
+            Some((index, token)) => Err(ParseError {
+                position: Some(*index),
+                code: &error_codes::UNEXPECTED_TOKEN,
+                message: format!(
+                    "Syntax Error: Unexpected token {token} after the end of the statement."
+                ),
+                insertion: None,
+            }),
+        });
+        match result {
+            Ok(statement) => statements.push((start, statement)),
+            Err(error) => {
+                errors.push(error);
+                // The statement is skipped again from its start, to know which blocks the
+                // error is in.
+                iterator = tokens.iter().enumerate().peekable();
+                if start > 0 {
+                    iterator.nth(start - 1);
+                }
+                let mut depth = 0usize;
+                while let Some((_, token)) =
+                    iterator.next_if(|(_, token)| depth > 0 || !matches!(token, Token::Semicolon))
+                {
+                    match token {
+                        Token::LBrace => depth += 1,
+                        // A statement ending with a block doesn't need a `;`.
+                        Token::RBrace if depth == 1 => break,
+                        Token::RBrace => depth = depth.saturating_sub(1),
+                        _ => {}
+                    }
+                }
+            }
+        }
+    }
+    (statements, errors)
 }
@@ -1,15 +1,615 @@
-use std::io::Write;
+use std::{
+    fs::OpenOptions,
+    io::{IsTerminal, Read, Write},
+    path::PathBuf,
+};
+
+use tray::lexer;
+
+pub const PROMPT: &str = "tray> ";
+pub const CONTINUATION_PROMPT: &str = "....> ";
+
+/// Maximum number of lines kept in the history file.
+const HISTORY_SIZE: usize = 1000;
+
+/// How long to wait for the rest of an escape sequence before taking Escape as a key press on
+/// its own, in milliseconds.
+const ESCAPE_TIMEOUT: i32 = 50;
+
+// The layout of `Termios` below is the one of x86_64 and aarch64, other architectures like
+// PowerPC or MIPS order or size the fields differently.
+#[cfg(all(
+    target_os = "linux",
+    any(target_arch = "x86_64", target_arch = "aarch64")
+))]
+mod terminal {
+    /// `struct termios` from `<termios.h>` on Linux.
+    #[repr(C)]
+    #[derive(Clone, Copy)]
+    struct Termios {
+        c_iflag: u32,
+        c_oflag: u32,
+        c_cflag: u32,
+        c_lflag: u32,
+        c_line: u8,
+        c_cc: [u8; 32],
+        c_ispeed: u32,
+        c_ospeed: u32,
+    }
+
+    /// `struct pollfd` from `<poll.h>`.
+    #[repr(C)]
+    struct PollFd {
+        fd: i32,
+        events: i16,
+        revents: i16,
+    }
+
+    extern "C" {
+        fn tcgetattr(fd: i32, termios: *mut Termios) -> i32;
+        fn tcsetattr(fd: i32, optional_actions: i32, termios: *const Termios) -> i32;
+        fn poll(fds: *mut PollFd, nfds: u64, timeout: i32) -> i32;
+        fn read(fd: i32, buffer: *mut u8, count: usize) -> isize;
+    }
+
+    const STDIN: i32 = 0;
+    const TCSANOW: i32 = 0;
+    const ISIG: u32 = 0o1;
+    const ICANON: u32 = 0o2;
+    const ECHO: u32 = 0o10;
+    const IEXTEN: u32 = 0o100000;
+    const ICRNL: u32 = 0o400;
+    const IXON: u32 = 0o2000;
+    const VTIME: usize = 5;
+    const VMIN: usize = 6;
+    const POLLIN: i16 = 1;
+
+    /// Reads stdin without buffering, unlike [`std::io::Stdin`], so that [`input_pending`] sees
+    /// every byte that wasn't read yet.
+    pub struct Input;
+
+    impl std::io::Read for Input {
+        fn read(&mut self, buffer: &mut [u8]) -> std::io::Result<usize> {
+            // SAFETY: `buffer` is valid for writes of its whole length.
+            let length = unsafe { read(STDIN, buffer.as_mut_ptr(), buffer.len()) };
+            usize::try_from(length).map_err(|_| std::io::Error::last_os_error())
+        }
+    }
+
+    pub fn input() -> Input {
+        Input
+    }
+
+    /// Waits at most `timeout` milliseconds for input to be available on stdin.
+    pub fn input_pending(timeout: i32) -> bool {
+        let mut stdin = PollFd {
+            fd: STDIN,
+            events: POLLIN,
+            revents: 0,
+        };
+        // SAFETY: `stdin` is a single valid pollfd.
+        unsafe { poll(&mut stdin, 1, timeout) > 0 }
+    }
+
+    /// Puts the terminal in raw mode for as long as it is alive: input is read byte by byte,
+    /// without echo, and control keys like Ctrl-C are delivered as bytes instead of signals.
+    pub struct RawMode {
+        original: Termios,
+    }
+
+    impl RawMode {
+        pub fn enable() -> Option<RawMode> {
+            let mut original = std::mem::MaybeUninit::<Termios>::uninit();
+            // SAFETY: tcgetattr fully initializes the struct when it succeeds.
+            let original = unsafe {
+                if tcgetattr(STDIN, original.as_mut_ptr()) != 0 {
+                    return None;
+                }
+                original.assume_init()
+            };
+            let mut raw = original;
+            raw.c_lflag &= !(ECHO | ICANON | ISIG | IEXTEN);
+            raw.c_iflag &= !(ICRNL | IXON);
+            raw.c_cc[VMIN] = 1;
+            raw.c_cc[VTIME] = 0;
+            // SAFETY: `raw` is a valid termios obtained from tcgetattr.
+            if unsafe { tcsetattr(STDIN, TCSANOW, &raw) } != 0 {
+                return None;
+            }
+            Some(RawMode { original })
+        }
+    }
+
+    impl Drop for RawMode {
+        fn drop(&mut self) {
+            // SAFETY: `original` is the valid termios the terminal had before raw mode.
+            unsafe {
+                tcsetattr(STDIN, TCSANOW, &self.original);
+            }
+        }
+    }
+}
+
+#[cfg(not(all(
+    target_os = "linux",
+    any(target_arch = "x86_64", target_arch = "aarch64")
+)))]
+mod terminal {
+    /// Raw mode is only implemented for Linux on x86_64 and aarch64, other platforms use
+    /// line-buffered input.
+    pub struct RawMode;
+
+    impl RawMode {
+        pub fn enable() -> Option<RawMode> {
+            None
+        }
+    }
+
+    pub fn input() -> std::io::StdinLock<'static> {
+        std::io::stdin().lock()
+    }
+
+    pub fn input_pending(_timeout: i32) -> bool {
+        true
+    }
+}
+
+/// Calls `handler` when the process receives SIGINT (Ctrl-C outside of raw mode) instead of
+/// terminating it.
+#[cfg(unix)]
+pub fn install_interrupt_handler(handler: extern "C" fn(i32)) {
+    extern "C" {
+        fn signal(signum: i32, handler: extern "C" fn(i32)) -> usize;
+    }
+    const SIGINT: i32 = 2;
+    // SAFETY: `handler` is a plain function pointer, callers must keep it async-signal-safe.
+    unsafe {
+        signal(SIGINT, handler);
+    }
+}
+
+#[cfg(not(unix))]
+pub fn install_interrupt_handler(_handler: extern "C" fn(i32)) {}
+
+/// The outcome of reading one line.
+enum Line {
+    Text(String),
+    /// The user pressed Ctrl-C to cancel the line.
+    Interrupted,
+    /// The input was closed, or the user pressed Ctrl-D on an empty line.
+    End,
+}
+
+enum Key {
+    Char(char),
+    Control(u8),
+    Enter,
+    Backspace,
+    Delete,
+    Left,
+    Right,
+    Up,
+    Down,
+    Home,
+    End,
+    Escape,
+}
+
+fn read_byte(input: &mut impl Read) -> Option<u8> {
+    let mut byte = [0u8];
+    match input.read(&mut byte) {
+        Ok(1) => Some(byte[0]),
+        _ => None,
+    }
+}
+
+/// Reads one key press, decoding UTF-8 characters and the escape sequences of the keys the
+/// editor supports. Returns `None` when the input is closed.
+///
+/// An Escape that isn't followed by more input within [`ESCAPE_TIMEOUT`] is the Escape key
+/// itself, so that it doesn't wait for, and swallow, the next key.
+fn read_key(input: &mut impl Read) -> Option<Key> {
+    let byte = read_byte(input)?;
+    Some(match byte {
+        b'\r' | b'\n' => Key::Enter,
+        127 | 8 => Key::Backspace,
+        0x1b if !terminal::input_pending(ESCAPE_TIMEOUT) => Key::Escape,
+        0x1b => match read_byte(input)? {
+            b'[' | b'O' => match read_byte(input)? {
+                b'A' => Key::Up,
+                b'B' => Key::Down,
+                b'C' => Key::Right,
+                b'D' => Key::Left,
+                b'H' => Key::Home,
+                b'F' => Key::End,
+                digit @ b'0'..=b'9' => {
+                    // `ESC [ <number> ~` sequences
+                    let mut last = digit;
+                    while last != b'~' {
+                        last = read_byte(input)?;
+                    }
+                    match digit {
+                        b'1' | b'7' => Key::Home,
+                        b'4' | b'8' => Key::End,
+                        b'3' => Key::Delete,
+                        _ => Key::Escape,
+                    }
+                }
+                _ => Key::Escape,
+            },
+            _ => Key::Escape,
+        },
+        0..=31 => Key::Control(byte + b'a' - 1),
+        _ => {
+            let length = match byte {
+                0xf0..=0xff => 4,
+                0xe0..=0xef => 3,
+                0xc0..=0xdf => 2,
+                _ => 1,
+            };
+            let mut bytes = vec![byte];
+            for _ in 1..length {
+                bytes.push(read_byte(input)?);
+            }
+            Key::Char(
+                String::from_utf8_lossy(&bytes)
+                    .chars()
+                    .next()
+                    .unwrap_or(char::REPLACEMENT_CHARACTER),
+            )
+        }
+    })
+}
+
+/// Previously entered lines, persisted to `~/.tray_history`.
+struct History {
+    entries: Vec<String>,
+    path: Option<PathBuf>,
+}
+
+impl History {
+    fn load() -> Self {
+        let path = std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".tray_history"));
+        let entries = path
+            .as_ref()
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .map(|content| content.lines().map(String::from).collect())
+            .unwrap_or_default();
+        History { entries, path }
+    }
+
+    fn add(&mut self, line: &str) {
+        if line.trim().is_empty() || self.entries.last().is_some_and(|last| last == line) {
+            return;
+        }
+        self.entries.push(line.to_string());
+        let Some(path) = &self.path else {
+            return;
+        };
+        if self.entries.len() > HISTORY_SIZE {
+            self.entries.drain(..self.entries.len() - HISTORY_SIZE);
+            let _ = std::fs::write(path, self.entries.join("\n") + "\n");
+        } else if let Ok(mut file) = OpenOptions::new().create(true).append(true).open(path) {
+            let _ = writeln!(file, "{line}");
+        }
+    }
+
+    /// Finds the most recent entry before `before` containing `query`.
+    fn search(&self, query: &str, before: usize) -> Option<usize> {
+        self.entries[..before.min(self.entries.len())]
+            .iter()
+            .rposition(|entry| entry.contains(query))
+    }
+}
+
+/// The line being edited and the position of the cursor in it.
+struct LineEditor<'a> {
+    prompt: &'a str,
+    buffer: Vec<char>,
+    cursor: usize,
+}
+
+impl LineEditor<'_> {
+    fn set(&mut self, line: &str) {
+        self.buffer = line.chars().collect();
+        self.cursor = self.buffer.len();
+    }
+
+    fn line(&self) -> String {
+        self.buffer.iter().collect()
+    }
+
+    fn render(&self) {
+        let line = self.line();
+        let column = self.prompt.chars().count() + self.cursor;
+        print!("\r{}{line}\x1b[K\r", self.prompt);
+        if column > 0 {
+            print!("\x1b[{column}C");
+        }
+        let _ = std::io::stdout().flush();
+    }
+
+    fn render_search(&self, query: &str, found: &str) {
+        print!("\r(reverse-i-search)`{query}': {found}\x1b[K");
+        let _ = std::io::stdout().flush();
+    }
+
+    fn delete_range(&mut self, start: usize, end: usize) {
+        self.buffer.drain(start..end);
+        self.cursor = start;
+    }
+
+    /// Returns where the identifier ending at the cursor starts.
+    fn identifier_start(&self) -> usize {
+        let mut index = self.cursor;
+        while index > 0
+            && (self.buffer[index - 1].is_alphanumeric() || self.buffer[index - 1] == '_')
+        {
+            index -= 1;
+        }
+        index
+    }
+
+    /// Completes the identifier before the cursor with the candidates returned by `complete`.
+    ///
+    /// A single candidate is inserted whole. With several, their common prefix is inserted, or
+    /// they are listed below the line if there is nothing more to insert. `complete` is given the
+    /// whole line before the cursor so that it can tell members from other names.
+    fn complete(&mut self, complete: Completer) {
+        let start = self.identifier_start();
+        let word: String = self.buffer[start..self.cursor].iter().collect();
+        let line: String = self.buffer[..self.cursor].iter().collect();
+        let candidates = complete(&line);
+        let Some(first) = candidates.first() else {
+            return;
+        };
+
+        let common_length = candidates.iter().fold(first.len(), |length, candidate| {
+            first
+                .bytes()
+                .zip(candidate.bytes())
+                .take(length)
+                .take_while(|(left, right)| left == right)
+                .count()
+        });
+        let mut common_prefix = &first[..common_length];
+        while !common_prefix.is_char_boundary(common_prefix.len()) {
+            common_prefix = &common_prefix[..common_prefix.len() - 1];
+        }
+
+        if common_prefix.len() > word.len() {
+            let insertion: Vec<char> = common_prefix[word.len()..].chars().collect();
+            let length = insertion.len();
+            self.buffer.splice(self.cursor..self.cursor, insertion);
+            self.cursor += length;
+        } else if candidates.len() > 1 {
+            print!("\r\n{}\r\n", candidates.join("  "));
+        }
+    }
+
+    fn previous_word_start(&self) -> usize {
+        let mut index = self.cursor;
+        while index > 0 && self.buffer[index - 1].is_whitespace() {
+            index -= 1;
+        }
+        while index > 0 && !self.buffer[index - 1].is_whitespace() {
+            index -= 1;
+        }
+        index
+    }
+}
+
+/// Returns the names that can complete the identifier ending the given line, offered when the
+/// user presses Tab.
+pub type Completer<'a> = &'a dyn Fn(&str) -> Vec<String>;
+
+/// Reads the lines typed by the user.
+///
+/// When stdin and stdout are terminals, lines are edited in raw mode with cursor movement,
+/// history navigation and search, and the usual Emacs-style control keys. Otherwise lines are
+/// read as they come, without prompts when stdin isn't a terminal so that piped scripts run
+/// as a batch.
+pub struct LineReader {
+    prompt: &'static str,
+    history: History,
+    interactive: bool,
+}
+
+impl LineReader {
+    pub fn new() -> Self {
+        LineReader {
+            prompt: PROMPT,
+            history: History::load(),
+            interactive: std::io::stdin().is_terminal(),
+        }
+    }
+
+    /// Reads lines until they form a complete input according to [`lexer::is_incomplete`],
+    /// showing [`CONTINUATION_PROMPT`] for every line after the first. Returns `None` once the
+    /// input is closed.
+    ///
+    /// In a terminal, an empty continuation line submits the input as it is, so that a typo like
+    /// an extra `(` can't trap the user in continuation mode. Piped input keeps its blank lines,
+    /// so that scripts with blank lines in a block run as a batch. The end of the input always
+    /// submits it. Ctrl-C discards the whole input and starts over.
+    pub fn read_input(&mut self, complete: Completer) -> Option<String> {
+        'input: loop {
+            let mut input = match self.read_line(complete) {
+                Line::Text(line) => line,
+                Line::Interrupted => continue,
+                Line::End => return None,
+            };
+            while lexer::is_incomplete(&input) {
+                self.prompt = CONTINUATION_PROMPT;
+                let line = self.read_line(complete);
+                self.prompt = PROMPT;
+                match line {
+                    Line::Text(line) if !self.interactive || !line.trim().is_empty() => {
+                        input.push('\n');
+                        input.push_str(&line);
+                    }
+                    Line::Interrupted => continue 'input,
+                    _ => break,
+                }
+            }
+            return Some(input);
+        }
+    }
+
+    fn read_line(&mut self, complete: Completer) -> Line {
+        if self.interactive && std::io::stdout().is_terminal() {
+            if let Some(raw_mode) = terminal::RawMode::enable() {
+                return self.edit_line(raw_mode, complete);
+            }
+        }
+        self.read_plain_line()
+    }
+
+    fn read_plain_line(&self) -> Line {
+        if self.interactive {
+            print!("{}", self.prompt);
+            let _ = std::io::stdout().flush();
+        }
+        let mut buffer = String::new();
+        match std::io::stdin().read_line(&mut buffer) {
+            Ok(0) | Err(_) => return Line::End,
+            Ok(_) => {}
+        }
+        buffer = buffer.trim_end_matches(['\r', '\n']).to_string();
+        return Line::Text(buffer);
+    }
+
+    fn edit_line(&mut self, _raw_mode: terminal::RawMode, complete: Completer) -> Line {
+        let mut stdin = terminal::input();
+        let mut editor = LineEditor {
+            prompt: self.prompt,
+            buffer: vec![],
+            cursor: 0,
+        };
+        let mut history_index = self.history.entries.len();
+        let mut unsubmitted_line = String::new();
+        editor.render();
+
+        loop {
+            let Some(key) = read_key(&mut stdin) else {
+                return Line::End;
+            };
+            match key {
+                Key::Enter => break,
+                Key::Char(char) => {
+                    editor.buffer.insert(editor.cursor, char);
+                    editor.cursor += 1;
+                }
+                Key::Backspace if editor.cursor > 0 => {
+                    editor.delete_range(editor.cursor - 1, editor.cursor);
+                }
+                Key::Delete | Key::Control(b'd') if editor.cursor < editor.buffer.len() => {
+                    editor.buffer.remove(editor.cursor);
+                }
+                Key::Control(b'd') if editor.buffer.is_empty() => {
+                    print!("\r\n");
+                    return Line::End;
+                }
+                Key::Left | Key::Control(b'b') if editor.cursor > 0 => editor.cursor -= 1,
+                Key::Right | Key::Control(b'f') if editor.cursor < editor.buffer.len() => {
+                    editor.cursor += 1
+                }
+                Key::Home | Key::Control(b'a') => editor.cursor = 0,
+                Key::End | Key::Control(b'e') => editor.cursor = editor.buffer.len(),
+                Key::Control(b'k') => editor.delete_range(editor.cursor, editor.buffer.len()),
+                Key::Control(b'u') => editor.delete_range(0, editor.cursor),
+                Key::Control(b'w') => {
+                    editor.delete_range(editor.previous_word_start(), editor.cursor)
+                }
+                Key::Up | Key::Control(b'p') if history_index > 0 => {
+                    if history_index == self.history.entries.len() {
+                        unsubmitted_line = editor.line();
+                    }
+                    history_index -= 1;
+                    editor.set(&self.history.entries[history_index]);
+                }
+                Key::Down | Key::Control(b'n') if history_index < self.history.entries.len() => {
+                    history_index += 1;
+                    match self.history.entries.get(history_index) {
+                        Some(entry) => editor.set(entry),
+                        None => editor.set(&unsubmitted_line),
+                    }
+                }
+                Key::Control(b'r') => {
+                    let Some((line, key)) = self.reverse_search(&mut stdin, &editor) else {
+                        return Line::End;
+                    };
+                    if let Some(line) = line {
+                        editor.set(&line);
+                    }
+                    if let Key::Enter = key {
+                        editor.render();
+                        break;
+                    }
+                }
+                Key::Control(b'i') => editor.complete(complete),
+                Key::Control(b'c') => {
+                    print!("^C\r\n");
+                    return Line::Interrupted;
+                }
+                _ => {}
+            }
+            editor.render();
+        }
+
+        print!("\r\n");
+        let line = editor.line();
+        self.history.add(&line);
+        Line::Text(line)
+    }
+
+    /// Runs a Ctrl-R reverse incremental search until a key other than a character, Backspace
+    /// or Ctrl-R is pressed. Returns the accepted entry, or `None` if the search was cancelled
+    /// with Escape or Ctrl-G, along with the key that ended the search.
+    fn reverse_search(
+        &self,
+        stdin: &mut impl Read,
+        editor: &LineEditor,
+    ) -> Option<(Option<String>, Key)> {
+        let mut query = String::new();
+        let mut found = None;
+        loop {
+            let found_line = found.map_or("", |index: usize| &self.history.entries[index]);
+            editor.render_search(&query, found_line);
+            match read_key(stdin)? {
+                Key::Char(char) => {
+                    query.push(char);
+                    found = self.history.search(
+                        &query,
+                        found.map_or(self.history.entries.len(), |index| index + 1),
+                    );
+                }
+                Key::Backspace => {
+                    query.pop();
+                    found = self.history.search(&query, self.history.entries.len());
+                }
+                Key::Control(b'r') => {
+                    if let Some(index) = found {
+                        found = self.history.search(&query, index).or(found);
+                    }
+                }
+                key @ (Key::Escape | Key::Control(b'g')) => return Some((None, key)),
+                key => return Some((found.map(|index| self.history.entries[index].clone()), key)),
+            }
+        }
+    }
+}
 
-pub struct LineReader {}
 impl Iterator for LineReader {
     type Item = String;
 
+    /// Reads a single line, without completion. A line cancelled with Ctrl-C is returned as an
+    /// empty line.
     fn next(&mut self) -> Option<Self::Item> {
-        print!("tray> ");
-        std::io::stdout().flush().ok()?;
-        let mut buffer = String::new();
-        std::io::stdin().read_line(&mut buffer).ok()?;
-        buffer = buffer.trim_end_matches(['\r', '\n']).to_string();
-        return Some(buffer);
+        match self.read_line(&|_| vec![]) {
+            Line::Text(line) => Some(line),
+            Line::Interrupted => Some(String::new()),
+            Line::End => None,
+        }
     }
 }
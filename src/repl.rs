@@ -0,0 +1,256 @@
+use std::time::Instant;
+
+use tray::{
+    checker,
+    diagnostic::{Diagnostic, ErrorFormat},
+    executer::Value,
+    lexer,
+    parser::{self, Statement},
+    Engine, Interpreter, InterruptHandle,
+};
+
+/// The file name diagnostics give for the input of the session.
+const REPL_NAME: &str = "<repl>";
+
+const HELP: &str = "\
+Commands:
+  :tokens <input>   Print the tokens of <input>
+  :ast <input>      Print the parse tree of <input>
+  :type <expr>      Print the type of <expr> without executing it
+  :time <input>     Execute <input> and print how long it took
+  :load <file>      Execute a file in the current session
+  :reset            Forget every variable and the result history
+  :help             Print this list";
+
+/// The state of an interactive session, kept from one input to the next.
+///
+/// Every expression result is bound to `_` and to a numbered history variable (`_1`, `_2`, ...)
+/// so it can be reused in later inputs. Inputs starting with `:` are commands, see `:help`.
+pub struct ReplSession {
+    interpreter: Interpreter,
+    history_length: usize,
+}
+
+impl ReplSession {
+    /// Starts a session executing its inputs with `engine`, whose integer overflows produce a
+    /// `bigint` when `promote_overflow` is enabled.
+    pub fn new(engine: Engine, promote_overflow: bool) -> Self {
+        let mut interpreter = Interpreter::new();
+        interpreter.set_engine(engine);
+        interpreter.set_promote_overflow(promote_overflow);
+        ReplSession {
+            interpreter,
+            history_length: 0,
+        }
+    }
+
+    /// Returns a handle which interrupts the input being executed.
+    pub fn interrupt_handle(&self) -> InterruptHandle {
+        self.interpreter.interrupt_handle()
+    }
+
+    /// Runs one command or lexes, parses and executes one input, printing its results and
+    /// errors. Returns whether it succeeded.
+    pub fn eval(&mut self, input: String) -> bool {
+        if let Some(command) = input.trim_start().strip_prefix(':') {
+            let (name, argument) = command
+                .split_once(char::is_whitespace)
+                .unwrap_or((command, ""));
+            self.command(name, argument.trim().to_string())
+        } else {
+            self.run(REPL_NAME, input, true)
+        }
+    }
+
+    fn command(&mut self, name: &str, argument: String) -> bool {
+        match name {
+            "tokens" => {
+                let lexed = match tray::lex(&argument) {
+                    Ok(lexed) => lexed,
+                    Err(diagnostics) => return report(&diagnostics, REPL_NAME, &argument),
+                };
+                for token in lexed.tokens {
+                    println!("{token}");
+                }
+            }
+            "ast" => match tray::parse(&argument) {
+                Ok(statements) => println!("{statements:#?}"),
+                Err(diagnostics) => return report(&diagnostics, REPL_NAME, &argument),
+            },
+            "type" => {
+                let statements = match tray::parse(&argument) {
+                    Ok(statements) => statements,
+                    Err(diagnostics) => return report(&diagnostics, REPL_NAME, &argument),
+                };
+                for statement in statements {
+                    // Variables declared with `let` or `var` have the type of their value.
+                    let expression = match statement {
+                        Statement::Declaration {
+                            declared_type: Some(declared_type),
+                            ..
+                        } => {
+                            println!("{declared_type}");
+                            continue;
+                        }
+                        Statement::Expression(expression) => expression,
+                        Statement::Declaration { value, .. }
+                        | Statement::Assignment { value, .. }
+                        | Statement::IndexAssignment { value, .. } => value,
+                        // Loops have no value.
+                        Statement::For { .. } => continue,
+                    };
+                    match checker::static_type(&expression, self.interpreter.environment()) {
+                        Ok(expression_type) => println!("{expression_type}"),
+                        Err(err) => {
+                            err.to_diagnostic()
+                                .emit(ErrorFormat::Human, REPL_NAME, &argument);
+                            return false;
+                        }
+                    }
+                }
+            }
+            "time" => {
+                let start = Instant::now();
+                let succeeded = self.run(REPL_NAME, argument, true);
+                println!("Executed in {:?}", start.elapsed());
+                return succeeded;
+            }
+            "load" => match std::fs::read_to_string(&argument) {
+                Ok(content) => {
+                    if !self.run(&argument, content, false) {
+                        return false;
+                    }
+                    println!("Loaded `{argument}`");
+                }
+                Err(err) => {
+                    eprintln!("Couldn't read file `{argument}`: {err}");
+                    return false;
+                }
+            },
+            "reset" => {
+                // The session keeps its settings and the handle the signal handler interrupts.
+                self.interpreter.clear_globals();
+                self.history_length = 0;
+            }
+            "help" => println!("{HELP}"),
+            _ => {
+                eprintln!("Unknown command `:{name}`. Type `:help` for a list of commands.");
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Type checks every statement of `input`, then executes them if there is no error,
+    /// stopping at the first runtime error. Errors are reported in the file `name`, which is
+    /// [`REPL_NAME`] unless the input was loaded from a file. Returns whether all of them
+    /// succeeded.
+    ///
+    /// When `record` is enabled, the values of the expressions are printed as they come, and
+    /// bound to the history variables once the input is done, since the input was checked
+    /// without them.
+    fn run(&mut self, name: &str, input: String, record: bool) -> bool {
+        let statements = match self.interpreter.compile(&input) {
+            Ok(statements) => statements,
+            Err(diagnostics) => return report(&diagnostics, name, &input),
+        };
+        self.interpreter.start_run();
+        let mut values = vec![];
+        let result = self.interpreter.execute_all(&statements, |value| {
+            if record {
+                println!("{value} : {}", value.value_type());
+                values.push(value);
+            }
+        });
+        for value in values {
+            self.record(value);
+        }
+        if let Err(err) = result {
+            let failed = self.interpreter.failed_statement();
+            crate::runtime_diagnostic(&err, failed, &input).emit(ErrorFormat::Human, name, &input);
+            return false;
+        }
+        true
+    }
+
+    /// Returns the names that can complete the identifier ending `line`, the input before the
+    /// cursor, sorted.
+    ///
+    /// After a `.`, these are the members of the value before it, whose type is found without
+    /// executing it. After a `::`, they are the functions and constants of the class before it.
+    /// Otherwise they are the keywords, type names and variables, taken from the session's
+    /// environment and the lists the lexer and parser match against, so that only names that
+    /// would resolve are suggested.
+    pub fn complete(&self, line: &str) -> Vec<String> {
+        let (before, word) = line.split_at(
+            line.trim_end_matches(|c: char| c.is_alphanumeric() || c == '_')
+                .len(),
+        );
+        let names: Vec<&str> = if let Some(before) = before.strip_suffix("::") {
+            let class = &before[before
+                .trim_end_matches(|c: char| c.is_alphanumeric() || c == '_')
+                .len()..];
+            self.interpreter.environment().static_member_names(class)
+        } else if let Some(before) = before.strip_suffix('.') {
+            match tray::parse(member_object(before)).as_deref() {
+                Ok([Statement::Expression(object)]) => {
+                    let environment = self.interpreter.environment();
+                    checker::static_type(object, environment)
+                        .map_or(vec![], |object_type| environment.member_names(&object_type))
+                }
+                _ => vec![],
+            }
+        } else {
+            lexer::KEYWORDS
+                .iter()
+                .chain(parser::TYPE_NAMES)
+                .chain(parser::GENERIC_TYPES.iter().map(|(name, _)| name))
+                .copied()
+                .chain(self.interpreter.environment().names())
+                .collect()
+        };
+        let mut names: Vec<String> = names
+            .into_iter()
+            .filter(|name| name.starts_with(word))
+            .map(String::from)
+            .collect();
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    /// Binds `value` to `_` and to the next history variable.
+    fn record(&mut self, value: Value) {
+        self.history_length += 1;
+        self.interpreter.set_global("_", value.clone());
+        self.interpreter
+            .set_global(&format!("_{}", self.history_length), value);
+    }
+}
+
+/// Prints `diagnostics` about `input`, read from the file `name`, and returns `false` for the
+/// failure.
+fn report(diagnostics: &[Diagnostic], name: &str, input: &str) -> bool {
+    for diagnostic in diagnostics {
+        diagnostic.emit(ErrorFormat::Human, name, input);
+    }
+    false
+}
+
+/// Returns the expression `line` ends with whose members are being completed: a chain of
+/// names, members, calls and indexes like `values.map(x => x * 2)[0]`.
+fn member_object(line: &str) -> &str {
+    let mut depth = 0;
+    let mut start = line.len();
+    for (index, c) in line.char_indices().rev() {
+        match c {
+            ')' | ']' => depth += 1,
+            '(' | '[' if depth > 0 => depth -= 1,
+            _ if depth > 0 => {}
+            c if c.is_alphanumeric() || matches!(c, '_' | '.' | ':' | '"') => {}
+            _ => break,
+        }
+        start = index;
+    }
+    &line[start..]
+}
@@ -0,0 +1,330 @@
+use std::{
+    cell::Cell,
+    fmt::Display,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use crate::{
+    error_codes::{self, ErrorCode},
+    executer::{RuntimeError, Value},
+    parser,
+};
+
+/// How deeply expressions and native calls can nest when no other limit is set, which is as
+/// deeply as the parser lets expressions nest by default.
+pub const DEFAULT_MAX_CALL_DEPTH: usize = parser::DEFAULT_MAX_NESTING;
+
+/// Something native functions can require to be enabled before scripts can call them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Capability {
+    Filesystem,
+    Process,
+    Environment,
+}
+
+impl Display for Capability {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Capability::Filesystem => f.write_str("file system access"),
+            Capability::Process => f.write_str("process access"),
+            Capability::Environment => f.write_str("environment variable access"),
+        }
+    }
+}
+
+/// The capabilities scripts are allowed to use, all of them by default.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Capabilities {
+    pub filesystem: bool,
+    pub process: bool,
+    pub environment: bool,
+}
+
+impl Default for Capabilities {
+    fn default() -> Self {
+        Capabilities {
+            filesystem: true,
+            process: true,
+            environment: true,
+        }
+    }
+}
+
+impl Capabilities {
+    /// Allows none of the capabilities, for running untrusted scripts.
+    pub fn none() -> Self {
+        Capabilities {
+            filesystem: false,
+            process: false,
+            environment: false,
+        }
+    }
+
+    pub fn allows(&self, capability: Capability) -> bool {
+        match capability {
+            Capability::Filesystem => self.filesystem,
+            Capability::Process => self.process,
+            Capability::Environment => self.environment,
+        }
+    }
+}
+
+/// Which limit a [`RuntimeError::LimitExceeded`] hit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Limit {
+    Fuel,
+    Timeout,
+    CallDepth,
+    HeapBytes,
+    StringLength,
+    Capability(Capability),
+}
+
+/// Bounds on the resources scripts can use. Only the call depth is limited by default.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Limits {
+    /// How many steps each run can take: expressions evaluated by the tree-walker, or
+    /// instructions executed by the virtual machine.
+    pub fuel: Option<u64>,
+    /// How long each run can take.
+    pub timeout: Option<Duration>,
+    /// How deeply expressions can nest when they run. The tree-walker recurses into them, and the
+    /// virtual machine, which doesn't, checks the depths the compiler recorded in the
+    /// [`Chunk`](crate::bytecode::Chunk) so that it fails on the same programs.
+    pub max_call_depth: usize,
+    /// How deeply expressions, blocks and types can nest in the source, checked when it is
+    /// parsed. This bounds the recursion of the parser and of everything walking the statements
+    /// it returns, so raising it lets deeper sources overflow their stack. The operands of a
+    /// chain of binary operators like `1 + 2 + 3` aren't nested in each other.
+    pub max_nesting: usize,
+    /// How many bytes the values held by variables, along with the value being computed, can
+    /// take on the heap. This is an estimate which doesn't count the memory of the interpreter
+    /// itself.
+    pub max_heap_bytes: Option<usize>,
+    /// How many bytes of UTF-8 a string can take.
+    pub max_string_length: Option<usize>,
+    pub capabilities: Capabilities,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            fuel: None,
+            timeout: None,
+            max_call_depth: DEFAULT_MAX_CALL_DEPTH,
+            max_nesting: parser::DEFAULT_MAX_NESTING,
+            max_heap_bytes: None,
+            max_string_length: None,
+            capabilities: Capabilities::default(),
+        }
+    }
+}
+
+impl Limits {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_fuel(mut self, fuel: u64) -> Self {
+        self.fuel = Some(fuel);
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub fn with_max_call_depth(mut self, depth: usize) -> Self {
+        self.max_call_depth = depth;
+        self
+    }
+
+    pub fn with_max_nesting(mut self, nesting: usize) -> Self {
+        self.max_nesting = nesting;
+        self
+    }
+
+    pub fn with_max_heap_bytes(mut self, bytes: usize) -> Self {
+        self.max_heap_bytes = Some(bytes);
+        self
+    }
+
+    pub fn with_max_string_length(mut self, length: usize) -> Self {
+        self.max_string_length = Some(length);
+        self
+    }
+
+    pub fn with_capabilities(mut self, capabilities: Capabilities) -> Self {
+        self.capabilities = capabilities;
+        self
+    }
+}
+
+fn limit_exceeded(limit: Limit, code: &'static ErrorCode, message: String) -> RuntimeError {
+    RuntimeError::LimitExceeded {
+        code,
+        limit,
+        message,
+    }
+}
+
+/// Stops the run of the sandbox it was taken from with [`RuntimeError::Interrupted`]. It can be
+/// sent to other threads, and [`InterruptHandle::interrupt`] only stores to an atomic, so it can
+/// be called from a signal handler.
+#[derive(Debug, Clone, Default)]
+pub struct InterruptHandle(Arc<AtomicBool>);
+
+impl InterruptHandle {
+    /// Makes the current run stop. Interrupts happening while nothing runs are forgotten when
+    /// the next run starts.
+    pub fn interrupt(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    fn clear(&self) {
+        self.0.store(false, Ordering::Relaxed);
+    }
+
+    fn is_interrupted(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// The [`Limits`] of an environment and what the current run has used of them.
+#[derive(Debug, Clone, Default)]
+pub struct Sandbox {
+    pub limits: Limits,
+    fuel_used: Cell<u64>,
+    depth: Cell<usize>,
+    deadline: Cell<Option<Instant>>,
+    interrupt: InterruptHandle,
+}
+
+impl Sandbox {
+    /// Starts a run, giving back all of its fuel and time and forgetting earlier interrupts.
+    pub fn start_run(&self) {
+        self.fuel_used.set(0);
+        self.depth.set(0);
+        self.deadline
+            .set(self.limits.timeout.map(|timeout| Instant::now() + timeout));
+        self.interrupt.clear();
+    }
+
+    /// Returns a handle which interrupts the runs of this sandbox.
+    pub fn interrupt_handle(&self) -> InterruptHandle {
+        self.interrupt.clone()
+    }
+
+    /// Makes the runs of this sandbox stop when `interrupt` is interrupted, instead of its own
+    /// handle.
+    pub fn set_interrupt_handle(&mut self, interrupt: InterruptHandle) {
+        self.interrupt = interrupt;
+    }
+
+    /// Accounts for evaluating one more expression, nested in the ones being evaluated. Each
+    /// successful call must be followed by [`Sandbox::leave`].
+    pub fn enter(&self) -> Result<(), RuntimeError> {
+        self.check_depth(self.depth.get() + 1)?;
+        self.consume_fuel()?;
+        self.depth.set(self.depth.get() + 1);
+        Ok(())
+    }
+
+    /// Checks that an expression nested `depth` levels deep is allowed to run.
+    pub fn check_depth(&self, depth: usize) -> Result<(), RuntimeError> {
+        if depth > self.limits.max_call_depth {
+            return Err(limit_exceeded(
+                Limit::CallDepth,
+                &error_codes::CALL_DEPTH_EXCEEDED,
+                format!(
+                    "Expressions are nested more than {} levels deep.",
+                    self.limits.max_call_depth
+                ),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Accounts for one more step of the run, failing if it is out of fuel or time or was
+    /// interrupted.
+    pub fn consume_fuel(&self) -> Result<(), RuntimeError> {
+        if self.interrupt.is_interrupted() {
+            return Err(RuntimeError::Interrupted);
+        }
+        if let Some(fuel) = self.limits.fuel {
+            if self.fuel_used.get() >= fuel {
+                return Err(limit_exceeded(
+                    Limit::Fuel,
+                    &error_codes::OUT_OF_FUEL,
+                    format!("The script took more than {fuel} steps."),
+                ));
+            }
+        }
+        if let (Some(deadline), Some(timeout)) = (self.deadline.get(), self.limits.timeout) {
+            if Instant::now() >= deadline {
+                return Err(limit_exceeded(
+                    Limit::Timeout,
+                    &error_codes::TIMED_OUT,
+                    format!("The script ran for more than {timeout:?}."),
+                ));
+            }
+        }
+        self.fuel_used.set(self.fuel_used.get() + 1);
+        Ok(())
+    }
+
+    pub fn leave(&self) {
+        self.depth.set(self.depth.get() - 1);
+    }
+
+    /// Checks that `value` fits the string and heap limits, `heap_bytes` being the size of the
+    /// values held by variables.
+    pub fn check_value(&self, value: &Value, heap_bytes: usize) -> Result<(), RuntimeError> {
+        if let (Value::String(string), Some(max)) = (value, self.limits.max_string_length) {
+            if string.len() > max {
+                return Err(limit_exceeded(
+                    Limit::StringLength,
+                    &error_codes::STRING_TOO_LONG,
+                    format!(
+                        "A string of {} bytes is longer than the limit of {max} bytes.",
+                        string.len()
+                    ),
+                ));
+            }
+        }
+        if let Some(max) = self.limits.max_heap_bytes {
+            let total = heap_bytes + value.heap_size();
+            if total > max {
+                return Err(limit_exceeded(
+                    Limit::HeapBytes,
+                    &error_codes::OUT_OF_MEMORY,
+                    format!("The script would use {total} bytes, more than the limit of {max}."),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks that scripts are allowed to call `function`, which requires `capability`.
+    pub fn check_capability(
+        &self,
+        capability: Option<Capability>,
+        function: &str,
+    ) -> Result<(), RuntimeError> {
+        match capability {
+            Some(capability) if !self.limits.capabilities.allows(capability) => {
+                Err(limit_exceeded(
+                    Limit::Capability(capability),
+                    &error_codes::CAPABILITY_DISABLED,
+                    format!("`{function}` requires {capability}, which is disabled."),
+                ))
+            }
+            _ => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,565 @@
+use crate::{
+    array,
+    bytecode::{Chunk, Instruction},
+    executer::{self, Environment, RuntimeError, Value},
+    native::NativeFunction,
+    parser::Type,
+};
+
+/// Runs `chunk` in `environment`, calling `on_value` with the value of each expression
+/// statement.
+///
+/// Variables live in slots while the chunk runs. They are read from `environment` the first
+/// time they are used, and the ones the chunk declares or assigns are written back to it when it
+/// ends, even if it fails, so that they stay visible to the code run after this. The variables
+/// of loops are put back as they were before them though, like when the loops end.
+pub fn run(
+    chunk: &Chunk,
+    environment: &mut Environment,
+    on_value: impl FnMut(Value),
+) -> Result<(), RuntimeError> {
+    let mut vm = Vm {
+        chunk,
+        environment,
+        stack: Vec::new(),
+        slots: vec![None; chunk.slots.len()],
+        changed_bytes: 0,
+        shadowed_bytes: 0,
+        loops: Vec::new(),
+        next: 0,
+        on_value,
+    };
+    vm.environment.failed_statement = None;
+    let mut result = Ok(());
+    while let Some(instruction) = chunk.code.get(vm.next) {
+        let index = vm.next;
+        vm.next += 1;
+        if let Err(err) = vm.step(instruction, chunk.depths[index]) {
+            let statement = chunk.statement_of[index] as usize;
+            vm.environment.failed_statement = Some(statement);
+            result = Err(err.in_statement(|| chunk.statements[statement].clone()));
+            break;
+        }
+    }
+    while !vm.loops.is_empty() {
+        vm.end_loop();
+    }
+    let Vm {
+        environment, slots, ..
+    } = vm;
+    for (slot, local) in slots.into_iter().enumerate() {
+        if let Some(local) = local.filter(|local| local.counted.is_some()) {
+            let name = &chunk.slots[slot];
+            environment.declare_variable(name, local.declared_type, local.value, local.mutable);
+        }
+    }
+    result
+}
+
+/// Calls `function` with `values`, which start with the object for methods and fields,
+/// followed by the `arguments` the program gave.
+fn call(
+    environment: &Environment,
+    function: &NativeFunction,
+    arguments: u32,
+    values: Vec<Value>,
+    chunk: &Chunk,
+    site: u32,
+) -> Result<Value, RuntimeError> {
+    environment
+        .sandbox
+        .check_capability(function.capability, &function.name)?;
+    if arguments as usize != function.parameters.len() {
+        return Err(function.wrong_argument_count(arguments as usize));
+    }
+    executer::call_with_trace(function, values, || chunk.sites[site as usize].clone())
+}
+
+/// A variable held by a slot.
+#[derive(Clone)]
+struct Local {
+    declared_type: Type,
+    value: Value,
+    mutable: bool,
+    /// For variables the chunk declared or assigned, which are written back to the environment
+    /// when it ends, the heap size of their value and of the variable of the environment they
+    /// replace. `None` for variables only read from the environment.
+    counted: Option<(usize, usize)>,
+}
+
+/// A loop over the elements of a `Vec`, from a `for` loop, `map` or `filter`.
+struct Loop {
+    /// The elements the `Vec` had when the loop started.
+    elements: Vec<Value>,
+    element_type: Type,
+    /// The index of the next element.
+    position: usize,
+    /// The element the body is run for.
+    element: Option<Value>,
+    results: Vec<Value>,
+    /// The variables of the slots of the loop before it started.
+    saved: Vec<(u32, Option<Local>)>,
+}
+
+struct Vm<'a, F> {
+    chunk: &'a Chunk,
+    environment: &'a mut Environment,
+    stack: Vec<Value>,
+    /// The variables used by the program, or `None` if they have to be read from the
+    /// environment.
+    slots: Vec<Option<Local>>,
+    /// The heap size of the variables the chunk declared or assigned, and of the ones of the
+    /// environment they replace, see [`Vm::heap_bytes`].
+    changed_bytes: usize,
+    shadowed_bytes: usize,
+    /// The loops being run, innermost last.
+    loops: Vec<Loop>,
+    /// The index of the next instruction.
+    next: usize,
+    on_value: F,
+}
+
+impl<F: FnMut(Value)> Vm<'_, F> {
+    fn pop(&mut self) -> Value {
+        self.stack
+            .pop()
+            .expect("The compiler should balance the stack.")
+    }
+
+    /// Pops the `count` values on top of the stack, in the order they were pushed.
+    fn pop_many(&mut self, count: u32) -> Vec<Value> {
+        let start = self.stack.len() - count as usize;
+        self.stack.split_off(start)
+    }
+
+    fn innermost_loop(&mut self) -> &mut Loop {
+        self.loops
+            .last_mut()
+            .expect("The compiler should only use loops inside of them.")
+    }
+
+    /// Ends the innermost loop, putting its variables back as they were before it.
+    fn end_loop(&mut self) -> Loop {
+        let mut ended = self
+            .loops
+            .pop()
+            .expect("The compiler should only end loops it started.");
+        for (slot, local) in std::mem::take(&mut ended.saved) {
+            self.set_local(slot, local);
+        }
+        ended
+    }
+
+    /// Returns the variable in `slot`, reading it from the environment if it isn't there yet.
+    fn local(&mut self, slot: u32) -> Result<&mut Local, RuntimeError> {
+        let slot = slot as usize;
+        if self.slots[slot].is_none() {
+            let name = &self.chunk.slots[slot];
+            let (declared_type, value, mutable) = self
+                .environment
+                .get_variable(name)
+                .ok_or_else(|| executer::undefined_variable(name))?;
+            self.slots[slot] = Some(Local {
+                declared_type: declared_type.clone(),
+                value: value.clone(),
+                mutable,
+                counted: None,
+            });
+        }
+        Ok(self.slots[slot]
+            .as_mut()
+            .expect("The slot was just filled."))
+    }
+
+    /// Replaces the variable in `slot` by `local`, keeping count of the heap size of the
+    /// variables the chunk changed.
+    fn set_local(&mut self, slot: u32, local: Option<Local>) {
+        let slot = &mut self.slots[slot as usize];
+        if let Some((bytes, shadowed)) = slot.as_ref().and_then(|local| local.counted) {
+            self.changed_bytes -= bytes;
+            self.shadowed_bytes -= shadowed;
+        }
+        if let Some((bytes, shadowed)) = local.as_ref().and_then(|local| local.counted) {
+            self.changed_bytes += bytes;
+            self.shadowed_bytes += shadowed;
+        }
+        *slot = local;
+    }
+
+    /// Declares or assigns the variable in `slot`, which is written back to the environment
+    /// when the chunk ends.
+    fn set_variable(&mut self, slot: u32, declared_type: Type, value: Value, mutable: bool) {
+        // The variable of the environment is replaced by the first change.
+        let shadowed = match self.slots[slot as usize]
+            .as_ref()
+            .and_then(|local| local.counted)
+        {
+            Some((_, shadowed)) => shadowed,
+            None => self
+                .environment
+                .get(&self.chunk.slots[slot as usize])
+                .map_or(0, Value::heap_size),
+        };
+        let counted = Some((value.heap_size(), shadowed));
+        let local = Local {
+            declared_type,
+            value,
+            mutable,
+            counted,
+        };
+        self.set_local(slot, Some(local));
+    }
+
+    /// Returns the sum of the heap size of the variables, like [`Environment::heap_bytes`]
+    /// would if the variables of the slots were written back to it.
+    fn heap_bytes(&self) -> usize {
+        (self.environment.heap_bytes() + self.changed_bytes).saturating_sub(self.shadowed_bytes)
+    }
+
+    /// Pushes `value`, failing if it goes over the limits of the sandbox.
+    fn push(&mut self, value: Value) -> Result<(), RuntimeError> {
+        self.environment
+            .sandbox
+            .check_value(&value, self.heap_bytes())?;
+        self.stack.push(value);
+        Ok(())
+    }
+
+    /// Runs `instruction`, which the tree-walker would run nested `depth` expressions deep.
+    fn step(&mut self, instruction: &Instruction, depth: u32) -> Result<(), RuntimeError> {
+        let chunk = self.chunk;
+        self.environment.sandbox.check_depth(depth as usize)?;
+        self.environment.sandbox.consume_fuel()?;
+        match instruction {
+            Instruction::Constant(index) => self.push(chunk.constants[*index as usize].clone())?,
+            Instruction::Load(slot) => {
+                let value = self.local(*slot)?.value.clone();
+                self.push(value)?;
+            }
+            Instruction::Declare {
+                slot,
+                declared_type,
+                mutable,
+            } => {
+                let name = &chunk.slots[*slot as usize];
+                let value = self.pop();
+                let (declared_type, value) = match declared_type {
+                    Some(declared_type) => {
+                        let declared_type = &chunk.types[*declared_type as usize];
+                        (
+                            declared_type.clone(),
+                            executer::convert(value, declared_type, name)?,
+                        )
+                    }
+                    None => (value.value_type(), value),
+                };
+                self.set_variable(*slot, declared_type, value, *mutable);
+            }
+            Instruction::Store(slot) => {
+                let value = self.pop();
+                let name = &chunk.slots[*slot as usize];
+                let local = self.local(*slot)?;
+                if !local.mutable {
+                    return Err(executer::assigned_immutable(name));
+                }
+                let value = executer::convert(value, &local.declared_type, name)?;
+                if let Some((bytes, shadowed)) = local.counted {
+                    // The variable was already changed, so only its value is replaced.
+                    let new_bytes = value.heap_size();
+                    local.value = value;
+                    local.counted = Some((new_bytes, shadowed));
+                    self.changed_bytes = self.changed_bytes - bytes + new_bytes;
+                } else {
+                    let declared_type = local.declared_type.clone();
+                    self.set_variable(*slot, declared_type, value, true);
+                }
+            }
+            Instruction::Plus => {
+                let value = self.pop();
+                self.push(executer::unary_plus(value)?)?;
+            }
+            Instruction::Negate => {
+                let value = self.pop();
+                self.push(executer::unary_minus(
+                    value,
+                    self.environment.promote_overflow,
+                )?)?;
+            }
+            Instruction::Binary(operator) => {
+                let right = self.pop();
+                let left = self.pop();
+                self.push(executer::binary(
+                    operator,
+                    &left,
+                    &right,
+                    self.environment.promote_overflow,
+                )?)?;
+            }
+            Instruction::Cast(target) => {
+                let value = self.pop();
+                self.push(executer::cast(value, *target)?)?;
+            }
+            Instruction::Call {
+                function,
+                arguments,
+                site,
+            } => {
+                let values = self.pop_many(*arguments);
+                let name = &chunk.names[*function as usize];
+                let function = self
+                    .environment
+                    .get_function(name)
+                    .ok_or_else(|| executer::undefined_function(name))?;
+                let result = call(self.environment, function, *arguments, values, chunk, *site)?;
+                self.push(result)?;
+            }
+            Instruction::CallMethod {
+                method,
+                arguments,
+                site,
+            } => {
+                let mut values = self.pop_many(*arguments);
+                values.insert(0, self.pop());
+                if let Value::Vec(array) = &values[0] {
+                    let array = array.clone();
+                    let method = &chunk.names[*method as usize];
+                    values.remove(0);
+                    self.push(array.call_method(method, values)?)?;
+                    return Ok(());
+                }
+                let method = executer::get_member(
+                    self.environment,
+                    &values[0].value_type(),
+                    &chunk.names[*method as usize],
+                    "method",
+                    |class| &class.methods,
+                )?;
+                let result = call(self.environment, method, *arguments, values, chunk, *site)?;
+                self.push(result)?;
+            }
+            Instruction::GetField { field, site } => {
+                let object = self.pop();
+                let getter = executer::get_member(
+                    self.environment,
+                    &object.value_type(),
+                    &chunk.names[*field as usize],
+                    "field",
+                    |class| &class.fields,
+                )?;
+                let result = call(self.environment, getter, 0, vec![object], chunk, *site)?;
+                self.push(result)?;
+            }
+            Instruction::GetConstant { class, member } => {
+                let value = executer::get_constant(
+                    self.environment,
+                    &chunk.names[*class as usize],
+                    &chunk.names[*member as usize],
+                )?;
+                self.push(value.clone())?;
+            }
+            Instruction::CallStatic {
+                class,
+                function,
+                arguments,
+                site,
+            } => {
+                let values = self.pop_many(*arguments);
+                let function = executer::get_static_function(
+                    self.environment,
+                    &chunk.names[*class as usize],
+                    &chunk.names[*function as usize],
+                )?;
+                let result = call(self.environment, function, *arguments, values, chunk, *site)?;
+                self.push(result)?;
+            }
+            Instruction::Yield => {
+                let value = self.pop();
+                (self.on_value)(value);
+            }
+            Instruction::MakeVec {
+                count,
+                element_type,
+            } => {
+                let values = self.pop_many(*count);
+                let element_type = element_type.map(|index| &chunk.types[index as usize]);
+                self.push(executer::vec_literal(element_type, values)?)?;
+            }
+            Instruction::Index => {
+                let index = self.pop();
+                let object = self.pop();
+                self.push(executer::index(&object, &index)?)?;
+            }
+            Instruction::StoreIndex => {
+                let value = self.pop();
+                let index = self.pop();
+                let object = self.pop();
+                executer::store_index(&object, &index, value)?;
+            }
+            Instruction::Iterate { scope } => {
+                let array = executer::iterate(self.pop())?;
+                let saved = chunk.scopes[*scope as usize]
+                    .iter()
+                    .map(|slot| (*slot, self.slots[*slot as usize].clone()))
+                    .collect();
+                self.loops.push(Loop {
+                    elements: array.to_vec(),
+                    element_type: array.element_type().clone(),
+                    position: 0,
+                    element: None,
+                    results: Vec::new(),
+                    saved,
+                });
+            }
+            Instruction::Next { slot, end } => {
+                let current = self.innermost_loop();
+                let Some(element) = current.elements.get(current.position).cloned() else {
+                    self.next = *end as usize;
+                    return Ok(());
+                };
+                current.position += 1;
+                current.element = Some(element.clone());
+                let element_type = current.element_type.clone();
+                self.set_variable(*slot, element_type, element, false);
+            }
+            Instruction::Jump(target) => self.next = *target as usize,
+            Instruction::Append => {
+                let value = self.pop();
+                self.innermost_loop().results.push(value);
+            }
+            Instruction::AppendIf => {
+                let kept = array::filter_result(self.pop())?;
+                let current = self.innermost_loop();
+                if let (true, Some(element)) = (kept, current.element.clone()) {
+                    current.results.push(element);
+                }
+            }
+            Instruction::EndLoop => {
+                self.end_loop();
+            }
+            Instruction::Collect { element_type } => {
+                let ended = self.end_loop();
+                let element_type = match element_type {
+                    Some(index) => &chunk.types[*index as usize],
+                    None => &ended.element_type,
+                };
+                self.push(executer::vec_literal(Some(element_type), ended.results)?)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Engine, Interpreter, Limit, Limits, RuntimeError};
+
+    /// Runs `source` with `engine`, returning the values of its expressions followed by the
+    /// error it failed with and the statement it failed in, if it failed.
+    fn run(engine: Engine, source: &str, limits: Limits) -> (Vec<String>, Option<String>) {
+        let mut interpreter = Interpreter::new();
+        interpreter.set_engine(engine);
+        interpreter.set_limits(limits);
+        let statements = interpreter.compile(source).unwrap();
+        let mut values = vec![];
+        let result = interpreter.execute_all(&statements, |value| values.push(value.to_string()));
+        let error = result
+            .err()
+            .map(|err| format!("{err} in {:?}", interpreter.failed_statement()));
+        (values, error)
+    }
+
+    /// Checks that the virtual machine runs `source` like the tree-walker, and returns what it
+    /// gives.
+    fn run_both(source: &str, limits: Limits) -> (Vec<String>, Option<String>) {
+        let vm = run(Engine::Vm, source, limits.clone());
+        assert_eq!(vm, run(Engine::Tree, source, limits), "{source}");
+        vm
+    }
+
+    #[test]
+    fn runs_like_the_tree_walker() {
+        let (values, error) = run_both(
+            "var total = 0;
+            var evens = [1, 2, 3, 4].filter(x => [2, 4].contains(x));
+            for x in [1, 2, 3] {
+                total = total + x;
+                for y in evens {
+                    x * y;
+                }
+            }
+            total; evens.map(x => x as f64 / 4f64); -(2 + 3) * 4; [\"a\", \"b\"].join(\"-\")",
+            Limits::new(),
+        );
+        assert_eq!(
+            values,
+            [
+                "2",
+                "4",
+                "4",
+                "8",
+                "6",
+                "12",
+                "6",
+                "[0.5, 1.0]",
+                "-20",
+                "\"a-b\""
+            ]
+        );
+        assert_eq!(error, None);
+    }
+
+    #[test]
+    fn loop_variables_are_restored_after_the_loop() {
+        let (values, _) = run_both("var x = \"outer\"; for x in [1, 2] { x; } x", Limits::new());
+        assert_eq!(values, ["1", "2", "\"outer\""]);
+    }
+
+    #[test]
+    fn errors_point_at_the_statement_they_happened_in() {
+        let (values, error) = run_both(
+            "var v = [1, 2]; for x in v { x; for y in v { v[x + y]; } } 0",
+            Limits::new(),
+        );
+        assert_eq!(values, ["1"]);
+        // The statements are `var v`, the outer loop, `x;`, the inner loop and `v[x + y];`.
+        assert!(error.unwrap().ends_with("in Some(4)"));
+    }
+
+    #[test]
+    fn variables_are_kept_after_a_failure() {
+        let mut interpreter = Interpreter::new();
+        let statements = interpreter
+            .compile("var count = 1; var zero = 0; count = 2; 1 / zero")
+            .unwrap();
+        assert!(interpreter.execute_all(&statements, |_| {}).is_err());
+        assert_eq!(interpreter.get_global::<i32>("count").unwrap(), 2);
+    }
+
+    #[test]
+    fn stops_when_out_of_fuel() {
+        let source = "var total = 0; for x in [1, 2, 3, 4, 5, 6, 7, 8] { total = total + x; }";
+        let mut interpreter = Interpreter::new();
+        interpreter.set_limits(Limits::new().with_fuel(10));
+        let statements = interpreter.compile(source).unwrap();
+        let result = interpreter.execute_all(&statements, |_| {});
+        assert!(matches!(
+            result,
+            Err(RuntimeError::LimitExceeded {
+                limit: Limit::Fuel,
+                ..
+            })
+        ));
+        assert!(run(Engine::Vm, source, Limits::new().with_fuel(1000))
+            .1
+            .is_none());
+    }
+
+    #[test]
+    fn stops_when_interrupted() {
+        let mut interpreter = Interpreter::new();
+        let statements = interpreter.compile("1 + 1").unwrap();
+        interpreter.interrupt_handle().interrupt();
+        let result = interpreter.execute_all(&statements, |_| {});
+        assert!(matches!(result, Err(RuntimeError::Interrupted)));
+    }
+}
@@ -0,0 +1,41 @@
+use std::{
+    io::Write,
+    process::{Command, Stdio},
+};
+
+/// Runs the REPL with `input` piped to it, returning its exit status and output.
+fn run_piped(input: &str) -> (bool, String, String) {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_tray"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    (
+        output.status.success(),
+        String::from_utf8(output.stdout).unwrap(),
+        String::from_utf8(output.stderr).unwrap(),
+    )
+}
+
+#[test]
+fn piped_scripts_keep_blank_lines_in_blocks() {
+    let (success, stdout, stderr) =
+        run_piped("var s = 0;\nfor x in [1, 2] {\n\n    s = s + x;\n}\ns\n");
+    assert!(success, "{stderr}");
+    assert_eq!(stdout, "3 : i32\n");
+}
+
+#[test]
+fn piped_failures_set_the_exit_code() {
+    let (success, stdout, _) = run_piped("1 + 1\nundefined\n");
+    assert!(!success);
+    assert_eq!(stdout, "2 : i32\n");
+}